@@ -0,0 +1,296 @@
+use crate::index::VectorIndex;
+use crate::llm::{ChatBackend, ChatMessage, EmbeddingBackend, TokenSender, ToolCall, ToolSpec};
+use crate::web::{self, ChunkConfig, LlmCleanConfig, MdPage, QueryExpander, SearchHooks};
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::{Value, json};
+use std::fmt;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+
+pub const SYSTEM_PROMPT: &str = "You are agent-bob, a concise assistant for programmers. \
+    When a question depends on documentation, recent releases or anything you are unsure of, \
+    call web_search first and base the answer on the pages it returns, citing their URLs. \
+    Answer in markdown.";
+
+const WEB_SEARCH: &str = "web_search";
+const RECALL: &str = "recall";
+
+/// Passages `recall` returns unless the model asks for a number.
+const RECALL_DEFAULT_K: usize = 5;
+
+/// Pages from past searches, embedded so the model can look them up again.
+#[derive(Clone)]
+pub struct Memory {
+    pub index: Arc<Mutex<VectorIndex>>,
+    pub embedder: Arc<dyn EmbeddingBackend>,
+}
+
+impl fmt::Debug for Memory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Memory").finish_non_exhaustive()
+    }
+}
+
+/// Limits for one agent turn.
+#[derive(Debug, Clone)]
+pub struct AgentConfig {
+    /// Model calls per turn that may use tools; the next one must answer.
+    pub max_tool_rounds: usize,
+    /// Search hits crawled per query the model asks for.
+    pub results_per_query: u32,
+    /// Markdown characters of search results returned per tool call, split across pages.
+    pub max_result_chars: usize,
+    pub search: LlmCleanConfig,
+    /// Index searched pages here and offer the `recall` tool.
+    pub memory: Option<Memory>,
+    /// Progress reporting and cancellation for the searches the model runs.
+    pub hooks: SearchHooks,
+}
+
+impl Default for AgentConfig {
+    fn default() -> Self {
+        Self {
+            max_tool_rounds: 4,
+            results_per_query: 3,
+            max_result_chars: 24_000,
+            search: LlmCleanConfig::default(),
+            memory: None,
+            hooks: SearchHooks::default(),
+        }
+    }
+}
+
+/// Query variants written by a chat model, for `SearchHooks::expander`.
+pub struct LlmQueryExpander {
+    pub backend: Arc<dyn ChatBackend>,
+    /// Rewrites asked for per query.
+    pub variants: usize,
+}
+
+impl fmt::Debug for LlmQueryExpander {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LlmQueryExpander")
+            .field("variants", &self.variants)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl QueryExpander for LlmQueryExpander {
+    async fn expand(&self, query: &str) -> Result<Vec<String>> {
+        let prompt = format!(
+            "Rewrite this web search query {} different ways to find more relevant pages: \
+             other wording, the technical term for it, or a narrower phrasing. \
+             Reply with one query per line and nothing else.\n\nQuery: {query}",
+            self.variants
+        );
+        let reply = self
+            .backend
+            .chat(&[ChatMessage::user(prompt)], &[], None)
+            .await?;
+        // Models number or bullet their lines despite being asked not to.
+        Ok(reply
+            .content
+            .lines()
+            .map(|line| {
+                line.trim()
+                    .trim_start_matches(|c: char| {
+                        c.is_ascii_digit() || matches!(c, '.' | ')' | '-' | '*' | ' ')
+                    })
+                    .trim_matches('"')
+                    .to_string()
+            })
+            .filter(|line| !line.is_empty())
+            .take(self.variants)
+            .collect())
+    }
+}
+
+fn web_search_tool() -> ToolSpec {
+    ToolSpec {
+        name: WEB_SEARCH.to_string(),
+        description: "Search the web and return the top pages as cleaned markdown, each with \
+                      its URL and title in front matter."
+            .to_string(),
+        parameters: json!({
+            "type": "object",
+            "properties": {
+                "queries": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "One or more search queries, searched together."
+                }
+            },
+            "required": ["queries"]
+        }),
+    }
+}
+
+fn recall_tool() -> ToolSpec {
+    ToolSpec {
+        name: RECALL.to_string(),
+        description: "Look up passages from pages fetched by earlier web searches, in this \
+                      and past sessions, most similar to a query first. Cheaper than a new \
+                      search; search the web when nothing relevant comes back."
+            .to_string(),
+        parameters: json!({
+            "type": "object",
+            "properties": {
+                "query": { "type": "string", "description": "What to look for." },
+                "k": { "type": "integer", "description": "How many passages to return." }
+            },
+            "required": ["query"]
+        }),
+    }
+}
+
+/// Answer the last user message in `history`, letting the model search the
+/// web as often as `cfg` allows. Tool calls, their results and the final
+/// answer are appended to `history`; the answer is also returned.
+///
+/// Reply text streams to `tokens`, along with a line per search.
+pub async fn run_turn(
+    backend: &dyn ChatBackend,
+    history: &mut Vec<ChatMessage>,
+    cfg: &AgentConfig,
+    tokens: Option<&TokenSender>,
+) -> Result<ChatMessage> {
+    let mut tools = vec![web_search_tool()];
+    if cfg.memory.is_some() {
+        tools.push(recall_tool());
+    }
+
+    let mut round = 0;
+    loop {
+        // Out of rounds: withhold the tools so the model has to answer.
+        let offered: &[ToolSpec] = if round < cfg.max_tool_rounds {
+            &tools
+        } else {
+            &[]
+        };
+        let mut reply = backend.chat(history, offered, tokens).await?;
+        if offered.is_empty() {
+            // Calls to tools that weren't offered can't be answered.
+            reply.tool_calls.clear();
+        }
+        history.push(reply.clone());
+        if reply.tool_calls.is_empty() {
+            return Ok(reply);
+        }
+
+        for call in &reply.tool_calls {
+            let result = run_tool(call, cfg, tokens).await;
+            history.push(ChatMessage::tool(&call.id, result));
+        }
+        round += 1;
+    }
+}
+
+/// The tool's output as the model will see it. Failures are reported to the
+/// model rather than ending the turn, so it can retry or answer anyway.
+async fn run_tool(call: &ToolCall, cfg: &AgentConfig, tokens: Option<&TokenSender>) -> String {
+    match (call.name.as_str(), &cfg.memory) {
+        (WEB_SEARCH, _) => web_search(call, cfg, tokens).await,
+        (RECALL, Some(memory)) => recall(call, memory).await,
+        _ => {
+            warn!(tool = %call.name, "model called an unknown tool");
+            format!("error: unknown tool `{}`", call.name)
+        }
+    }
+}
+
+async fn web_search(call: &ToolCall, cfg: &AgentConfig, tokens: Option<&TokenSender>) -> String {
+    let queries = search_queries(&call.arguments);
+    if queries.is_empty() {
+        return "error: expected {\"queries\": [\"...\"]}".to_string();
+    }
+
+    debug!(?queries, "agent search");
+    if let Some(tx) = tokens {
+        let _ = tx
+            .send(format!("\n*searching: {}*\n", queries.join("; ")))
+            .await;
+    }
+
+    match web::search_with_hooks(&queries, cfg.results_per_query, &cfg.search, &cfg.hooks).await {
+        Ok(pages) if pages.is_empty() => "no pages found".to_string(),
+        Ok(pages) => {
+            if let Some(memory) = &cfg.memory {
+                let mut index = memory.index.lock().await;
+                let added = index
+                    .add_pages(memory.embedder.as_ref(), &pages, &ChunkConfig::default())
+                    .await;
+                // The answer doesn't depend on the index, so carry on without it.
+                if let Err(e) = added {
+                    warn!(error = %format!("{e:#}"), "indexing search results failed");
+                }
+            }
+            render_results(&pages, cfg.max_result_chars)
+        }
+        Err(e) => format!("error: search failed: {e:#}"),
+    }
+}
+
+async fn recall(call: &ToolCall, memory: &Memory) -> String {
+    let Some(query) = call.arguments.get("query").and_then(Value::as_str) else {
+        return "error: expected {\"query\": \"...\"}".to_string();
+    };
+    let k = call
+        .arguments
+        .get("k")
+        .and_then(Value::as_u64)
+        .map_or(RECALL_DEFAULT_K, |k| k.clamp(1, 20) as usize);
+
+    debug!(query, k, "agent recall");
+    let index = memory.index.lock().await;
+    match index.search(memory.embedder.as_ref(), query, k).await {
+        Ok(hits) if hits.is_empty() => "nothing indexed yet".to_string(),
+        Ok(hits) => hits
+            .iter()
+            .map(|hit| {
+                let section = hit.chunk.heading_path.join(" > ");
+                format!(
+                    "---\nurl: {}\nsection: {section}\nscore: {:.2}\n---\n{}",
+                    hit.chunk.url,
+                    hit.score,
+                    hit.chunk.text.trim()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        Err(e) => format!("error: recall failed: {e:#}"),
+    }
+}
+
+/// `queries` as asked for, also accepting a single `query` string.
+fn search_queries(args: &Value) -> Vec<String> {
+    let list: Vec<&str> = match args.get("queries") {
+        Some(Value::Array(items)) => items.iter().filter_map(Value::as_str).collect(),
+        Some(Value::String(q)) => vec![q.as_str()],
+        _ => args
+            .get("query")
+            .and_then(Value::as_str)
+            .into_iter()
+            .collect(),
+    };
+    list.into_iter()
+        .map(str::trim)
+        .filter(|q| !q.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Each page's markdown, cut to an equal share of `max_chars`.
+fn render_results(pages: &[MdPage], max_chars: usize) -> String {
+    let share = max_chars / pages.len();
+    pages
+        .iter()
+        .map(|p| match p.markdown.char_indices().nth(share) {
+            Some((end, _)) => format!("{}\n\n[...truncated...]", &p.markdown[..end]),
+            None => p.markdown.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
@@ -0,0 +1,597 @@
+use agent_bob::config::Settings;
+use agent_bob::llm::{Backend, DEFAULT_OLLAMA_URL, DEFAULT_OPENAI_URL, LlmConfig};
+use agent_bob::session::LAST_SESSION;
+use agent_bob::web::{
+    ConfigError, FetchConfig, FetchIdentity, LlmCleanConfig, MdPage, QueryExpansion, QueryResults,
+    TokenModel, UserAgent, default_cache_dir, strip_header,
+};
+use clap::{ArgAction, Parser, Subcommand, ValueEnum};
+use std::fs;
+use std::io;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+/// Search the web and turn the hits into clean markdown for an LLM.
+#[derive(Debug, Parser)]
+#[command(name = "agent-bob", version, about)]
+pub struct Cli {
+    /// What to do; without a command, starts `chat`.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Settings file; flags override it [default: ~/.config/agent-bob/config.toml].
+    #[arg(long = "config", global = true, value_name = "PATH")]
+    pub config_file: Option<PathBuf>,
+
+    /// Pages fetched at once [default: 16].
+    #[arg(long, global = true)]
+    pub concurrency: Option<usize>,
+
+    /// Seconds a single fetch may take, body included [default: 20].
+    #[arg(long, global = true)]
+    pub timeout: Option<u64>,
+
+    /// Seconds a whole search may spend fetching; pages unfinished by then
+    /// are reported as failures.
+    #[arg(long, global = true, value_name = "SECS")]
+    pub deadline: Option<u64>,
+
+    /// MiB a whole search may download before it stops fetching.
+    #[arg(long, global = true, value_name = "MIB")]
+    pub max_total_mib: Option<usize>,
+
+    /// Markdown characters kept per page; longer pages are truncated [default: 24000].
+    #[arg(long, global = true)]
+    pub max_md_chars: Option<usize>,
+
+    /// Also cap each page at this many tokens, estimated for `--model`.
+    #[arg(long, global = true)]
+    pub max_md_tokens: Option<usize>,
+
+    /// Pages with less markdown than this are dropped [default: 200].
+    #[arg(long, global = true)]
+    pub min_md_chars: Option<usize>,
+
+    /// Keep responses whose Content-Type isn't HTML.
+    #[arg(long, global = true)]
+    pub allow_non_html: bool,
+
+    /// Fetch pages even where robots.txt disallows it, without crawl delays.
+    #[arg(long, global = true)]
+    pub ignore_robots: bool,
+
+    /// Order each query's pages by relevance to the query instead of search rank.
+    #[arg(long, global = true)]
+    pub sort_by_relevance: bool,
+
+    /// Also fetch up to this many same-site links from each hit's main
+    /// content: "next page" links, then those whose text matches the query.
+    #[arg(long, global = true, value_name = "K")]
+    pub follow: Option<usize>,
+
+    /// Append followed pages to the page linking to them instead of listing
+    /// them separately.
+    #[arg(long, global = true)]
+    pub merge_followed: bool,
+
+    /// Don't fetch pages: print each search hit's title, URL and snippet.
+    #[arg(long, global = true)]
+    pub snippets_only: bool,
+
+    /// Also search variants of each query and merge the hits: rewrites from
+    /// `--model` when there is one, else the query with synonyms swapped in.
+    #[arg(long, global = true)]
+    pub expand: bool,
+
+    /// Also search each query restricted to this site; repeat for several.
+    #[arg(long = "expand-site", global = true, value_name = "DOMAIN")]
+    pub expand_sites: Vec<String>,
+
+    /// Only fetch pages from this site (`docs.rs`, `*.rust-lang.org`);
+    /// repeat for several.
+    #[arg(long = "allow-domain", global = true, value_name = "DOMAIN")]
+    pub allowed_domains: Vec<String>,
+
+    /// Never fetch pages from this site; repeat for several.
+    #[arg(long = "block-domain", global = true, value_name = "DOMAIN")]
+    pub blocked_domains: Vec<String>,
+
+    /// Render pages that come out empty in headless Chrome, for sites that
+    /// only show content once JavaScript runs. Needs the `browser` feature.
+    #[arg(long, global = true)]
+    pub render_js: bool,
+
+    /// Keep only pages in this language, as an ISO 639-3 code (`eng`, `deu`,
+    /// `fra`, ...); repeat for several. Pages too short to tell are kept.
+    #[arg(long = "lang", global = true, value_name = "CODE")]
+    pub languages: Vec<String>,
+
+    /// Send every request through this `http://`, `https://` or `socks5://` proxy.
+    #[arg(long, global = true, value_name = "URL")]
+    pub proxy: Option<String>,
+
+    /// Extra request header, as `Name: value`; repeat for several.
+    #[arg(long = "header", short = 'H', global = true, value_name = "HEADER", value_parser = parse_header)]
+    pub headers: Vec<(String, String)>,
+
+    /// Keep cookies sites set and send them back on later requests.
+    #[arg(long, global = true)]
+    pub cookies: bool,
+
+    /// Send this User-Agent instead of a desktop Chrome's.
+    #[arg(long, global = true, value_name = "UA")]
+    pub user_agent: Option<String>,
+
+    /// Send a different desktop browser's User-Agent on each request.
+    #[arg(long, global = true, conflicts_with = "user_agent")]
+    pub rotate_user_agent: bool,
+
+    /// Identify as the agent-bob bot, with this URL or email to reach you at.
+    #[arg(long, global = true, value_name = "CONTACT", conflicts_with_all = ["user_agent", "rotate_user_agent"])]
+    pub bot_contact: Option<String>,
+
+    /// Log more to stderr: `-v` per-search summaries, `-vv` why each page was
+    /// dropped, `-vvv` everything. RUST_LOG overrides it.
+    #[arg(long, short = 'v', global = true, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Chat in a full-screen, scrollable transcript (PageUp/PageDown, mouse
+    /// wheel) instead of the terminal's scrollback.
+    #[arg(long, global = true)]
+    pub fullscreen: bool,
+
+    /// How results are printed or written.
+    #[arg(long, global = true, value_enum, default_value_t = Format::Markdown)]
+    pub format: Format,
+
+    /// Write results here instead of stdout. An existing directory (or a path
+    /// ending in `/`) gets one file per page; anything else one combined file.
+    #[arg(long, global = true, value_name = "PATH")]
+    pub out: Option<PathBuf>,
+
+    /// Where fetched pages are cached [default: ~/.cache/agent-bob].
+    #[arg(long, global = true, value_name = "DIR")]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Cache size in MiB; the oldest pages are evicted past it [default: 256].
+    #[arg(long, global = true, value_name = "MIB")]
+    pub cache_max_mib: Option<u64>,
+
+    /// Always fetch from the network and don't write the cache.
+    #[arg(long, global = true, conflicts_with = "cache_dir")]
+    pub no_cache: bool,
+
+    /// Chat with this model in interactive mode instead of previewing search results.
+    #[arg(long, global = true)]
+    pub model: Option<String>,
+
+    /// API the model is served through. `openai` reads OPENAI_API_KEY [default: openai].
+    #[arg(long, global = true, value_enum)]
+    pub backend: Option<Backend>,
+
+    /// API base URL [default: https://api.openai.com/v1, or http://localhost:11434 for ollama].
+    #[arg(long, global = true, value_name = "URL")]
+    pub llm_url: Option<String>,
+
+    /// Embed fetched pages with this model and let the chat model search them
+    /// again later, across sessions. Uses the same API as `--model`.
+    #[arg(long, global = true, value_name = "MODEL")]
+    pub embed_model: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Search the web and print the cleaned pages.
+    Search {
+        /// Search queries; pass several to search them together.
+        #[arg(required = true)]
+        queries: Vec<String>,
+
+        /// Search hits to crawl for each query.
+        #[arg(long = "results", short = 'n', default_value_t = 3)]
+        results_per_query: u32,
+
+        /// Print the pages under their query, with its failures, in query
+        /// order and search rank within each.
+        #[arg(long)]
+        group_by_query: bool,
+    },
+    /// Fetch pages by URL and print them cleaned, without searching.
+    Fetch {
+        #[arg(required = true)]
+        urls: Vec<String>,
+    },
+    /// Chat with `--model`, which searches as it needs to; without a model,
+    /// search each line typed (`;` separates queries) and preview the pages.
+    Chat {
+        /// Continue a saved conversation (with `--model`); without an id, the latest.
+        #[arg(long, value_name = "ID", num_args = 0..=1, default_missing_value = LAST_SESSION)]
+        resume: Option<String>,
+    },
+    /// Serve `web_search`, `fetch_url` and `crawl_site` as MCP tools over
+    /// stdio, for Claude Desktop and other MCP clients.
+    Mcp,
+    /// Serve `POST /search` and `POST /fetch` over HTTP, answering with the
+    /// cleaned pages as JSON.
+    Serve {
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+
+        /// Address to listen on; `0.0.0.0` to accept other machines.
+        #[arg(long, default_value = "127.0.0.1")]
+        host: IpAddr,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// Each page's markdown under a short banner.
+    Markdown,
+    /// One JSON array of pages.
+    Json,
+    /// One JSON page per line, printed as each is ready.
+    Ndjson,
+}
+
+impl Cli {
+    pub fn is_interactive(&self) -> bool {
+        matches!(self.command, None | Some(Command::Chat { .. }))
+    }
+
+    /// The log filter when RUST_LOG isn't set. Silent in interactive mode,
+    /// where stderr would scribble over the prompt, unless `-v` asks for it.
+    pub fn log_filter(&self) -> &'static str {
+        match self.verbose {
+            0 if self.is_interactive() => "off",
+            0 => "warn",
+            1 => "warn,agent_bob=info",
+            2 => "warn,agent_bob=debug",
+            _ => "agent_bob=trace,info",
+        }
+    }
+
+    /// The crawl config the flags and `settings` describe, validated so bad
+    /// combinations fail before any request goes out.
+    pub fn config(&self, settings: &Settings) -> Result<LlmCleanConfig, ConfigError> {
+        let file = &settings.crawl;
+        let d = LlmCleanConfig::default();
+        LlmCleanConfig::builder()
+            .concurrency(
+                self.concurrency
+                    .or(file.concurrency)
+                    .unwrap_or(d.concurrency),
+            )
+            .search_concurrency(file.search_concurrency.unwrap_or(d.search_concurrency))
+            .total_timeout_secs(
+                self.timeout
+                    .or(file.timeout_secs)
+                    .unwrap_or(d.total_timeout_secs),
+            )
+            .max_md_chars(
+                self.max_md_chars
+                    .or(file.max_md_chars)
+                    .unwrap_or(d.max_md_chars),
+            )
+            .max_md_tokens(self.max_md_tokens.or(file.max_md_tokens))
+            .token_model(
+                self.model(settings)
+                    .map(TokenModel::for_model)
+                    .unwrap_or_default(),
+            )
+            .min_md_chars(
+                self.min_md_chars
+                    .or(file.min_md_chars)
+                    .unwrap_or(d.min_md_chars),
+            )
+            .identity(self.identity(settings))
+            .fetch(FetchConfig {
+                proxy: self.proxy.clone().or(file.proxy.clone()),
+                headers: file
+                    .headers
+                    .iter()
+                    .flatten()
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .chain(self.headers.iter().cloned())
+                    .collect(),
+                cookies: self.cookies || file.cookies == Some(true),
+            })
+            .require_html_content_type(!(self.allow_non_html || file.allow_non_html == Some(true)))
+            .respect_robots_txt(!self.ignore_robots && file.respect_robots_txt != Some(false))
+            .follow_links_depth(match self.follow.or(file.follow_links) {
+                Some(k) if k > 0 => 1,
+                _ => d.follow_links_depth,
+            })
+            .max_followed_links_per_page(
+                self.follow
+                    .or(file.follow_links)
+                    .unwrap_or(d.max_followed_links_per_page),
+            )
+            .merge_followed_links(self.merge_followed || file.merge_followed == Some(true))
+            .github_token(file.github_token.clone())
+            .snippets_only(self.snippets_only)
+            .query_expansion(QueryExpansion {
+                synonyms: self.expand,
+                sites: self.expand_sites.clone(),
+                ..Default::default()
+            })
+            .sort_by_relevance(self.sort_by_relevance || file.sort_by_relevance == Some(true))
+            .allowed_languages(if self.languages.is_empty() {
+                file.allowed_languages.clone().unwrap_or_default()
+            } else {
+                self.languages.clone()
+            })
+            .allowed_domains(
+                file.allowed_domains
+                    .iter()
+                    .flatten()
+                    .chain(&self.allowed_domains)
+                    .cloned()
+                    .collect(),
+            )
+            .blocked_domains(
+                file.blocked_domains
+                    .iter()
+                    .flatten()
+                    .chain(&self.blocked_domains)
+                    .cloned()
+                    .collect(),
+            )
+            .render_js(self.render_js || file.render_js == Some(true))
+            .browser_executable(file.browser.clone())
+            .cache_dir(self.cache_dir(settings))
+            .cache_max_bytes(
+                self.cache_max_mib
+                    .or(file.cache_max_mib)
+                    .map_or(d.cache_max_bytes, |mib| mib << 20),
+            )
+            .search_deadline_secs(self.deadline.or(file.deadline_secs))
+            .max_total_bytes(
+                self.max_total_mib
+                    .or(file.max_total_mib)
+                    .map(|mib| mib << 20),
+            )
+            .search(settings.search.clone())
+            .build()
+    }
+
+    /// The chat backend to use, when a model was given.
+    pub fn llm_config(&self, settings: &Settings) -> Option<LlmConfig> {
+        let model = self.model(settings)?.to_string();
+        Some(self.backend_config(settings, model))
+    }
+
+    /// The embedding backend, served through the same API as the chat model.
+    pub fn embed_config(&self, settings: &Settings) -> Option<LlmConfig> {
+        let model = self
+            .embed_model
+            .clone()
+            .or(settings.llm.embed_model.clone())?;
+        Some(self.backend_config(settings, model))
+    }
+
+    fn model<'a>(&'a self, settings: &'a Settings) -> Option<&'a str> {
+        self.model.as_deref().or(settings.llm.model.as_deref())
+    }
+
+    fn backend_config(&self, settings: &Settings, model: String) -> LlmConfig {
+        let llm = &settings.llm;
+        let base_url = self.llm_url.clone().or(llm.url.clone());
+        match self.backend.or(llm.backend).unwrap_or(Backend::OpenAi) {
+            Backend::OpenAi => LlmConfig::OpenAi {
+                base_url: base_url.unwrap_or(DEFAULT_OPENAI_URL.into()),
+                api_key: llm.api_key.clone(),
+                model,
+            },
+            Backend::Ollama => LlmConfig::Ollama {
+                base_url: base_url.unwrap_or(DEFAULT_OLLAMA_URL.into()),
+                model,
+            },
+        }
+    }
+
+    /// Flags win over the file; within each, a bot contact wins over a
+    /// rotation, which wins over a fixed user agent.
+    fn identity(&self, settings: &Settings) -> FetchIdentity {
+        let file = &settings.crawl;
+        let user_agent = if let Some(contact) = &self.bot_contact {
+            UserAgent::Bot {
+                contact: contact.clone(),
+            }
+        } else if self.rotate_user_agent {
+            UserAgent::Rotate(Vec::new())
+        } else if let Some(agent) = &self.user_agent {
+            UserAgent::Custom(agent.clone())
+        } else if let Some(contact) = &file.bot_contact {
+            UserAgent::Bot {
+                contact: contact.clone(),
+            }
+        } else if let Some(agents) = &file.user_agents {
+            UserAgent::Rotate(agents.clone())
+        } else if let Some(agent) = &file.user_agent {
+            UserAgent::Custom(agent.clone())
+        } else {
+            UserAgent::default()
+        };
+        FetchIdentity {
+            user_agent,
+            overrides: file
+                .user_agent_overrides
+                .iter()
+                .flatten()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+        }
+    }
+
+    fn cache_dir(&self, settings: &Settings) -> Option<PathBuf> {
+        let file = &settings.crawl;
+        if self.no_cache || (self.cache_dir.is_none() && file.cache == Some(false)) {
+            return None;
+        }
+        self.cache_dir
+            .clone()
+            .or(file.cache_dir.clone())
+            .or_else(default_cache_dir)
+    }
+}
+
+/// A page with a readable header, as printed to stdout or a combined file.
+pub fn render_page(p: &MdPage) -> String {
+    format!(
+        "\n==============================\n\
+         Query:  {}\n\
+         Title:  {}\n\
+         URL:    {}\n\
+         Status: {}\n\
+         ------------------------------\n\n\
+         {}\n",
+        p.query,
+        p.title.as_deref().unwrap_or("(none)"),
+        p.url,
+        p.status,
+        p.markdown
+    )
+}
+
+/// Page content lines shown per page in interactive mode.
+const PREVIEW_LINES: usize = 12;
+/// Outline headings shown per page in interactive mode.
+const PREVIEW_HEADINGS: usize = 8;
+
+/// Queries typed at the interactive prompt: `;`-separated, blanks dropped.
+pub fn parse_queries(input: &str) -> Vec<String> {
+    input
+        .split(';')
+        .map(str::trim)
+        .filter(|q| !q.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Title, URL, outline and the first lines of content, as markdown for the
+/// interactive scrollback.
+pub fn render_page_preview(p: &MdPage) -> String {
+    let title = p.title.as_deref().unwrap_or("(untitled)");
+    let mut out = format!("## {title}\n{}\n", p.final_url);
+
+    if !p.outline.is_empty() {
+        out.push('\n');
+        for item in p.outline.iter().take(PREVIEW_HEADINGS) {
+            let indent = "  ".repeat(item.level.saturating_sub(1) as usize);
+            out.push_str(&format!("{indent}- {}\n", item.text));
+        }
+    }
+
+    out.push('\n');
+    let mut fences = 0;
+    for line in strip_header(&p.markdown)
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .take(PREVIEW_LINES)
+    {
+        fences += line.trim_start().starts_with("```") as usize;
+        out.push_str(line);
+        out.push('\n');
+    }
+    // Don't let a cut-off code block swallow the next page.
+    if fences % 2 == 1 {
+        out.push_str("```\n");
+    }
+    out
+}
+
+/// All of `pages` in `format`, as printed to stdout or written to a single file.
+pub fn render_pages(pages: &[MdPage], format: Format) -> String {
+    match format {
+        Format::Markdown => pages.iter().map(render_page).collect(),
+        Format::Json => json(pages) + "\n",
+        Format::Ndjson => pages.iter().map(|p| json(p) + "\n").collect(),
+    }
+}
+
+/// Each query's pages and failures; JSON objects are `{query, pages, errors}`.
+pub fn render_groups(groups: &[QueryResults], format: Format) -> String {
+    match format {
+        Format::Markdown => groups
+            .iter()
+            .map(|g| {
+                let mut out = format!("# {}\n\n", g.query);
+                for e in &g.errors {
+                    out.push_str(&format!("> failed: {e}\n\n"));
+                }
+                if g.pages.is_empty() {
+                    out.push_str("(no pages)\n\n");
+                }
+                out.extend(g.pages.iter().map(render_page));
+                out
+            })
+            .collect(),
+        Format::Json => json(groups) + "\n",
+        Format::Ndjson => groups.iter().map(|g| json(g) + "\n").collect(),
+    }
+}
+
+/// `write_pages` for grouped results; a directory gets the pages in group order.
+pub fn write_groups(out: &Path, groups: &[QueryResults], format: Format) -> io::Result<()> {
+    let is_dir = out.is_dir() || out.as_os_str().to_string_lossy().ends_with('/');
+    if !is_dir {
+        return fs::write(out, render_groups(groups, format));
+    }
+    let pages: Vec<MdPage> = groups.iter().flat_map(|g| g.pages.clone()).collect();
+    write_pages(out, &pages, format)
+}
+
+/// Write `pages` to `out`: one file per page into a directory, else all in one file.
+pub fn write_pages(out: &Path, pages: &[MdPage], format: Format) -> io::Result<()> {
+    let is_dir = out.is_dir() || out.as_os_str().to_string_lossy().ends_with('/');
+    if !is_dir {
+        return fs::write(out, render_pages(pages, format));
+    }
+
+    fs::create_dir_all(out)?;
+    for (idx, p) in pages.iter().enumerate() {
+        let name = slugify(p.title.as_deref().unwrap_or(&p.final_url));
+        let (ext, body) = match format {
+            Format::Markdown => ("md", p.markdown.clone()),
+            Format::Json | Format::Ndjson => ("json", json(p) + "\n"),
+        };
+        fs::write(out.join(format!("{:02}-{name}.{ext}", idx + 1)), body)?;
+    }
+    Ok(())
+}
+
+fn json<T: serde::Serialize + ?Sized>(value: &T) -> String {
+    // Plain data with string keys; serializing it can't fail.
+    serde_json::to_string(value).expect("pages serialize to JSON")
+}
+
+/// Lowercase ASCII alphanumerics joined by single dashes, at most 60 chars.
+fn slugify(s: &str) -> String {
+    let mut slug = String::new();
+    for ch in s.chars() {
+        if ch.is_ascii_alphanumeric() {
+            slug.push(ch.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+        if slug.len() >= 60 {
+            break;
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        "page".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+/// `--header Name: value`.
+fn parse_header(s: &str) -> Result<(String, String), String> {
+    match s.split_once(':') {
+        Some((name, value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_string(), value.trim().to_string()))
+        }
+        _ => Err(format!("expected `Name: value`, got {s:?}")),
+    }
+}
@@ -0,0 +1,186 @@
+use crate::llm::Backend;
+use crate::web::SearchConfig;
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// `$XDG_CONFIG_HOME/agent-bob/config.toml`, falling back to `~/.config/agent-bob/config.toml`.
+pub fn default_config_file() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|d| !d.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))?;
+    Some(base.join("agent-bob").join("config.toml"))
+}
+
+/// Settings from the config file and environment. Every field is optional;
+/// command-line flags override them, and they override the built-in defaults.
+#[derive(Debug, Default)]
+pub struct Settings {
+    pub crawl: CrawlSettings,
+    pub search: SearchConfig,
+    pub llm: LlmSettings,
+}
+
+/// `[crawl]`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CrawlSettings {
+    pub concurrency: Option<usize>,
+    pub search_concurrency: Option<usize>,
+    pub timeout_secs: Option<u64>,
+    pub deadline_secs: Option<u64>,
+    pub max_total_mib: Option<usize>,
+    pub max_md_chars: Option<usize>,
+    pub max_md_tokens: Option<usize>,
+    pub min_md_chars: Option<usize>,
+    pub user_agent: Option<String>,
+    /// Sent in turn instead of `user_agent`; empty rotates built-in browsers.
+    pub user_agents: Option<Vec<String>>,
+    /// Identify as `agent-bob` with this contact URL or email instead.
+    pub bot_contact: Option<String>,
+    /// `[crawl.user_agent_overrides]`: domain pattern to user agent, for
+    /// sites that block the default one.
+    pub user_agent_overrides: Option<BTreeMap<String, String>>,
+    pub proxy: Option<String>,
+    /// For GitHub's API; `GITHUB_TOKEN` also sets it.
+    pub github_token: Option<String>,
+    /// `[crawl.headers]`: sent with every request; `--header` adds to them.
+    pub headers: Option<BTreeMap<String, String>>,
+    pub cookies: Option<bool>,
+    pub respect_robots_txt: Option<bool>,
+    pub allow_non_html: Option<bool>,
+    pub sort_by_relevance: Option<bool>,
+    /// Same-site links followed from each hit; `--follow` replaces it.
+    pub follow_links: Option<usize>,
+    pub merge_followed: Option<bool>,
+    /// `--allow-domain` and `--block-domain` add to these.
+    pub allowed_domains: Option<Vec<String>>,
+    pub blocked_domains: Option<Vec<String>>,
+    pub render_js: Option<bool>,
+    /// Chrome or Chromium for `render_js`, when it isn't on `PATH`.
+    pub browser: Option<PathBuf>,
+    /// ISO 639-3 codes; `--lang` replaces them.
+    pub allowed_languages: Option<Vec<String>>,
+    /// `false` turns the page cache off.
+    pub cache: Option<bool>,
+    pub cache_dir: Option<PathBuf>,
+    pub cache_max_mib: Option<u64>,
+}
+
+/// `[llm]`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LlmSettings {
+    pub backend: Option<Backend>,
+    pub url: Option<String>,
+    pub model: Option<String>,
+    pub embed_model: Option<String>,
+    pub api_key: Option<String>,
+}
+
+/// The file as written; `[search]` is resolved into a `SearchConfig` on load.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct File {
+    crawl: CrawlSettings,
+    search: SearchSection,
+    llm: LlmSettings,
+}
+
+/// `[search]`: `provider` plus whatever credentials it needs.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct SearchSection {
+    provider: Option<String>,
+    api_key: Option<String>,
+    /// SearXNG instance.
+    url: Option<String>,
+    /// Google Programmable Search engine id.
+    cx: Option<String>,
+}
+
+impl Settings {
+    /// Read `path`, or the default config file when `None`, then apply the
+    /// `AGENT_BOB_*` and `OPENAI_API_KEY` environment overrides. Only a
+    /// missing default file is fine; a missing explicit one is an error.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let mut file = match path.map(Path::to_path_buf).or_else(default_config_file) {
+            Some(p) if path.is_some() || p.exists() => {
+                let text =
+                    fs::read_to_string(&p).with_context(|| format!("reading {}", p.display()))?;
+                toml::from_str(&text).with_context(|| format!("parsing {}", p.display()))?
+            }
+            _ => File::default(),
+        };
+        apply_env(&mut file)?;
+
+        Ok(Self {
+            search: file.search.resolve()?,
+            crawl: file.crawl,
+            llm: file.llm,
+        })
+    }
+}
+
+fn apply_env(file: &mut File) -> Result<()> {
+    let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+
+    if let Some(v) = var("AGENT_BOB_BACKEND") {
+        file.llm.backend = Some(match v.to_ascii_lowercase().as_str() {
+            "openai" => Backend::OpenAi,
+            "ollama" => Backend::Ollama,
+            _ => bail!("AGENT_BOB_BACKEND is {v:?}, expected openai or ollama"),
+        });
+    }
+    for (name, slot) in [
+        ("AGENT_BOB_LLM_URL", &mut file.llm.url),
+        ("AGENT_BOB_MODEL", &mut file.llm.model),
+        ("AGENT_BOB_EMBED_MODEL", &mut file.llm.embed_model),
+        ("OPENAI_API_KEY", &mut file.llm.api_key),
+        ("AGENT_BOB_USER_AGENT", &mut file.crawl.user_agent),
+        ("AGENT_BOB_PROXY", &mut file.crawl.proxy),
+        ("GITHUB_TOKEN", &mut file.crawl.github_token),
+        ("AGENT_BOB_SEARCH_PROVIDER", &mut file.search.provider),
+        ("AGENT_BOB_SEARCH_API_KEY", &mut file.search.api_key),
+        ("AGENT_BOB_SEARCH_URL", &mut file.search.url),
+        ("AGENT_BOB_SEARCH_CX", &mut file.search.cx),
+    ] {
+        if let Some(v) = var(name) {
+            *slot = Some(v);
+        }
+    }
+    Ok(())
+}
+
+impl SearchSection {
+    fn resolve(self) -> Result<SearchConfig> {
+        let Some(provider) = self.provider else {
+            return Ok(SearchConfig::default());
+        };
+        let need = |value: Option<String>, key: &str| {
+            value.with_context(|| format!("search provider {provider} needs `{key}`"))
+        };
+        Ok(match provider.to_ascii_lowercase().as_str() {
+            "duckduckgo" => SearchConfig::DuckDuckGo,
+            "brave" => SearchConfig::Brave {
+                api_key: need(self.api_key, "api_key")?,
+            },
+            "bing" => SearchConfig::Bing {
+                api_key: need(self.api_key, "api_key")?,
+            },
+            "searxng" => SearchConfig::SearXng {
+                base_url: need(self.url, "url")?,
+            },
+            "google" => SearchConfig::GoogleCse {
+                api_key: need(self.api_key, "api_key")?,
+                cx: need(self.cx, "cx")?,
+            },
+            _ => bail!(
+                "unknown search provider {provider:?}; expected duckduckgo, brave, bing, searxng or google"
+            ),
+        })
+    }
+}
@@ -0,0 +1,153 @@
+use crate::llm::EmbeddingBackend;
+use crate::web::{ChunkConfig, MdPage, chunk_markdown};
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+/// Chunks sent to the embedding backend per request.
+const EMBED_BATCH: usize = 32;
+
+/// `$XDG_DATA_HOME/agent-bob/index.jsonl`, falling back to `~/.local/share/agent-bob/index.jsonl`.
+pub fn default_index_file() -> Option<PathBuf> {
+    Some(crate::data_dir()?.join("index.jsonl"))
+}
+
+/// One embedded chunk, as stored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedChunk {
+    pub url: String,
+    pub heading_path: Vec<String>,
+    pub text: String,
+    /// Unit length, so a dot product is the cosine similarity.
+    vector: Vec<f32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Hit<'a> {
+    /// Cosine similarity to the query, in `-1.0..=1.0`.
+    pub score: f32,
+    pub chunk: &'a IndexedChunk,
+}
+
+/// Embedded page chunks in a JSON-lines file, searched by brute force. Fine
+/// up to tens of thousands of chunks, which is far more than a session crawls.
+pub struct VectorIndex {
+    path: PathBuf,
+    chunks: Vec<IndexedChunk>,
+}
+
+impl VectorIndex {
+    /// Load the index at `path`; a missing file is an empty index.
+    pub fn open(path: &Path) -> Result<Self> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e).with_context(|| format!("reading {}", path.display())),
+        };
+
+        let mut chunks = Vec::new();
+        for (n, line) in text.lines().enumerate().filter(|(_, l)| !l.is_empty()) {
+            match serde_json::from_str(line) {
+                Ok(chunk) => chunks.push(chunk),
+                // A torn last write shouldn't cost the whole index.
+                Err(e) => {
+                    warn!(path = %path.display(), line = n + 1, error = %e, "skipping bad index line")
+                }
+            }
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            chunks,
+        })
+    }
+
+    /// Chunk, embed and store `pages`, replacing chunks already indexed for
+    /// the same URLs. Returns how many chunks were added.
+    pub async fn add_pages(
+        &mut self,
+        embedder: &dyn EmbeddingBackend,
+        pages: &[MdPage],
+        cfg: &ChunkConfig,
+    ) -> Result<usize> {
+        let chunks: Vec<_> = pages.iter().flat_map(|p| chunk_markdown(p, cfg)).collect();
+
+        let mut added = Vec::with_capacity(chunks.len());
+        for batch in chunks.chunks(EMBED_BATCH) {
+            let texts: Vec<String> = batch.iter().map(|c| c.text.clone()).collect();
+            let vectors = embedder.embed(&texts).await?;
+            for (chunk, vector) in batch.iter().zip(vectors) {
+                added.push(IndexedChunk {
+                    url: chunk.url.clone(),
+                    heading_path: chunk.heading_path.clone(),
+                    text: chunk.text.clone(),
+                    vector: normalized(vector),
+                });
+            }
+        }
+
+        let urls: HashSet<&str> = pages.iter().map(|p| p.final_url.as_str()).collect();
+        self.chunks.retain(|c| !urls.contains(c.url.as_str()));
+        let n = added.len();
+        self.chunks.extend(added);
+        self.save()?;
+        debug!(added = n, total = self.chunks.len(), "indexed pages");
+        Ok(n)
+    }
+
+    /// The `k` chunks most similar to `query`, best first.
+    pub async fn search(
+        &self,
+        embedder: &dyn EmbeddingBackend,
+        query: &str,
+        k: usize,
+    ) -> Result<Vec<Hit<'_>>> {
+        if self.chunks.is_empty() || k == 0 {
+            return Ok(Vec::new());
+        }
+        let Some(query) = embedder.embed(&[query.to_string()]).await?.pop() else {
+            bail!("embedding backend returned no vector for the query");
+        };
+        let query = normalized(query);
+
+        let mut hits = Vec::with_capacity(self.chunks.len());
+        for chunk in &self.chunks {
+            // Vectors from another model can't be compared; skip rather than fail.
+            if chunk.vector.len() != query.len() {
+                continue;
+            }
+            let score = chunk.vector.iter().zip(&query).map(|(a, b)| a * b).sum();
+            hits.push(Hit { score, chunk });
+        }
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+        hits.truncate(k);
+        Ok(hits)
+    }
+
+    /// Rewrite the whole file through a temp file, so a crash leaves the old index.
+    fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut out = String::new();
+        for chunk in &self.chunks {
+            out.push_str(&serde_json::to_string(chunk)?);
+            out.push('\n');
+        }
+        let tmp = self.path.with_extension("jsonl.tmp");
+        fs::write(&tmp, out).with_context(|| format!("writing {}", tmp.display()))?;
+        fs::rename(&tmp, &self.path)
+            .with_context(|| format!("replacing {}", self.path.display()))?;
+        Ok(())
+    }
+}
+
+fn normalized(mut v: Vec<f32>) -> Vec<f32> {
+    let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        v.iter_mut().for_each(|x| *x /= norm);
+    }
+    v
+}
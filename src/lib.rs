@@ -0,0 +1,31 @@
+//! Search the web and turn the hits into clean markdown for an LLM.
+//!
+//! [`web`] is the pipeline: search, fetch, extract and clean, with
+//! [`web::search`] and [`web::LlmCleanConfig`] as the place to start. The
+//! rest is what the `agent-bob` binary builds on top of it: a chat agent
+//! that searches as it answers ([`agent`], [`llm`]), a page index it can
+//! search again later ([`index`]), saved conversations ([`session`]), the
+//! terminal UI ([`ui`]), the settings file ([`config`]), and servers for
+//! other agents and services ([`mcp`], [`serve`]).
+
+pub mod agent;
+pub mod config;
+pub mod index;
+pub mod llm;
+pub mod mcp;
+pub mod serve;
+pub mod session;
+pub mod ui;
+pub mod web;
+
+use std::path::PathBuf;
+
+/// `$XDG_DATA_HOME/agent-bob`, falling back to `~/.local/share/agent-bob`: where
+/// the prompt history, saved sessions and the page index live.
+pub fn data_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_DATA_HOME")
+        .filter(|d| !d.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".local/share")))?;
+    Some(base.join("agent-bob"))
+}
@@ -0,0 +1,250 @@
+use anyhow::{Result, bail};
+use async_trait::async_trait;
+use clap::ValueEnum;
+use reqwest::{Client, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+mod ollama;
+mod openai;
+
+use ollama::OllamaBackend;
+use openai::OpenAiBackend;
+
+pub const DEFAULT_OPENAI_URL: &str = "https://api.openai.com/v1";
+pub const DEFAULT_OLLAMA_URL: &str = "http://localhost:11434";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+    /// The result of a tool call, answering the assistant message that made it.
+    Tool,
+}
+
+/// Serializes in its own shape, for saved sessions; backends convert to their wire format.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: Role,
+    pub content: String,
+    /// Calls the assistant made instead of (or besides) answering.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    /// For `Role::Tool`, the call this is the result of.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+}
+
+impl ChatMessage {
+    fn new(role: Role, content: impl Into<String>) -> Self {
+        Self {
+            role,
+            content: content.into(),
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+        }
+    }
+
+    pub fn system(content: impl Into<String>) -> Self {
+        Self::new(Role::System, content)
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self::new(Role::User, content)
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self::new(Role::Assistant, content)
+    }
+
+    pub fn tool(call_id: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            tool_call_id: Some(call_id.into()),
+            ..Self::new(Role::Tool, content)
+        }
+    }
+}
+
+/// A function the model may call, described by a JSON Schema for its arguments.
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolSpec {
+    pub name: String,
+    pub description: String,
+    pub parameters: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    /// Usually an object; a string when the model produced invalid JSON.
+    pub arguments: Value,
+}
+
+/// Receives reply text piece by piece while it streams in.
+pub type TokenSender = mpsc::Sender<String>;
+
+/// A chat model. Implement with `#[async_trait]` for backends `LlmConfig`
+/// doesn't cover.
+#[async_trait]
+pub trait ChatBackend: Send + Sync {
+    /// The assistant's reply to `messages`, which may call any of `tools`.
+    /// With `tokens`, the text is also sent there as it arrives; a closed
+    /// receiver doesn't stop the reply.
+    async fn chat(
+        &self,
+        messages: &[ChatMessage],
+        tools: &[ToolSpec],
+        tokens: Option<&TokenSender>,
+    ) -> Result<ChatMessage>;
+}
+
+/// Turns text into vectors for similarity search. Implement with
+/// `#[async_trait]` for backends `LlmConfig` doesn't cover.
+#[async_trait]
+pub trait EmbeddingBackend: Send + Sync {
+    /// One vector per text, in order.
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>>;
+}
+
+/// Which API [`LlmConfig`] talks to, as named in flags and config files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// Any OpenAI-compatible `/chat/completions` endpoint.
+    #[value(name = "openai")]
+    OpenAi,
+    Ollama,
+}
+
+/// The built-in chat backends.
+#[derive(Debug, Clone)]
+pub enum LlmConfig {
+    /// `/chat/completions` on OpenAI or anything that mimics it (vLLM,
+    /// llama.cpp, LM Studio, OpenRouter).
+    OpenAi {
+        base_url: String,
+        api_key: Option<String>,
+        model: String,
+    },
+    /// Ollama's native `/api/chat`.
+    Ollama { base_url: String, model: String },
+}
+
+impl LlmConfig {
+    pub fn backend(&self) -> Arc<dyn ChatBackend> {
+        let client = Client::new();
+        match self.clone() {
+            LlmConfig::OpenAi {
+                base_url,
+                api_key,
+                model,
+            } => Arc::new(OpenAiBackend {
+                client,
+                base_url,
+                api_key,
+                model,
+            }),
+            LlmConfig::Ollama { base_url, model } => Arc::new(OllamaBackend {
+                client,
+                base_url,
+                model,
+            }),
+        }
+    }
+
+    /// The same API used for embeddings; `model` must then be an embedding model.
+    pub fn embedder(&self) -> Arc<dyn EmbeddingBackend> {
+        let client = Client::new();
+        match self.clone() {
+            LlmConfig::OpenAi {
+                base_url,
+                api_key,
+                model,
+            } => Arc::new(OpenAiBackend {
+                client,
+                base_url,
+                api_key,
+                model,
+            }),
+            LlmConfig::Ollama { base_url, model } => Arc::new(OllamaBackend {
+                client,
+                base_url,
+                model,
+            }),
+        }
+    }
+}
+
+/// POST `body` as JSON, failing with the server's message on a non-2xx status.
+async fn post_json(
+    client: &Client,
+    url: &str,
+    api_key: Option<&str>,
+    body: &impl Serialize,
+) -> Result<Response> {
+    let mut req = client
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(serde_json::to_string(body)?);
+    if let Some(key) = api_key {
+        req = req.bearer_auth(key);
+    }
+
+    let resp = req.send().await?;
+    let status = resp.status();
+    if !status.is_success() {
+        let text = resp.text().await.unwrap_or_default();
+        bail!("{url} returned {status}: {}", text.trim());
+    }
+    Ok(resp)
+}
+
+/// A response body read line by line as chunks arrive, for SSE and NDJSON streams.
+struct Lines {
+    resp: Response,
+    buf: Vec<u8>,
+    eof: bool,
+}
+
+impl Lines {
+    fn new(resp: Response) -> Self {
+        Self {
+            resp,
+            buf: Vec::new(),
+            eof: false,
+        }
+    }
+
+    async fn next(&mut self) -> Result<Option<String>> {
+        loop {
+            if let Some(end) = self.buf.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = self.buf.drain(..=end).collect();
+                return Ok(Some(String::from_utf8_lossy(&line).trim_end().to_string()));
+            }
+            if self.eof {
+                if self.buf.is_empty() {
+                    return Ok(None);
+                }
+                let line = std::mem::take(&mut self.buf);
+                return Ok(Some(String::from_utf8_lossy(&line).trim_end().to_string()));
+            }
+            match self.resp.chunk().await? {
+                Some(chunk) => self.buf.extend_from_slice(&chunk),
+                None => self.eof = true,
+            }
+        }
+    }
+}
+
+async fn send_token(tokens: Option<&TokenSender>, text: &str) {
+    if let Some(tx) = tokens
+        && !text.is_empty()
+    {
+        let _ = tx.send(text.to_string()).await;
+    }
+}
@@ -0,0 +1,185 @@
+use super::{
+    ChatBackend, ChatMessage, EmbeddingBackend, Lines, Role, TokenSender, ToolCall, ToolSpec,
+    post_json, send_token,
+};
+use anyhow::{Context, Result, bail};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub(super) struct OllamaBackend {
+    pub(super) client: Client,
+    pub(super) base_url: String,
+    pub(super) model: String,
+}
+
+#[derive(Serialize)]
+struct Request<'a> {
+    model: &'a str,
+    messages: Vec<WireMessage<'a>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<WireTool<'a>>,
+    stream: bool,
+}
+
+#[derive(Serialize)]
+struct WireMessage<'a> {
+    role: Role,
+    content: &'a str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<WireToolCall<'a>>,
+}
+
+#[derive(Serialize)]
+struct WireToolCall<'a> {
+    function: WireFunction<'a>,
+}
+
+/// Unlike the OpenAI API, arguments are a JSON object here.
+#[derive(Serialize)]
+struct WireFunction<'a> {
+    name: &'a str,
+    arguments: &'a Value,
+}
+
+#[derive(Serialize)]
+struct WireTool<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    function: &'a ToolSpec,
+}
+
+/// One NDJSON line of a streamed `/api/chat` reply.
+#[derive(Deserialize)]
+struct Chunk {
+    message: Option<Message>,
+    #[serde(default)]
+    done: bool,
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Message {
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    tool_calls: Vec<CallIn>,
+}
+
+#[derive(Deserialize)]
+struct CallIn {
+    function: FunctionIn,
+}
+
+#[derive(Deserialize)]
+struct FunctionIn {
+    name: String,
+    #[serde(default)]
+    arguments: Value,
+}
+
+#[async_trait]
+impl ChatBackend for OllamaBackend {
+    async fn chat(
+        &self,
+        messages: &[ChatMessage],
+        tools: &[ToolSpec],
+        tokens: Option<&TokenSender>,
+    ) -> Result<ChatMessage> {
+        let url = format!("{}/api/chat", self.base_url.trim_end_matches('/'));
+        let req = Request {
+            model: &self.model,
+            messages: messages.iter().map(wire_message).collect(),
+            tools: tools
+                .iter()
+                .map(|function| WireTool {
+                    kind: "function",
+                    function,
+                })
+                .collect(),
+            stream: true,
+        };
+        let resp = post_json(&self.client, &url, None, &req).await?;
+
+        let mut reply = ChatMessage::assistant("");
+        let mut lines = Lines::new(resp);
+        while let Some(line) = lines.next().await? {
+            if line.is_empty() {
+                continue;
+            }
+            let chunk: Chunk = serde_json::from_str(&line)
+                .with_context(|| format!("unexpected stream line: {line}"))?;
+            if let Some(error) = chunk.error {
+                bail!("ollama: {error}");
+            }
+            if let Some(message) = chunk.message {
+                send_token(tokens, &message.content).await;
+                reply.content.push_str(&message.content);
+                // Ollama sends whole calls, without ids; number them so results can refer back.
+                for call in message.tool_calls {
+                    reply.tool_calls.push(ToolCall {
+                        id: format!("call_{}", reply.tool_calls.len()),
+                        name: call.function.name,
+                        arguments: call.function.arguments,
+                    });
+                }
+            }
+            if chunk.done {
+                break;
+            }
+        }
+        Ok(reply)
+    }
+}
+
+#[derive(Serialize)]
+struct EmbedRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Deserialize)]
+struct EmbedResponse {
+    embeddings: Vec<Vec<f32>>,
+}
+
+#[async_trait]
+impl EmbeddingBackend for OllamaBackend {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let url = format!("{}/api/embed", self.base_url.trim_end_matches('/'));
+        let req = EmbedRequest {
+            model: &self.model,
+            input: texts,
+        };
+        let resp = post_json(&self.client, &url, None, &req).await?;
+        let body = resp.text().await?;
+        let parsed: EmbedResponse = serde_json::from_str(&body)
+            .with_context(|| format!("unexpected embeddings response from {url}"))?;
+        if parsed.embeddings.len() != texts.len() {
+            bail!(
+                "{url} returned {} embeddings for {} inputs",
+                parsed.embeddings.len(),
+                texts.len()
+            );
+        }
+        Ok(parsed.embeddings)
+    }
+}
+
+fn wire_message(m: &ChatMessage) -> WireMessage<'_> {
+    WireMessage {
+        role: m.role,
+        content: &m.content,
+        tool_calls: m
+            .tool_calls
+            .iter()
+            .map(|call| WireToolCall {
+                function: WireFunction {
+                    name: &call.name,
+                    arguments: &call.arguments,
+                },
+            })
+            .collect(),
+    }
+}
@@ -0,0 +1,234 @@
+use super::{
+    ChatBackend, ChatMessage, EmbeddingBackend, Lines, Role, TokenSender, ToolCall, ToolSpec,
+    post_json, send_token,
+};
+use anyhow::{Context, Result, bail};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub(super) struct OpenAiBackend {
+    pub(super) client: Client,
+    pub(super) base_url: String,
+    pub(super) api_key: Option<String>,
+    pub(super) model: String,
+}
+
+#[derive(Serialize)]
+struct Request<'a> {
+    model: &'a str,
+    messages: Vec<WireMessage<'a>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<WireTool<'a>>,
+    stream: bool,
+}
+
+#[derive(Serialize)]
+struct WireMessage<'a> {
+    role: Role,
+    content: &'a str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<WireToolCall<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct WireToolCall<'a> {
+    id: &'a str,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    function: WireFunction<'a>,
+}
+
+/// Arguments travel as a JSON-encoded string in this API.
+#[derive(Serialize)]
+struct WireFunction<'a> {
+    name: &'a str,
+    arguments: String,
+}
+
+#[derive(Serialize)]
+struct WireTool<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    function: &'a ToolSpec,
+}
+
+/// One server-sent `data:` event of a streamed completion.
+#[derive(Deserialize)]
+struct Chunk {
+    #[serde(default)]
+    choices: Vec<Choice>,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    delta: Option<Delta>,
+}
+
+#[derive(Deserialize)]
+struct Delta {
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<ToolCallDelta>,
+}
+
+/// A piece of a tool call; pieces with the same `index` are concatenated.
+#[derive(Deserialize)]
+struct ToolCallDelta {
+    #[serde(default)]
+    index: usize,
+    id: Option<String>,
+    function: Option<FunctionDelta>,
+}
+
+#[derive(Deserialize)]
+struct FunctionDelta {
+    name: Option<String>,
+    arguments: Option<String>,
+}
+
+#[async_trait]
+impl ChatBackend for OpenAiBackend {
+    async fn chat(
+        &self,
+        messages: &[ChatMessage],
+        tools: &[ToolSpec],
+        tokens: Option<&TokenSender>,
+    ) -> Result<ChatMessage> {
+        let url = format!("{}/chat/completions", self.base_url.trim_end_matches('/'));
+        let req = Request {
+            model: &self.model,
+            messages: messages.iter().map(wire_message).collect(),
+            tools: tools
+                .iter()
+                .map(|function| WireTool {
+                    kind: "function",
+                    function,
+                })
+                .collect(),
+            stream: true,
+        };
+        let resp = post_json(&self.client, &url, self.api_key.as_deref(), &req).await?;
+
+        let mut reply = String::new();
+        // (id, name, arguments) by tool-call index.
+        let mut calls: Vec<(String, String, String)> = Vec::new();
+        let mut lines = Lines::new(resp);
+        while let Some(line) = lines.next().await? {
+            // Blank lines separate events; `:` lines are keep-alive comments.
+            let Some(data) = line.strip_prefix("data:").map(str::trim) else {
+                continue;
+            };
+            if data == "[DONE]" {
+                break;
+            }
+            let chunk: Chunk = serde_json::from_str(data)
+                .with_context(|| format!("unexpected stream event: {data}"))?;
+            let Some(delta) = chunk.choices.into_iter().next().and_then(|c| c.delta) else {
+                continue;
+            };
+
+            if let Some(text) = delta.content {
+                send_token(tokens, &text).await;
+                reply.push_str(&text);
+            }
+            for piece in delta.tool_calls {
+                if calls.len() <= piece.index {
+                    calls.resize(piece.index + 1, Default::default());
+                }
+                let call = &mut calls[piece.index];
+                call.0.push_str(piece.id.as_deref().unwrap_or(""));
+                if let Some(function) = piece.function {
+                    call.1.push_str(function.name.as_deref().unwrap_or(""));
+                    call.2.push_str(function.arguments.as_deref().unwrap_or(""));
+                }
+            }
+        }
+
+        let mut message = ChatMessage::assistant(reply);
+        message.tool_calls = calls
+            .into_iter()
+            .filter(|(_, name, _)| !name.is_empty())
+            .map(|(id, name, args)| ToolCall {
+                id,
+                name,
+                arguments: parse_arguments(args),
+            })
+            .collect();
+        Ok(message)
+    }
+}
+
+#[derive(Serialize)]
+struct EmbedRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Deserialize)]
+struct EmbedResponse {
+    data: Vec<Embedding>,
+}
+
+#[derive(Deserialize)]
+struct Embedding {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[async_trait]
+impl EmbeddingBackend for OpenAiBackend {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let url = format!("{}/embeddings", self.base_url.trim_end_matches('/'));
+        let req = EmbedRequest {
+            model: &self.model,
+            input: texts,
+        };
+        let resp = post_json(&self.client, &url, self.api_key.as_deref(), &req).await?;
+        let body = resp.text().await?;
+        let mut parsed: EmbedResponse = serde_json::from_str(&body)
+            .with_context(|| format!("unexpected embeddings response from {url}"))?;
+
+        // Entries carry their input's index and aren't promised to be in order.
+        parsed.data.sort_by_key(|e| e.index);
+        if parsed.data.len() != texts.len() {
+            bail!(
+                "{url} returned {} embeddings for {} inputs",
+                parsed.data.len(),
+                texts.len()
+            );
+        }
+        Ok(parsed.data.into_iter().map(|e| e.embedding).collect())
+    }
+}
+
+/// No arguments at all reads as `{}`; invalid JSON is kept as a string for the caller to report.
+fn parse_arguments(args: String) -> Value {
+    if args.trim().is_empty() {
+        return Value::Object(Default::default());
+    }
+    serde_json::from_str(&args).unwrap_or(Value::String(args))
+}
+
+fn wire_message(m: &ChatMessage) -> WireMessage<'_> {
+    WireMessage {
+        role: m.role,
+        content: &m.content,
+        tool_calls: m
+            .tool_calls
+            .iter()
+            .map(|call| WireToolCall {
+                id: &call.id,
+                kind: "function",
+                function: WireFunction {
+                    name: &call.name,
+                    arguments: call.arguments.to_string(),
+                },
+            })
+            .collect(),
+        tool_call_id: m.tool_call_id.as_deref(),
+    }
+}
@@ -1,32 +1,326 @@
-mod ui;
-mod web;
+mod cli;
 
+use agent_bob::agent::{self, AgentConfig, LlmQueryExpander, Memory};
+use agent_bob::config::Settings;
+use agent_bob::index::{self, VectorIndex};
+use agent_bob::llm::{ChatBackend, ChatMessage};
+use agent_bob::session::{self, Session};
+use agent_bob::{mcp, serve, ui, web};
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
+use cli::{Cli, Command, Format};
 use std::error::Error;
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tracing_subscriber::EnvFilter;
+use web::{CrawlEvent, LlmCleanConfig, ProgressFn, QueryExpander, SearchHooks, StreamItem};
 
-fn handle(input: &str) -> String {
-    format!("User message: {}", input)
-}
+/// Search hits crawled per query in interactive mode, to keep answers quick.
+const INTERACTIVE_RESULTS_PER_QUERY: u32 = 2;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    let queries = vec![
-        "rust tokio JoinSet example".to_string(),
-        "html2md rust convert html to markdown".to_string(),
-    ];
-
-    let results_per_query = 3;
-    let pages = web::search(&queries, results_per_query).await?;
-
-    for p in pages {
-        println!("\n==============================");
-        println!("Query:  {}", p.query);
-        println!("Title:  {}", p.title.as_deref().unwrap_or("(none)"));
-        println!("URL:    {}", p.url);
-        println!("Status: {}", p.status);
-        println!("------------------------------\n");
-        println!("{}", p.markdown);
+    let cli = Cli::parse();
+    let settings = Settings::load(cli.config_file.as_deref())?;
+    let cfg = match cli.config(&settings) {
+        Ok(cfg) => cfg,
+        Err(e) => Cli::command().error(ErrorKind::ValueValidation, e).exit(),
+    };
+
+    // Drops and failures by default; -vv or RUST_LOG=agent_bob=debug for cleaning decisions.
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(cli.log_filter())),
+        )
+        .with_writer(std::io::stderr)
+        .init();
+
+    let hooks = SearchHooks {
+        progress: Some(progress_line()),
+        expander: cli.expand.then(|| cli.llm_config(&settings)).flatten().map(
+            |llm| -> Arc<dyn QueryExpander> {
+                Arc::new(LlmQueryExpander {
+                    backend: llm.backend(),
+                    variants: cfg.query_expansion.max_variants,
+                })
+            },
+        ),
+        ..Default::default()
+    };
+    let pages = match &cli.command {
+        None | Some(Command::Chat { .. }) => {
+            let resume = match &cli.command {
+                Some(Command::Chat { resume }) => resume.as_deref(),
+                _ => None,
+            };
+            return match cli.llm_config(&settings) {
+                Some(llm) => {
+                    let agent = AgentConfig {
+                        search: cfg,
+                        memory: memory(&cli, &settings)?,
+                        ..Default::default()
+                    };
+                    chat(llm.backend(), agent, session(resume)?, ui_config(&cli)).await
+                }
+                None if resume.is_some() => Err("--resume needs a --model to chat with".into()),
+                None => interactive(cfg, ui_config(&cli)).await,
+            };
+        }
+        Some(Command::Search {
+            queries,
+            results_per_query,
+            group_by_query: true,
+        }) => {
+            let groups = web::search_grouped(queries, *results_per_query, &cfg, &hooks).await?;
+            eprintln!();
+            match &cli.out {
+                Some(out) => cli::write_groups(out, &groups, cli.format)?,
+                None => print!("{}", cli::render_groups(&groups, cli.format)),
+            }
+            return Ok(());
+        }
+        Some(Command::Search {
+            queries,
+            results_per_query,
+            group_by_query: false,
+        }) => {
+            // Streamed so a consumer can start on the first page while the rest crawl.
+            if cli.format == Format::Ndjson && cli.out.is_none() {
+                let pages = web::search_stream(queries, *results_per_query, &cfg, &hooks);
+                return print_stream(pages).await;
+            }
+            web::search_with_hooks(queries, *results_per_query, &cfg, &hooks).await?
+        }
+        Some(Command::Fetch { urls }) => web::fetch_urls(urls, &cfg, &hooks).await?,
+        Some(Command::Mcp) => {
+            // Progress lines would only clutter the client's server log.
+            let hooks = SearchHooks {
+                progress: None,
+                ..hooks
+            };
+            return Ok(mcp::serve_stdio(cfg, hooks).await?);
+        }
+        Some(Command::Serve { port, host }) => {
+            let hooks = SearchHooks {
+                progress: None,
+                ..hooks
+            };
+            return Ok(serve::serve((*host, *port).into(), cfg, hooks).await?);
+        }
+    };
+    eprintln!();
+
+    match &cli.out {
+        Some(out) => cli::write_pages(out, &pages, cli.format)?,
+        None => print!("{}", cli::render_pages(&pages, cli.format)),
+    }
+
+    Ok(())
+}
+
+/// Print pages as NDJSON as they arrive.
+async fn print_stream(
+    mut items: mpsc::Receiver<anyhow::Result<StreamItem>>,
+) -> Result<(), Box<dyn Error>> {
+    let mut printed = 0usize;
+    let mut first_error = None;
+    while let Some(item) = items.recv().await {
+        match item? {
+            StreamItem::Page(page) => {
+                print!("{}", cli::render_pages(&[page], Format::Ndjson));
+                // Piped stdout is block-buffered; a consumer should see each page now.
+                std::io::stdout().flush()?;
+                printed += 1;
+            }
+            StreamItem::Failure(f) if f.reason.is_error() => {
+                first_error.get_or_insert(f);
+            }
+            _ => {}
+        }
+    }
+    eprintln!();
+    // Nothing but failures: fail with the first, rather than print nothing.
+    if printed == 0
+        && let Some(f) = first_error
+    {
+        return Err(format!("searching {} failed: {}", f.query, f.message).into());
     }
+    Ok(())
+}
+
+/// Search for each submitted line and preview the pages found, until quit.
+async fn interactive(cfg: LlmCleanConfig, mut ui_cfg: ui::UiConfig) -> Result<(), Box<dyn Error>> {
+    let progress = ui::Progress::default();
+    ui_cfg.progress = Some(progress.clone());
+    let cfg = Arc::new(cfg);
+    ui::run_streaming_with_config(&ui_cfg, move |input, tx: mpsc::Sender<String>| {
+        let cfg = cfg.clone();
+        let hooks = SearchHooks {
+            progress: Some(progress_status(progress.clone())),
+            ..Default::default()
+        };
+        async move {
+            let queries = cli::parse_queries(&input);
+            if queries.is_empty() {
+                return;
+            }
+            let _ = tx
+                .send(format!("*searching: {}*\n", queries.join("; ")))
+                .await;
+
+            let pages =
+                web::search_with_hooks(&queries, INTERACTIVE_RESULTS_PER_QUERY, &cfg, &hooks).await;
+            match pages {
+                Ok(pages) if pages.is_empty() => {
+                    let _ = tx.send("*no pages found*\n".to_string()).await;
+                }
+                Ok(pages) => {
+                    for p in &pages {
+                        let _ = tx.send(cli::render_page_preview(p) + "\n").await;
+                    }
+                }
+                Err(e) => {
+                    let _ = tx.send(format!("**search failed:** {e:#}\n")).await;
+                }
+            }
+        }
+    })
+    .await
+}
+
+/// Chat with `backend`, which may search the web before answering; replies
+/// stream into the scrollback. The conversation is kept for the whole session,
+/// and saved to `session` after every turn when there is one.
+async fn chat(
+    backend: Arc<dyn ChatBackend>,
+    cfg: AgentConfig,
+    session: Option<Session>,
+    mut ui_cfg: ui::UiConfig,
+) -> Result<(), Box<dyn Error>> {
+    let progress = ui::Progress::default();
+    ui_cfg.progress = Some(progress.clone());
+    let history = match &session {
+        Some(s) => {
+            if s.messages.len() > 1 {
+                ui_cfg.greeting = Some(s.transcript());
+            }
+            s.messages.clone()
+        }
+        None => vec![ChatMessage::system(agent::SYSTEM_PROMPT)],
+    };
+    let history = Arc::new(Mutex::new(history));
+    let session = Arc::new(Mutex::new(session));
+    let cfg = Arc::new(cfg);
 
+    let turns = session.clone();
+    ui::run_streaming_with_config(&ui_cfg, move |input, tx: mpsc::Sender<String>| {
+        let (backend, history, session) = (backend.clone(), history.clone(), turns.clone());
+        // A fresh counter per turn, so the status counts this turn's fetches.
+        let mut cfg = (*cfg).clone();
+        cfg.hooks.progress = Some(progress_status(progress.clone()));
+        async move {
+            let mut turn = history.lock().unwrap().clone();
+            turn.push(ChatMessage::user(input));
+            match agent::run_turn(backend.as_ref(), &mut turn, &cfg, Some(&tx)).await {
+                Ok(_) => {
+                    let saved = session.lock().unwrap().as_mut().map(|s| {
+                        s.messages = turn.clone();
+                        s.save()
+                    });
+                    *history.lock().unwrap() = turn;
+                    if let Some(Err(e)) = saved {
+                        let _ = tx
+                            .send(format!("\n**saving session failed:** {e:#}\n"))
+                            .await;
+                    }
+                }
+                // The failed turn is dropped, so the next one starts clean.
+                Err(e) => {
+                    let _ = tx.send(format!("\n**model error:** {e:#}\n")).await;
+                }
+            }
+        }
+    })
+    .await?;
+
+    if let Some(s) = session.lock().unwrap().as_ref()
+        && s.messages.len() > 1
+    {
+        eprintln!("resume with: agent-bob chat --resume {}", s.id);
+    }
     Ok(())
-    // ui::run(handle)
+}
+
+/// The session to save the chat to: `resume`d, or a new one. `None` when
+/// there's nowhere to keep it.
+fn session(resume: Option<&str>) -> Result<Option<Session>, Box<dyn Error>> {
+    let Some(dir) = session::default_sessions_dir() else {
+        return match resume {
+            Some(_) => Err("no session directory: neither XDG_DATA_HOME nor HOME is set".into()),
+            None => Ok(None),
+        };
+    };
+    Ok(Some(match resume {
+        Some(id) => Session::resume(&dir, id)?,
+        None => Session::create(&dir, vec![ChatMessage::system(agent::SYSTEM_PROMPT)]),
+    }))
+}
+
+/// The page index the agent searches, when an embedding model was given.
+fn memory(cli: &Cli, settings: &Settings) -> Result<Option<Memory>, Box<dyn Error>> {
+    let (Some(embed), Some(path)) = (cli.embed_config(settings), index::default_index_file())
+    else {
+        return Ok(None);
+    };
+    Ok(Some(Memory {
+        index: Arc::new(tokio::sync::Mutex::new(VectorIndex::open(&path)?)),
+        embedder: embed.embedder(),
+    }))
+}
+
+/// The prompt's look, with input history kept across sessions.
+fn ui_config(cli: &Cli) -> ui::UiConfig {
+    ui::UiConfig {
+        history_file: ui::default_history_file(),
+        screen: if cli.fullscreen {
+            ui::ScreenMode::FullScreen
+        } else {
+            ui::ScreenMode::Inline
+        },
+        ..Default::default()
+    }
+}
+
+/// Single-line fetch counter on stderr.
+fn progress_line() -> ProgressFn {
+    fetch_counter(|done, total| eprint!("\rfetched {done}/{total}"))
+}
+
+/// Fetch counts for the UI's status row.
+fn progress_status(progress: ui::Progress) -> ProgressFn {
+    fetch_counter(move |done, total| progress.set(format!("{done}/{total} pages fetched")))
+}
+
+/// Calls `report(finished, started)` as fetches start and finish.
+fn fetch_counter(report: impl Fn(usize, usize) + Send + Sync + 'static) -> ProgressFn {
+    let started = AtomicUsize::new(0);
+    let finished = AtomicUsize::new(0);
+
+    Arc::new(move |event: CrawlEvent| {
+        match event {
+            CrawlEvent::FetchStarted { .. } => {
+                started.fetch_add(1, Ordering::Relaxed);
+            }
+            CrawlEvent::FetchCompleted { .. } | CrawlEvent::FetchFailed { .. } => {
+                finished.fetch_add(1, Ordering::Relaxed);
+            }
+            _ => return,
+        }
+        report(
+            finished.load(Ordering::Relaxed),
+            started.load(Ordering::Relaxed),
+        );
+    })
 }
@@ -0,0 +1,284 @@
+//! A Model Context Protocol server over stdio: JSON-RPC 2.0, one message
+//! per line, exposing the web pipeline as the `web_search`, `fetch_url` and
+//! `crawl_site` tools.
+
+use crate::web::{self, LlmCleanConfig, MdPage, SearchHooks};
+use anyhow::Result;
+use serde_json::{Value, json};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+/// Spoken when the client doesn't ask for a version of its own.
+const PROTOCOL_VERSION: &str = "2024-11-05";
+
+const WEB_SEARCH: &str = "web_search";
+const FETCH_URL: &str = "fetch_url";
+const CRAWL_SITE: &str = "crawl_site";
+
+/// Search hits crawled per query unless the client asks for a number.
+const DEFAULT_RESULTS_PER_QUERY: u32 = 3;
+/// Link hops `crawl_site` follows unless the client asks for a number.
+const DEFAULT_CRAWL_DEPTH: u8 = 1;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// What the tools search and fetch with.
+struct Server {
+    cfg: LlmCleanConfig,
+    hooks: SearchHooks,
+}
+
+/// Serve MCP on stdin/stdout until stdin closes. Tool calls run
+/// concurrently; responses are written as they finish. Logs go to stderr,
+/// which stays out of the protocol.
+pub async fn serve_stdio(cfg: LlmCleanConfig, hooks: SearchHooks) -> Result<()> {
+    let server = Arc::new(Server { cfg, hooks });
+    let (tx, mut rx) = mpsc::channel::<Value>(16);
+
+    let writer = tokio::spawn(async move {
+        let mut stdout = tokio::io::stdout();
+        while let Some(msg) = rx.recv().await {
+            let mut line = msg.to_string();
+            line.push('\n');
+            stdout.write_all(line.as_bytes()).await?;
+            stdout.flush().await?;
+        }
+        anyhow::Ok(())
+    });
+
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let msg: Value = match serde_json::from_str(&line) {
+            Ok(msg) => msg,
+            Err(e) => {
+                let _ = tx
+                    .send(error(Value::Null, PARSE_ERROR, &e.to_string()))
+                    .await;
+                continue;
+            }
+        };
+        let server = server.clone();
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            if let Some(response) = server.handle(msg).await {
+                let _ = tx.send(response).await;
+            }
+        });
+    }
+
+    drop(tx);
+    writer.await??;
+    Ok(())
+}
+
+impl Server {
+    /// The response to `msg`; `None` for notifications, which get none.
+    async fn handle(&self, msg: Value) -> Option<Value> {
+        let id = msg.get("id").cloned();
+        let Some(method) = msg.get("method").and_then(Value::as_str) else {
+            // Responses need no answer; the server sends no requests anyway.
+            if msg.get("result").is_some() || msg.get("error").is_some() {
+                return None;
+            }
+            return id.map(|id| error(id, INVALID_REQUEST, "expected a method"));
+        };
+        let params = msg.get("params").cloned().unwrap_or(Value::Null);
+        debug!(method, "mcp request");
+
+        let result = match method {
+            "initialize" => Ok(json!({
+                "protocolVersion": params
+                    .get("protocolVersion")
+                    .and_then(Value::as_str)
+                    .unwrap_or(PROTOCOL_VERSION),
+                "capabilities": { "tools": {} },
+                "serverInfo": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+            })),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": tools() })),
+            "tools/call" => self.call_tool(&params).await,
+            _ if method.starts_with("notifications/") => return None,
+            _ => Err((METHOD_NOT_FOUND, format!("unknown method: {method}"))),
+        };
+
+        let id = id?;
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => error(id, code, &message),
+        })
+    }
+
+    /// Tool failures are results with `isError` set, so the model sees them;
+    /// only a call naming no known tool is a protocol error.
+    async fn call_tool(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let name = params.get("name").and_then(Value::as_str).unwrap_or("");
+        let args = params.get("arguments").cloned().unwrap_or(json!({}));
+
+        let pages = match name {
+            WEB_SEARCH => {
+                let queries = strings(&args, "queries", "query");
+                if queries.is_empty() {
+                    return Ok(tool_error("expected {\"queries\": [\"...\"]}"));
+                }
+                let n = args
+                    .get("results_per_query")
+                    .and_then(Value::as_u64)
+                    .map_or(DEFAULT_RESULTS_PER_QUERY, |n| n.clamp(1, 10) as u32);
+                web::search_with_hooks(&queries, n, &self.cfg, &self.hooks).await
+            }
+            FETCH_URL => {
+                let urls = strings(&args, "urls", "url");
+                if urls.is_empty() {
+                    return Ok(tool_error("expected {\"url\": \"https://...\"}"));
+                }
+                web::fetch_urls(&urls, &self.cfg, &self.hooks).await
+            }
+            CRAWL_SITE => {
+                let Some(url) = args.get("url").and_then(Value::as_str) else {
+                    return Ok(tool_error("expected {\"url\": \"https://...\"}"));
+                };
+                let depth = args
+                    .get("depth")
+                    .and_then(Value::as_u64)
+                    .map_or(DEFAULT_CRAWL_DEPTH, |d| d.min(3) as u8);
+                let mut cfg = self.cfg.clone();
+                if let Some(max) = args.get("max_pages").and_then(Value::as_u64) {
+                    cfg.max_pages = (max as usize).clamp(1, self.cfg.max_pages);
+                }
+                web::crawl_site(url, depth, &cfg).await
+            }
+            _ => return Err((INVALID_PARAMS, format!("unknown tool: {name}"))),
+        };
+
+        Ok(match pages {
+            Ok(pages) if pages.is_empty() => tool_text("no pages found", false),
+            Ok(pages) => tool_text(&render(&pages), false),
+            Err(e) => {
+                warn!(tool = name, error = %format!("{e:#}"), "mcp tool failed");
+                tool_error(&format!("{name} failed: {e:#}"))
+            }
+        })
+    }
+}
+
+fn tools() -> Value {
+    json!([
+        {
+            "name": WEB_SEARCH,
+            "description": "Search the web and return the top pages as cleaned markdown, \
+                            each with its URL and title in front matter.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "queries": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "One or more search queries, searched together."
+                    },
+                    "results_per_query": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "maximum": 10,
+                        "description": "Search hits to fetch per query (default 3)."
+                    }
+                },
+                "required": ["queries"]
+            }
+        },
+        {
+            "name": FETCH_URL,
+            "description": "Fetch pages by URL and return them as cleaned markdown.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "urls": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Absolute http(s) URLs."
+                    }
+                },
+                "required": ["urls"]
+            }
+        },
+        {
+            "name": CRAWL_SITE,
+            "description": "Fetch the pages of a documentation site or section: those its \
+                            sitemap lists under the URL, else those linked from it.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "url": {
+                        "type": "string",
+                        "description": "Where to start; only pages under this path are fetched."
+                    },
+                    "depth": {
+                        "type": "integer",
+                        "minimum": 0,
+                        "maximum": 3,
+                        "description": "Link hops to follow without a sitemap (default 1)."
+                    },
+                    "max_pages": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "description": "Pages to fetch at most."
+                    }
+                },
+                "required": ["url"]
+            }
+        }
+    ])
+}
+
+/// `plural` as a list of strings, also accepting a single string under
+/// either name.
+fn strings(args: &Value, plural: &str, singular: &str) -> Vec<String> {
+    let list: Vec<&str> = match args.get(plural).or_else(|| args.get(singular)) {
+        Some(Value::Array(items)) => items.iter().filter_map(Value::as_str).collect(),
+        Some(Value::String(s)) => vec![s.as_str()],
+        _ => Vec::new(),
+    };
+    list.into_iter()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Each page's markdown, front matter included, in result order.
+fn render(pages: &[MdPage]) -> String {
+    pages
+        .iter()
+        .map(|p| p.markdown.as_str())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn tool_text(text: &str, is_error: bool) -> Value {
+    json!({
+        "content": [{ "type": "text", "text": text }],
+        "isError": is_error,
+    })
+}
+
+fn tool_error(message: &str) -> Value {
+    tool_text(&format!("error: {message}"), true)
+}
+
+fn error(id: Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
@@ -0,0 +1,122 @@
+//! An HTTP API over the web pipeline: `POST /search` and `POST /fetch` take
+//! JSON and answer with the cleaned pages as a JSON array of [`MdPage`]s.
+
+use crate::web::{self, LlmCleanConfig, MdPage, SearchHooks};
+use anyhow::{Context, Result};
+use axum::Router;
+use axum::extract::{Json, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use serde::Deserialize;
+use serde_json::json;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tracing::{info, warn};
+
+/// Search hits crawled per query when a request doesn't say.
+const DEFAULT_RESULTS_PER_QUERY: u32 = 3;
+/// Most hits a request may ask for per query.
+const MAX_RESULTS_PER_QUERY: u32 = 10;
+
+/// What every request searches and fetches with.
+struct ApiState {
+    cfg: LlmCleanConfig,
+    hooks: SearchHooks,
+}
+
+/// `POST /search` body.
+#[derive(Debug, Deserialize)]
+struct SearchRequest {
+    queries: Vec<String>,
+    results_per_query: Option<u32>,
+}
+
+/// `POST /fetch` body.
+#[derive(Debug, Deserialize)]
+struct FetchRequest {
+    urls: Vec<String>,
+}
+
+/// Answered as `{"error": "..."}` with its status.
+struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "error": self.1 }))).into_response()
+    }
+}
+
+/// Serve the API on `addr` until Ctrl-C, then finish the requests in flight.
+pub async fn serve(addr: SocketAddr, cfg: LlmCleanConfig, hooks: SearchHooks) -> Result<()> {
+    let app = Router::new()
+        .route("/search", post(search))
+        .route("/fetch", post(fetch))
+        .with_state(Arc::new(ApiState { cfg, hooks }));
+
+    let listener = TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("failed to listen on {addr}"))?;
+    info!(%addr, "serving");
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await
+        .context("server failed")
+}
+
+async fn search(
+    State(state): State<Arc<ApiState>>,
+    Json(req): Json<SearchRequest>,
+) -> Result<Json<Vec<MdPage>>, ApiError> {
+    let queries = non_empty(req.queries);
+    if queries.is_empty() {
+        return Err(ApiError(
+            StatusCode::BAD_REQUEST,
+            "expected at least one query".to_string(),
+        ));
+    }
+    let n = req
+        .results_per_query
+        .map_or(DEFAULT_RESULTS_PER_QUERY, |n| {
+            n.clamp(1, MAX_RESULTS_PER_QUERY)
+        });
+    web::search_with_hooks(&queries, n, &state.cfg, &state.hooks)
+        .await
+        .map(Json)
+        .map_err(upstream)
+}
+
+async fn fetch(
+    State(state): State<Arc<ApiState>>,
+    Json(req): Json<FetchRequest>,
+) -> Result<Json<Vec<MdPage>>, ApiError> {
+    let urls = non_empty(req.urls);
+    if urls.is_empty() {
+        return Err(ApiError(
+            StatusCode::BAD_REQUEST,
+            "expected at least one url".to_string(),
+        ));
+    }
+    web::fetch_urls(&urls, &state.cfg, &state.hooks)
+        .await
+        .map(Json)
+        .map_err(upstream)
+}
+
+fn non_empty(items: Vec<String>) -> Vec<String> {
+    items
+        .into_iter()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Pages that merely failed are left out of the results, so an error here
+/// means every search failed or the config is unusable.
+fn upstream(e: anyhow::Error) -> ApiError {
+    warn!(error = %format!("{e:#}"), "request failed");
+    ApiError(StatusCode::BAD_GATEWAY, format!("{e:#}"))
+}
@@ -0,0 +1,121 @@
+use crate::llm::{ChatMessage, Role};
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// `--resume` without an id: the most recently saved session.
+pub const LAST_SESSION: &str = "last";
+
+/// `$XDG_DATA_HOME/agent-bob/sessions`, falling back to `~/.local/share/agent-bob/sessions`.
+pub fn default_sessions_dir() -> Option<PathBuf> {
+    Some(crate::data_dir()?.join("sessions"))
+}
+
+/// One chat conversation, tool calls and results included, saved as
+/// `<dir>/<id>.json` after every completed turn.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Session {
+    pub id: String,
+    /// Unix seconds.
+    pub created: u64,
+    pub messages: Vec<ChatMessage>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Session {
+    /// A new session in `dir`, starting from `messages` (usually the system
+    /// prompt). Nothing is written until the first `save`.
+    pub fn create(dir: &Path, messages: Vec<ChatMessage>) -> Self {
+        let created = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        // Ids are creation times; bump past any session started the same second.
+        let mut id = created.to_string();
+        let mut n = 1;
+        while dir.join(format!("{id}.json")).exists() {
+            id = format!("{created}-{n}");
+            n += 1;
+        }
+        Self {
+            path: dir.join(format!("{id}.json")),
+            id,
+            created,
+            messages,
+        }
+    }
+
+    /// Load session `id` from `dir`; [`LAST_SESSION`] picks the newest.
+    pub fn resume(dir: &Path, id: &str) -> Result<Self> {
+        let path = if id == LAST_SESSION {
+            newest(dir)?.with_context(|| format!("no saved sessions in {}", dir.display()))?
+        } else {
+            dir.join(format!("{id}.json"))
+        };
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("reading session {}", path.display()))?;
+        let mut session: Self = serde_json::from_str(&text)
+            .with_context(|| format!("parsing session {}", path.display()))?;
+        if session.messages.is_empty() {
+            bail!("session {} has no messages", session.id);
+        }
+        session.path = path;
+        Ok(session)
+    }
+
+    /// Rewrite the file through a temp file, so a crash leaves the last good copy.
+    pub fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string(self)?)
+            .with_context(|| format!("writing {}", tmp.display()))?;
+        fs::rename(&tmp, &self.path)
+            .with_context(|| format!("replacing {}", self.path.display()))?;
+        Ok(())
+    }
+
+    /// The conversation as markdown for the scrollback: user turns quoted,
+    /// tool calls as one italic line each, tool results and the system prompt left out.
+    pub fn transcript(&self) -> String {
+        let mut out = String::new();
+        for m in &self.messages {
+            match m.role {
+                Role::User => {
+                    for line in m.content.lines() {
+                        out.push_str(&format!("> {line}\n"));
+                    }
+                    out.push('\n');
+                }
+                Role::Assistant => {
+                    for call in &m.tool_calls {
+                        out.push_str(&format!("*{}: {}*\n", call.name, call.arguments));
+                    }
+                    if !m.content.trim().is_empty() {
+                        out.push_str(m.content.trim_end());
+                        out.push_str("\n\n");
+                    }
+                }
+                Role::System | Role::Tool => {}
+            }
+        }
+        out
+    }
+}
+
+fn newest(dir: &Path) -> Result<Option<PathBuf>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("reading {}", dir.display())),
+    };
+    Ok(entries
+        .flatten()
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|e| Some((e.metadata().ok()?.modified().ok()?, e.path())))
+        .max()
+        .map(|(_, path)| path))
+}
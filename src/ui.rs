@@ -1,24 +1,203 @@
-use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
-use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+use crossterm::cursor::Show;
+use crossterm::event::{
+    self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+    Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags, MouseEventKind,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::execute;
+use crossterm::terminal::{
+    self, EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode,
+};
 use ratatui::backend::CrosstermBackend;
-use ratatui::text::Line;
+use ratatui::layout::Rect;
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
 use ratatui::widgets::{Paragraph, Widget};
 use ratatui::{Frame, Terminal, TerminalOptions, Viewport};
+use std::cell::Cell;
+use std::ops::Range;
+use std::panic;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use std::{error::Error, io};
+use tokio::sync::mpsc;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
-const PROMPT: &str = "> ";
-const VIEWPORT_HEIGHT: u16 = 6;
+mod history;
+mod markdown;
+
+pub use history::default_history_file;
+use markdown::MarkdownRenderer;
+
+const MAX_HISTORY: usize = 100;
+/// How long to block on input or a pending handler before checking the other.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+const SPINNER_FRAME_MS: u128 = 80;
+/// Rows one mouse-wheel notch scrolls the transcript.
+const WHEEL_ROWS: isize = 3;
+/// Chunks a streaming handler can send ahead of the UI draining them.
+const STREAM_CAPACITY: usize = 64;
+
+/// How handler responses are drawn in the scrollback.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResponseFormat {
+    /// Headings, emphasis, lists and code styled; see `markdown::MarkdownRenderer`.
+    #[default]
+    Markdown,
+    /// Wrapped as-is, for handlers whose output isn't markdown.
+    Plain,
+}
+
+/// Where responses go.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ScreenMode {
+    /// Into the terminal's own scrollback, above a prompt at the bottom.
+    #[default]
+    Inline,
+    /// A scrollable transcript pane on the alternate screen (PageUp/PageDown,
+    /// mouse wheel), gone on exit. Mouse capture means selecting text needs
+    /// Shift held in most terminals.
+    FullScreen,
+}
+
+/// Look of the prompt and scrollback.
+#[derive(Debug, Clone)]
+pub struct UiConfig {
+    pub prompt: String,
+    /// Rows of the inline input area; clamped to the terminal height.
+    pub viewport_height: u16,
+    pub prompt_style: Style,
+    /// Text being typed, and its echo in the scrollback.
+    pub input_style: Style,
+    /// Base style for responses; markdown styling is layered on top.
+    pub response_style: Style,
+    pub response_format: ResponseFormat,
+    /// Where submitted inputs are kept across sessions; `None` keeps them in memory only.
+    pub history_file: Option<PathBuf>,
+    /// Shown in the scrollback before the first prompt, like a response;
+    /// e.g. a resumed conversation.
+    pub greeting: Option<String>,
+    pub screen: ScreenMode,
+    /// Shown after the spinner while a handler runs; the handler updates it.
+    pub progress: Option<Progress>,
+}
+
+/// A short status line ("3/9 pages fetched") a running handler can update.
+/// Clone it into the handler; it's cleared on each submission.
+#[derive(Debug, Clone, Default)]
+pub struct Progress(Arc<Mutex<String>>);
+
+impl Progress {
+    pub fn set(&self, status: impl Into<String>) {
+        *self.0.lock().unwrap() = status.into();
+    }
+
+    pub fn clear(&self) {
+        self.0.lock().unwrap().clear();
+    }
+
+    fn get(&self) -> String {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            prompt: "> ".to_string(),
+            viewport_height: 6,
+            prompt_style: Style::new(),
+            input_style: Style::new(),
+            response_style: Style::new(),
+            response_format: ResponseFormat::default(),
+            history_file: None,
+            greeting: None,
+            screen: ScreenMode::default(),
+            progress: None,
+        }
+    }
+}
+
+impl UiConfig {
+    /// Same width as `prompt`, so the input doesn't shift while a handler runs.
+    fn busy_prompt(&self, frame: char) -> String {
+        let width = self.prompt.width();
+        if width == 0 {
+            return String::new();
+        }
+        format!("{frame}{}", " ".repeat(width - 1))
+    }
+}
 
 #[derive(Default)]
 struct Model {
     input: String,
+    /// Char index into `input`; `input.chars().count()` when at the end.
+    cursor: usize,
+    /// Submitted inputs, oldest first.
+    history: Vec<String>,
+    /// Entry being shown while walking the history; `None` when editing fresh input.
+    history_pos: Option<usize>,
+    /// What was typed before history navigation started, restored past the newest entry.
+    draft: String,
+    /// When the running handler started; the prompt and status row show it.
+    busy_since: Option<Instant>,
+    /// Responses kept in-app, for `ScreenMode::FullScreen`.
+    transcript: Option<Transcript>,
+}
+
+/// The full-screen transcript and how far it's scrolled.
+#[derive(Default)]
+struct Transcript {
+    lines: Vec<Line<'static>>,
+    /// Rows scrolled up from the bottom; 0 follows new output.
+    scroll: usize,
+    /// Pane height at the last draw, for paging.
+    page: Cell<usize>,
+}
+
+impl Transcript {
+    fn push(&mut self, lines: Vec<Line<'static>>) {
+        // Keep the view still while scrolled back.
+        if self.scroll > 0 {
+            self.scroll += lines.len();
+        }
+        self.lines.extend(lines);
+    }
+
+    fn scroll_by(&mut self, delta: isize) {
+        let max = self.lines.len().saturating_sub(self.page.get());
+        self.scroll = self.scroll.saturating_add_signed(delta).min(max);
+    }
 }
 
 enum Msg {
     Input(char),
     Paste(String),
     Backspace,
+    Delete,
+    DeleteWordBack,
+    KillToStart,
+    KillToEnd,
+    Left,
+    Right,
+    WordLeft,
+    WordRight,
+    Home,
+    End,
+    HistoryPrev,
+    HistoryNext,
+    PageUp,
+    PageDown,
+    /// Mouse wheel, in rows; positive scrolls back.
+    Scroll(isize),
     Submit,
+    /// Ctrl+D: quits only on an empty, idle prompt, like a shell.
+    Eof,
     Quit,
 }
 
@@ -26,161 +205,719 @@ enum Cmd {
     Submit(String),
 }
 
-pub fn run(handler: fn(&str) -> String) -> Result<(), Box<dyn Error>> {
-    enable_raw_mode()?;
-    let stdout = io::stdout();
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::with_options(
-        backend,
-        TerminalOptions {
-            viewport: Viewport::Inline(VIEWPORT_HEIGHT),
-        },
-    )?;
+/// A boxed handler future, for handlers picked at runtime.
+pub type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+/// A type-erased `run_async` handler; `Box<dyn FnMut>` is itself a handler.
+pub type AsyncHandler = Box<dyn FnMut(String) -> BoxFuture<String> + Send>;
+
+/// Run the prompt with a blocking handler on a private runtime. The handler
+/// may keep state across submissions, e.g. a running transcript. Use
+/// `run_async` instead when already inside a tokio runtime.
+pub fn run<H>(handler: H) -> Result<(), Box<dyn Error>>
+where
+    H: FnMut(&str) -> String,
+{
+    run_with_config(&UiConfig::default(), handler)
+}
+
+/// `run` with a custom prompt, viewport and styles.
+pub fn run_with_config<H>(cfg: &UiConfig, mut handler: H) -> Result<(), Box<dyn Error>>
+where
+    H: FnMut(&str) -> String,
+{
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    rt.block_on(run_async_with_config(cfg, move |input: String| {
+        let response = handler(&input);
+        async move { response }
+    }))
+}
+
+/// Run the prompt, handing each submission to `handler`. The UI keeps taking
+/// keystrokes while a response is pending; Ctrl+C cancels it, and Enter is
+/// ignored until it finishes. Takes any async closure, or an [`AsyncHandler`].
+pub async fn run_async<F, Fut>(handler: F) -> Result<(), Box<dyn Error>>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = String>,
+{
+    run_async_with_config(&UiConfig::default(), handler).await
+}
+
+/// `run_async` with a custom prompt, viewport and styles.
+pub async fn run_async_with_config<F, Fut>(
+    cfg: &UiConfig,
+    mut handler: F,
+) -> Result<(), Box<dyn Error>>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = String>,
+{
+    run_streaming_with_config(cfg, move |input, tx: mpsc::Sender<String>| {
+        let response = handler(input);
+        async move {
+            let response = response.await;
+            if !response.trim().is_empty() {
+                let _ = tx.send(response).await;
+            }
+        }
+    })
+    .await
+}
+
+/// Like `run_async`, but the handler sends its response in chunks; each line
+/// is appended to the scrollback as soon as its newline arrives.
+pub async fn run_streaming<F, Fut>(handler: F) -> Result<(), Box<dyn Error>>
+where
+    F: FnMut(String, mpsc::Sender<String>) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    run_streaming_with_config(&UiConfig::default(), handler).await
+}
+
+/// `run_streaming` with a custom prompt, viewport and styles.
+pub async fn run_streaming_with_config<F, Fut>(
+    cfg: &UiConfig,
+    handler: F,
+) -> Result<(), Box<dyn Error>>
+where
+    F: FnMut(String, mpsc::Sender<String>) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    if cfg.viewport_height == 0 {
+        return Err("viewport_height must be at least 1".into());
+    }
+    let (_, rows) = terminal::size()?;
+    let height = cfg.viewport_height.min(rows.max(1));
+    let fullscreen = cfg.screen == ScreenMode::FullScreen;
 
-    let mut model = Model::default();
-    let res = run_app(&mut terminal, &mut model, handler);
+    let _guard = TerminalGuard::new(fullscreen)?;
 
-    disable_raw_mode()?;
-    terminal.show_cursor()?;
+    let backend = CrosstermBackend::new(io::stdout());
+    let options = TerminalOptions {
+        viewport: if fullscreen {
+            Viewport::Fullscreen
+        } else {
+            Viewport::Inline(height)
+        },
+    };
+    let mut terminal = Terminal::with_options(backend, options)?;
+    let mut model = Model {
+        history: cfg
+            .history_file
+            .as_deref()
+            .map(history::load)
+            .unwrap_or_default(),
+        transcript: fullscreen.then(Transcript::default),
+        ..Default::default()
+    };
+    let res = run_app(&mut terminal, &mut model, cfg, handler).await;
 
+    // Blank the prompt so the shell picks up right below the scrollback.
+    let _ = terminal.clear();
     res?;
     Ok(())
 }
 
-fn run_app(
+/// Like `run_streaming`, for handlers that start the work elsewhere (a spawned
+/// task, `web::search_stream`) and hand back its output channel. The response
+/// ends when every sender is dropped; cancelling drops the receiver, so the
+/// producer's next send fails and it can stop.
+pub async fn run_channel<F>(handler: F) -> Result<(), Box<dyn Error>>
+where
+    F: FnMut(String) -> mpsc::Receiver<String>,
+{
+    run_channel_with_config(&UiConfig::default(), handler).await
+}
+
+/// `run_channel` with a custom prompt, viewport and styles.
+pub async fn run_channel_with_config<F>(
+    cfg: &UiConfig,
+    mut handler: F,
+) -> Result<(), Box<dyn Error>>
+where
+    F: FnMut(String) -> mpsc::Receiver<String>,
+{
+    run_streaming_with_config(cfg, move |input, tx: mpsc::Sender<String>| {
+        let mut rx = handler(input);
+        async move {
+            while let Some(chunk) = rx.recv().await {
+                if tx.send(chunk).await.is_err() {
+                    break;
+                }
+            }
+        }
+    })
+    .await
+}
+
+/// Whether `TerminalGuard` pushed keyboard enhancement flags that need popping.
+static KEYBOARD_ENHANCED: AtomicBool = AtomicBool::new(false);
+/// Whether `TerminalGuard` switched to the alternate screen.
+static ALTERNATE_SCREEN: AtomicBool = AtomicBool::new(false);
+
+/// Raw mode and bracketed paste for as long as it lives; dropping it (on any
+/// return path) puts the terminal back.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn new(fullscreen: bool) -> io::Result<Self> {
+        enable_raw_mode()?;
+        if fullscreen {
+            execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+            ALTERNATE_SCREEN.store(true, Ordering::Relaxed);
+        }
+        // Without it a multi-line paste arrives as keystrokes and its newlines submit.
+        // Terminals that don't support it just ignore the sequence.
+        let _ = execute!(io::stdout(), EnableBracketedPaste);
+        // So Shift+Enter is told apart from Enter where the terminal can (kitty,
+        // WezTerm, foot, ...); elsewhere Alt+Enter still inserts a newline.
+        if terminal::supports_keyboard_enhancement().unwrap_or(false)
+            && execute!(
+                io::stdout(),
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES)
+            )
+            .is_ok()
+        {
+            KEYBOARD_ENHANCED.store(true, Ordering::Relaxed);
+        }
+        install_panic_hook();
+        Ok(Self)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore_terminal();
+    }
+}
+
+/// Undo the terminal modes `TerminalGuard` sets; safe to call more than once.
+fn restore_terminal() {
+    if KEYBOARD_ENHANCED.swap(false, Ordering::Relaxed) {
+        let _ = execute!(io::stdout(), PopKeyboardEnhancementFlags);
+    }
+    if ALTERNATE_SCREEN.swap(false, Ordering::Relaxed) {
+        let _ = execute!(io::stdout(), DisableMouseCapture, LeaveAlternateScreen);
+    }
+    let _ = execute!(io::stdout(), DisableBracketedPaste, Show);
+    let _ = disable_raw_mode();
+}
+
+/// Restore the terminal before the default hook prints, so a panic message
+/// isn't mangled by raw mode.
+fn install_panic_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        default_hook(info);
+    }));
+}
+
+/// A submission whose handler is still running.
+struct InFlight<Fut> {
+    started: Instant,
+    fut: Pin<Box<Fut>>,
+    rx: mpsc::Receiver<String>,
+    /// Streamed text after the last newline, held until its line completes.
+    partial: String,
+    /// `None` for `ResponseFormat::Plain`.
+    markdown: Option<MarkdownRenderer>,
+    style: Style,
+}
+
+impl<Fut> InFlight<Fut> {
+    fn render(&mut self, text: &str, width: u16) -> Vec<Line<'static>> {
+        let lines = match &mut self.markdown {
+            Some(markdown) => markdown.render(text, width),
+            None => wrap_plain_lines(text, width),
+        };
+        lines
+            .into_iter()
+            .map(|mut line| {
+                line.style = self.style.patch(line.style);
+                line
+            })
+            .collect()
+    }
+}
+
+async fn run_app<F, Fut>(
     terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
     model: &mut Model,
-    handler: fn(&str) -> String,
-) -> io::Result<()> {
+    cfg: &UiConfig,
+    mut handler: F,
+) -> io::Result<()>
+where
+    F: FnMut(String, mpsc::Sender<String>) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let mut in_flight: Option<InFlight<Fut>> = None;
+
+    if let Some(text) = &cfg.greeting {
+        let width = terminal.size()?.width;
+        let lines = match cfg.response_format {
+            ResponseFormat::Markdown => MarkdownRenderer::default().render(text, width),
+            ResponseFormat::Plain => wrap_plain_lines(text, width),
+        };
+        let lines = lines
+            .into_iter()
+            .map(|mut line| {
+                line.style = cfg.response_style.patch(line.style);
+                line
+            })
+            .collect();
+        insert_lines(terminal, model, lines)?;
+    }
+
     loop {
-        terminal.draw(|f| view(f, model))?;
+        model.busy_since = in_flight.as_ref().map(|req| req.started);
+        terminal.draw(|f| view(f, model, cfg))?;
+
+        if let Some(req) = in_flight.as_mut() {
+            // Drive the handler for one tick, then show whatever it streamed.
+            let done = tokio::time::timeout(POLL_INTERVAL, req.fut.as_mut())
+                .await
+                .is_ok();
+            while let Ok(chunk) = req.rx.try_recv() {
+                req.partial.push_str(&chunk);
+            }
 
-        if let Some(msg) = read_msg()? {
-            if matches!(msg, Msg::Quit) {
-                return Ok(());
+            let width = terminal.size()?.width;
+            if let Some(lines) = take_complete_lines(&mut req.partial) {
+                let lines = req.render(&lines, width);
+                insert_lines(terminal, model, lines)?;
+            }
+            if done {
+                if !req.partial.is_empty() {
+                    let partial = std::mem::take(&mut req.partial);
+                    let lines = req.render(&partial, width);
+                    insert_lines(terminal, model, lines)?;
+                }
+                in_flight = None;
             }
 
-            if let Some(cmd) = update(model, msg) {
-                run_cmd(terminal, cmd, handler)?;
+            if !event::poll(Duration::ZERO)? {
+                continue;
+            }
+        } else if !event::poll(POLL_INTERVAL)? {
+            continue;
+        }
+
+        let Some(msg) = read_msg()? else {
+            continue;
+        };
+        match msg {
+            Msg::Eof if model.input.is_empty() && in_flight.is_none() => return Ok(()),
+            // Ctrl+C / Esc cancel a pending response first, and only quit when idle.
+            Msg::Quit => {
+                let Some(mut req) = in_flight.take() else {
+                    return Ok(());
+                };
+                let width = terminal.size()?.width;
+                let mut lines = Vec::new();
+                if !req.partial.is_empty() {
+                    let partial = std::mem::take(&mut req.partial);
+                    lines = req.render(&partial, width);
+                }
+                lines.push(Line::from("(cancelled)"));
+                insert_lines(terminal, model, lines)?;
+            }
+            // One request at a time; the input stays put for later.
+            Msg::Submit if in_flight.is_some() => {}
+            msg => {
+                if let Some(Cmd::Submit(payload)) = update(model, msg) {
+                    if let Some(path) = &cfg.history_file {
+                        history::append(path, &payload);
+                    }
+                    let width = terminal.size()?.width;
+                    let echo = prompted_lines(cfg, &cfg.prompt, &payload, width);
+                    insert_lines(terminal, model, echo)?;
+
+                    if let Some(progress) = &cfg.progress {
+                        progress.clear();
+                    }
+                    let (tx, rx) = mpsc::channel(STREAM_CAPACITY);
+                    in_flight = Some(InFlight {
+                        started: Instant::now(),
+                        fut: Box::pin(handler(payload, tx)),
+                        rx,
+                        partial: String::new(),
+                        markdown: (cfg.response_format == ResponseFormat::Markdown)
+                            .then(MarkdownRenderer::default),
+                        style: cfg.response_style,
+                    });
+                }
             }
         }
     }
 }
 
-fn view(f: &mut Frame, model: &Model) {
-    let area = f.area();
-    let wrapped = wrap_prompted_lines(PROMPT, &model.input, area.width);
-    let line_count = wrapped.lines.len().max(1);
-    let scroll = line_count.saturating_sub(area.height as usize);
-    let input = Paragraph::new(wrapped.lines).scroll((scroll as u16, 0));
+fn view(f: &mut Frame, model: &Model, cfg: &UiConfig) {
+    let mut area = f.area();
+    if let Some(transcript) = &model.transcript {
+        let input_height = cfg
+            .viewport_height
+            .min(area.height.saturating_sub(1))
+            .max(1);
+        let pane = Rect {
+            height: area.height - input_height,
+            ..area
+        };
+        area = Rect {
+            y: area.y + pane.height,
+            height: input_height,
+            ..area
+        };
+        view_transcript(f, transcript, pane);
+    }
+
+    let busy_prompt;
+    let prompt = match model.busy_since {
+        Some(since) => {
+            let elapsed = since.elapsed();
+            let frame = SPINNER[(elapsed.as_millis() / SPINNER_FRAME_MS) as usize % SPINNER.len()];
+            // The bottom row turns into a status line, when there are rows to spare.
+            if area.height >= 2 {
+                area.height -= 1;
+                let mut status = format!("{frame} {:.1}s", elapsed.as_secs_f32());
+                let progress = cfg.progress.as_ref().map(Progress::get);
+                if let Some(progress) = progress.filter(|p| !p.is_empty()) {
+                    status.push_str(" · ");
+                    status.push_str(&progress);
+                }
+                let row = Rect {
+                    y: area.y + area.height,
+                    height: 1,
+                    ..area
+                };
+                f.render_widget(
+                    Paragraph::new(Line::styled(
+                        status,
+                        Style::new().add_modifier(Modifier::DIM),
+                    )),
+                    row,
+                );
+            }
+            busy_prompt = cfg.busy_prompt(frame);
+            &busy_prompt
+        }
+        None => &cfg.prompt,
+    };
+    let prompt_width = prompt.width() as u16;
+    let lines = prompted_lines(cfg, prompt, &model.input, area.width);
+    let content_width = area.width.saturating_sub(prompt_width).max(1) as usize;
+    let (row, col) = cursor_position(&model.input, model.cursor, content_width);
+
+    // Scroll just enough to keep the cursor row on screen.
+    let scroll = row.saturating_sub((area.height as usize).saturating_sub(1));
+    let input = Paragraph::new(lines).scroll((scroll as u16, 0));
     f.render_widget(input, area);
 
-    let cursor_line = line_count.saturating_sub(1).saturating_sub(scroll);
-    let cursor_y = area.y + cursor_line as u16;
-    let cursor_x = area.x + PROMPT.len() as u16 + wrapped.last_len as u16;
+    let cursor_y = area.y + (row - scroll) as u16;
+    let cursor_x = area.x + (prompt_width + col as u16).min(area.width.saturating_sub(1));
     if cursor_x < area.x + area.width && cursor_y < area.y + area.height {
         f.set_cursor_position((cursor_x, cursor_y));
     }
 }
 
+/// The transcript rows that fit `pane`, `scroll` rows up from the end, with
+/// a marker over the last row while scrolled back.
+fn view_transcript(f: &mut Frame, transcript: &Transcript, pane: Rect) {
+    let height = pane.height as usize;
+    transcript.page.set(height);
+
+    let end = transcript.lines.len().saturating_sub(transcript.scroll);
+    let start = end.saturating_sub(height);
+    let mut lines = transcript.lines[start..end].to_vec();
+    if transcript.scroll > 0 && !lines.is_empty() {
+        lines.pop();
+        lines.push(Line::styled(
+            format!("-- {} more rows below (PageDown) --", transcript.scroll + 1),
+            Style::new().add_modifier(Modifier::REVERSED),
+        ));
+    }
+    f.render_widget(Paragraph::new(lines), pane);
+}
+
 fn read_msg() -> io::Result<Option<Msg>> {
     match event::read()? {
-        Event::Key(key) => {
-            if !matches!(key.kind, KeyEventKind::Press | KeyEventKind::Repeat) {
-                return Ok(None);
-            }
-            let msg = match key.code {
-                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Msg::Quit,
-                KeyCode::Enter => Msg::Submit,
-                KeyCode::Char(c) => {
-                    if key.modifiers.contains(KeyModifiers::CONTROL)
-                        || key.modifiers.contains(KeyModifiers::ALT)
-                    {
-                        return Ok(None);
-                    }
-                    Msg::Input(c)
-                }
-                KeyCode::Backspace => Msg::Backspace,
-                _ => return Ok(None),
-            };
-            Ok(Some(msg))
-        }
+        Event::Key(key) => Ok(key_msg(key)),
         Event::Paste(text) => Ok(Some(Msg::Paste(text))),
+        Event::Mouse(mouse) => Ok(match mouse.kind {
+            MouseEventKind::ScrollUp => Some(Msg::Scroll(WHEEL_ROWS)),
+            MouseEventKind::ScrollDown => Some(Msg::Scroll(-WHEEL_ROWS)),
+            _ => None,
+        }),
         _ => Ok(None),
     }
 }
 
+/// The action a key press maps to; `None` for releases and unbound keys.
+fn key_msg(key: KeyEvent) -> Option<Msg> {
+    if !matches!(key.kind, KeyEventKind::Press | KeyEventKind::Repeat) {
+        return None;
+    }
+    let msg = match key.code {
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Msg::Quit,
+        KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => Msg::Eof,
+        KeyCode::Esc => Msg::Quit,
+        // Most terminals only report Shift+Enter with keyboard enhancement enabled.
+        KeyCode::Enter
+            if key
+                .modifiers
+                .intersects(KeyModifiers::ALT | KeyModifiers::SHIFT) =>
+        {
+            Msg::Input('\n')
+        }
+        KeyCode::Enter => Msg::Submit,
+        KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::CONTROL) => match c {
+            'a' => Msg::Home,
+            'e' => Msg::End,
+            'w' => Msg::DeleteWordBack,
+            'u' => Msg::KillToStart,
+            'k' => Msg::KillToEnd,
+            _ => return None,
+        },
+        KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::ALT) => match c {
+            'b' => Msg::WordLeft,
+            'f' => Msg::WordRight,
+            _ => return None,
+        },
+        KeyCode::Char(c) => Msg::Input(c),
+        KeyCode::Backspace if key.modifiers.contains(KeyModifiers::ALT) => Msg::DeleteWordBack,
+        KeyCode::Backspace => Msg::Backspace,
+        KeyCode::Delete => Msg::Delete,
+        KeyCode::Left | KeyCode::Right
+            if key
+                .modifiers
+                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+        {
+            if key.code == KeyCode::Left {
+                Msg::WordLeft
+            } else {
+                Msg::WordRight
+            }
+        }
+        KeyCode::Left => Msg::Left,
+        KeyCode::Right => Msg::Right,
+        KeyCode::Home => Msg::Home,
+        KeyCode::End => Msg::End,
+        KeyCode::Up => Msg::HistoryPrev,
+        KeyCode::Down => Msg::HistoryNext,
+        KeyCode::PageUp => Msg::PageUp,
+        KeyCode::PageDown => Msg::PageDown,
+        _ => return None,
+    };
+    Some(msg)
+}
+
 fn update(model: &mut Model, msg: Msg) -> Option<Cmd> {
     match msg {
         Msg::Input(ch) => {
-            push_input_char(&mut model.input, ch);
+            insert_input_char(model, ch);
             None
         }
         Msg::Paste(text) => {
-            push_input_str(&mut model.input, &text);
+            for ch in text.replace("\r\n", "\n").chars() {
+                insert_input_char(model, ch);
+            }
             None
         }
         Msg::Backspace => {
-            model.input.pop();
+            delete_chars(model, model.cursor.saturating_sub(1), model.cursor);
+            None
+        }
+        Msg::Delete => {
+            delete_chars(model, model.cursor, model.cursor + 1);
+            None
+        }
+        Msg::DeleteWordBack => {
+            let start = word_start_before(&model.input, model.cursor);
+            delete_chars(model, start, model.cursor);
+            None
+        }
+        Msg::KillToStart => {
+            delete_chars(model, 0, model.cursor);
+            None
+        }
+        Msg::KillToEnd => {
+            delete_chars(model, model.cursor, usize::MAX);
+            None
+        }
+        Msg::Left => {
+            model.cursor = model.cursor.saturating_sub(1);
+            None
+        }
+        Msg::Right => {
+            model.cursor = (model.cursor + 1).min(model.input.chars().count());
+            None
+        }
+        Msg::WordLeft => {
+            model.cursor = word_start_before(&model.input, model.cursor);
+            None
+        }
+        Msg::WordRight => {
+            model.cursor = word_end_after(&model.input, model.cursor);
+            None
+        }
+        Msg::Home => {
+            model.cursor = 0;
+            None
+        }
+        Msg::End => {
+            model.cursor = model.input.chars().count();
+            None
+        }
+        Msg::HistoryPrev => {
+            let pos = match model.history_pos {
+                _ if model.history.is_empty() => return None,
+                None => {
+                    model.draft = std::mem::take(&mut model.input);
+                    model.history.len() - 1
+                }
+                Some(pos) => pos.saturating_sub(1),
+            };
+            model.history_pos = Some(pos);
+            set_input(model, model.history[pos].clone());
+            None
+        }
+        Msg::HistoryNext => {
+            match model.history_pos {
+                None => {}
+                Some(pos) if pos + 1 < model.history.len() => {
+                    model.history_pos = Some(pos + 1);
+                    set_input(model, model.history[pos + 1].clone());
+                }
+                Some(_) => {
+                    model.history_pos = None;
+                    let draft = std::mem::take(&mut model.draft);
+                    set_input(model, draft);
+                }
+            }
             None
         }
         Msg::Submit => {
-            let payload = std::mem::take(&mut model.input);
-            if payload.trim().is_empty() {
+            model.cursor = 0;
+            model.history_pos = None;
+            model.draft.clear();
+            let input = std::mem::take(&mut model.input);
+            // Outer whitespace only; blank lines inside a composed prompt are kept.
+            let payload = input.trim().to_string();
+            if payload.is_empty() {
                 None
             } else {
+                push_history(&mut model.history, &payload);
                 Some(Cmd::Submit(payload))
             }
         }
-        Msg::Quit => None,
+        Msg::PageUp | Msg::PageDown | Msg::Scroll(_) => {
+            if let Some(t) = &mut model.transcript {
+                let page = t.page.get().saturating_sub(1).max(1) as isize;
+                t.scroll_by(match msg {
+                    Msg::PageUp => page,
+                    Msg::PageDown => -page,
+                    Msg::Scroll(rows) => rows,
+                    _ => unreachable!(),
+                });
+            }
+            None
+        }
+        Msg::Eof | Msg::Quit => None,
     }
 }
 
-fn run_cmd(
+/// Split off everything before the last newline in `partial`, if there is one.
+fn take_complete_lines(partial: &mut String) -> Option<String> {
+    let end = partial.rfind('\n')?;
+    let rest = partial.split_off(end + 1);
+    let mut lines = std::mem::replace(partial, rest);
+    lines.pop();
+    Some(lines)
+}
+
+/// Add `lines` to the scrollback, or the transcript in full-screen mode.
+fn insert_lines(
     terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
-    cmd: Cmd,
-    handler: fn(&str) -> String,
+    model: &mut Model,
+    lines: Vec<Line<'static>>,
 ) -> io::Result<()> {
-    match cmd {
-        Cmd::Submit(payload) => {
-            let width = terminal.size()?.width;
-            let mut lines = wrap_prompted_lines(PROMPT, &payload, width).lines;
-
-            let response = handler(&payload);
-            if !response.trim().is_empty() {
-                lines.extend(wrap_plain_lines(&response, width));
-            }
-
-            let height = lines.len().max(1) as u16;
-            terminal.insert_before(height, move |buf| {
-                Paragraph::new(lines).render(buf.area, buf);
-            })?;
-        }
+    if lines.is_empty() {
+        return Ok(());
     }
-    Ok(())
+    if let Some(transcript) = &mut model.transcript {
+        transcript.push(lines);
+        return Ok(());
+    }
+    let height = lines.len() as u16;
+    terminal.insert_before(height, move |buf| {
+        Paragraph::new(lines).render(buf.area, buf);
+    })
 }
 
-struct WrappedLines {
-    lines: Vec<Line<'static>>,
-    last_len: usize,
+/// `text` after `prompt` in the configured styles, wrapped with continuation
+/// rows indented to the prompt's display width.
+fn prompted_lines(cfg: &UiConfig, prompt: &str, text: &str, width: u16) -> Vec<Line<'static>> {
+    wrap_prompted_lines(prompt, text, width)
+        .into_iter()
+        .map(|(head, line)| {
+            Line::from(vec![
+                Span::styled(head, cfg.prompt_style),
+                Span::styled(line, cfg.input_style),
+            ])
+        })
+        .collect()
 }
 
-fn wrap_prompted_lines(prefix: &str, text: &str, width: u16) -> WrappedLines {
+fn wrap_prompted_lines(prefix: &str, text: &str, width: u16) -> Vec<(String, String)> {
     let width = width.max(1) as usize;
-    let prefix_len = prefix.len();
+    let prefix_len = prefix.width();
     let content_width = width.saturating_sub(prefix_len).max(1);
 
     let content_lines = wrap_text(text, content_width);
-    let last_len = content_lines.last().map(|line| line.len()).unwrap_or(0);
     let mut lines = Vec::with_capacity(content_lines.len());
     let indent = " ".repeat(prefix_len);
 
-    for (idx, line) in content_lines.iter().enumerate() {
+    for (idx, line) in content_lines.into_iter().enumerate() {
         let head = if idx == 0 { prefix } else { &indent };
-        lines.push(Line::from(format!("{head}{line}")));
+        lines.push((head.to_string(), line));
     }
 
-    WrappedLines { lines, last_len }
+    lines
+}
+
+/// Row and column (in cells) of char index `cursor` in `wrap_text(text, width)`.
+/// A cursor right after a full row sits at the start of the next one, unless a
+/// newline follows, in which case it stays past the end of that row.
+fn cursor_position(text: &str, cursor: usize, width: usize) -> (usize, usize) {
+    let width = width.max(1);
+    let at = byte_offset(text, cursor);
+    let mut row = 0;
+    let mut line_start = 0;
+    let mut raw_lines = text.split('\n').peekable();
+
+    while let Some(raw_line) = raw_lines.next() {
+        let rows = wrap_ranges(raw_line, width);
+        if at <= line_start + raw_line.len() {
+            let offset = at - line_start;
+            let (idx, range) = rows
+                .iter()
+                .enumerate()
+                .rfind(|(_, r)| r.start <= offset)
+                .expect("first row starts at 0");
+            let col = raw_line[range.start..offset].width();
+            if offset == raw_line.len() && col >= width && raw_lines.peek().is_none() {
+                return (row + idx + 1, 0);
+            }
+            return (row + idx, col);
+        }
+        row += rows.len();
+        line_start += raw_line.len() + 1;
+    }
+
+    (row, 0)
 }
 
 fn wrap_plain_lines(text: &str, width: u16) -> Vec<Line<'static>> {
@@ -190,50 +927,302 @@ fn wrap_plain_lines(text: &str, width: u16) -> Vec<Line<'static>> {
 
 fn wrap_text(text: &str, width: usize) -> Vec<String> {
     let width = width.max(1);
-    let mut lines = Vec::new();
-    for raw_line in text.split('\n') {
-        lines.extend(split_to_width(raw_line, width));
-    }
-    if lines.is_empty() {
-        lines.push(String::new());
-    }
-    lines
+    text.split('\n')
+        .flat_map(|raw_line| {
+            wrap_ranges(raw_line, width)
+                .into_iter()
+                .map(move |r| raw_line[r].to_string())
+        })
+        .collect()
 }
 
-fn split_to_width(text: &str, width: usize) -> Vec<String> {
-    let mut lines = Vec::new();
-    let mut current = String::new();
-    let mut current_len = 0;
+/// Byte ranges of the rows `line` wraps to at `width` display cells. Breaks
+/// after whitespace when it can and mid-word only for words wider than a row;
+/// whitespace at a break stays at the end of the row it follows.
+fn wrap_ranges(line: &str, width: usize) -> Vec<Range<usize>> {
+    let mut rows = Vec::new();
+    let mut start = 0;
+    let mut row_width = 0;
+    // Byte index just past the last whitespace in the row, and the row width there.
+    let mut soft_break: Option<(usize, usize)> = None;
 
-    for ch in text.chars() {
-        current.push(ch);
-        current_len += 1;
-        if current_len >= width {
-            lines.push(current);
-            current = String::new();
-            current_len = 0;
+    for (i, ch) in line.char_indices() {
+        let w = ch.width().unwrap_or(0);
+        if ch.is_whitespace() {
+            row_width += w;
+            soft_break = Some((i + ch.len_utf8(), row_width));
+            continue;
         }
-    }
 
-    if lines.is_empty() {
-        lines.push(current);
-    } else if !current.is_empty() {
-        lines.push(current);
+        while row_width > 0 && row_width + w > width {
+            match soft_break.take() {
+                Some((at, width_at)) if at > start => {
+                    rows.push(start..at);
+                    start = at;
+                    row_width -= width_at;
+                }
+                _ => {
+                    rows.push(start..i);
+                    start = i;
+                    row_width = 0;
+                }
+            }
+        }
+        row_width += w;
     }
 
-    lines
+    rows.push(start..line.len());
+    rows
+}
+
+fn set_input(model: &mut Model, input: String) {
+    model.cursor = input.chars().count();
+    model.input = input;
+}
+
+fn push_history(history: &mut Vec<String>, entry: &str) {
+    if history.last().is_some_and(|last| last == entry) {
+        return;
+    }
+    history.push(entry.to_string());
+    if history.len() > MAX_HISTORY {
+        history.remove(0);
+    }
 }
 
-fn push_input_char(input: &mut String, ch: char) {
+fn insert_input_char(model: &mut Model, ch: char) {
     let normalized = match ch {
-        '\n' | '\r' => ' ',
+        '\r' => '\n',
         _ => ch,
     };
-    input.push(normalized);
+    let at = byte_offset(&model.input, model.cursor);
+    model.input.insert(at, normalized);
+    model.cursor += 1;
+}
+
+/// Remove chars `start..end` (clamped to the input) and park the cursor at `start`.
+fn delete_chars(model: &mut Model, start: usize, end: usize) {
+    let len = model.input.chars().count();
+    let (start, end) = (start.min(len), end.min(len));
+    if start < end {
+        let range = byte_offset(&model.input, start)..byte_offset(&model.input, end);
+        model.input.replace_range(range, "");
+    }
+    model.cursor = start;
+}
+
+/// Char index where Ctrl+W stops: skip whitespace left of `cursor`, then one
+/// run of either word characters or punctuation.
+fn word_start_before(text: &str, cursor: usize) -> usize {
+    let chars: Vec<char> = text.chars().take(cursor).collect();
+    let mut i = chars.len();
+    while i > 0 && chars[i - 1].is_whitespace() {
+        i -= 1;
+    }
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    if let Some(&last) = i.checked_sub(1).and_then(|j| chars.get(j)) {
+        let word = is_word(last);
+        while i > 0 && !chars[i - 1].is_whitespace() && is_word(chars[i - 1]) == word {
+            i -= 1;
+        }
+    }
+    i
 }
 
-fn push_input_str(input: &mut String, text: &str) {
-    for ch in text.chars() {
-        push_input_char(input, ch);
+/// Mirror of `word_start_before`: skip whitespace right of `cursor`, then one
+/// run of either word characters or punctuation.
+fn word_end_after(text: &str, cursor: usize) -> usize {
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = cursor.min(chars.len());
+    while i < chars.len() && chars[i].is_whitespace() {
+        i += 1;
+    }
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    if let Some(&first) = chars.get(i) {
+        let word = is_word(first);
+        while i < chars.len() && !chars[i].is_whitespace() && is_word(chars[i]) == word {
+            i += 1;
+        }
+    }
+    i
+}
+
+/// Byte offset of char index `idx`, so edits never split a multi-byte char.
+fn byte_offset(text: &str, idx: usize) -> usize {
+    text.char_indices()
+        .nth(idx)
+        .map(|(i, _)| i)
+        .unwrap_or(text.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A model holding `input` with the cursor at char index `cursor`.
+    fn editing(input: &str, cursor: usize) -> Model {
+        Model {
+            input: input.to_string(),
+            cursor,
+            ..Default::default()
+        }
+    }
+
+    fn press(model: &mut Model, code: KeyCode, modifiers: KeyModifiers) -> Option<Cmd> {
+        let msg = key_msg(KeyEvent::new(code, modifiers)).expect("key should be bound");
+        update(model, msg)
+    }
+
+    fn ctrl(model: &mut Model, c: char) {
+        assert!(press(model, KeyCode::Char(c), KeyModifiers::CONTROL).is_none());
+    }
+
+    #[test]
+    fn ctrl_w_deletes_the_word_before_the_cursor() {
+        let mut model = editing("cargo build --release  ", 23);
+        ctrl(&mut model, 'w');
+        assert_eq!((model.input.as_str(), model.cursor), ("cargo build --", 14));
+        // Punctuation is its own run.
+        ctrl(&mut model, 'w');
+        assert_eq!((model.input.as_str(), model.cursor), ("cargo build ", 12));
+    }
+
+    #[test]
+    fn ctrl_w_keeps_text_after_the_cursor() {
+        let mut model = editing("héllo wörld", 5);
+        ctrl(&mut model, 'w');
+        assert_eq!((model.input.as_str(), model.cursor), (" wörld", 0));
+    }
+
+    #[test]
+    fn ctrl_u_kills_to_start() {
+        let mut model = editing("fix the bug", 8);
+        ctrl(&mut model, 'u');
+        assert_eq!((model.input.as_str(), model.cursor), ("bug", 0));
+    }
+
+    #[test]
+    fn ctrl_k_kills_to_end() {
+        let mut model = editing("fix the bug", 3);
+        ctrl(&mut model, 'k');
+        assert_eq!((model.input.as_str(), model.cursor), ("fix", 3));
+    }
+
+    #[test]
+    fn ctrl_a_and_ctrl_e_move_to_the_ends() {
+        let mut model = editing("日本語 text", 2);
+        ctrl(&mut model, 'a');
+        assert_eq!(model.cursor, 0);
+        ctrl(&mut model, 'e');
+        assert_eq!(model.cursor, 8);
+        assert_eq!(model.input, "日本語 text");
+    }
+
+    #[test]
+    fn alt_backspace_deletes_the_word_before_the_cursor() {
+        let mut model = editing("open src/main.rs", 16);
+        assert!(press(&mut model, KeyCode::Backspace, KeyModifiers::ALT).is_none());
+        assert_eq!((model.input.as_str(), model.cursor), ("open src/main.", 14));
+        assert!(press(&mut model, KeyCode::Backspace, KeyModifiers::NONE).is_none());
+        assert_eq!((model.input.as_str(), model.cursor), ("open src/main", 13));
+    }
+
+    #[test]
+    fn key_releases_are_ignored() {
+        let mut release = KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE);
+        release.kind = KeyEventKind::Release;
+        assert!(key_msg(release).is_none());
+    }
+
+    /// Wrap `text` after `prefix`, checking every row fits and nothing is lost.
+    fn wrap_checked(prefix: &str, text: &str, width: u16) -> Vec<String> {
+        let rows = wrap_prompted_lines(prefix, text, width);
+        for (head, line) in &rows {
+            assert_eq!(head.width(), prefix.width());
+            assert!(
+                head.width() + line.trim_end().width() <= width as usize,
+                "{line:?} overflows {width}"
+            );
+        }
+        let lines: Vec<String> = rows.into_iter().map(|(_, line)| line).collect();
+        assert_eq!(lines.concat(), text);
+        lines
+    }
+
+    #[test]
+    fn wraps_english_at_spaces() {
+        let text = "The quick brown fox jumps over the lazy dog near the riverbank.";
+        let lines = wrap_checked("> ", text, 22);
+        assert_eq!(
+            lines,
+            [
+                "The quick brown fox ",
+                "jumps over the lazy ",
+                "dog near the ",
+                "riverbank.",
+            ]
+        );
+    }
+
+    #[test]
+    fn wraps_cjk_by_display_width() {
+        // Every char is two cells wide and a valid break point.
+        let text = "日本語のテキストを折り返す";
+        let lines = wrap_checked("> ", text, 12);
+        assert_eq!(lines, ["日本語のテ", "キストを折", "り返す"]);
+        assert!(lines.iter().all(|l| l.width() <= 10));
+    }
+
+    #[test]
+    fn wraps_pasted_emoji_without_splitting_chars() {
+        let text = "ship it 🚀🚀🚀🚀🚀🚀 then 🎉";
+        let lines = wrap_checked("> ", text, 10);
+        assert_eq!(lines, ["ship it ", "🚀🚀🚀🚀", "🚀🚀 ", "then 🎉"]);
+    }
+
+    #[test]
+    fn breaks_long_tokens_mid_word() {
+        let token = "a".repeat(25);
+        let text = format!("see {token} ok");
+        let lines = wrap_checked("$ ", &text, 12);
+        assert_eq!(lines, ["see ", "aaaaaaaaaa", "aaaaaaaaaa", "aaaaa ok"]);
+    }
+
+    #[test]
+    fn continuation_rows_indent_to_the_prompt() {
+        let rows = wrap_prompted_lines("❯ ", "one two three", 8);
+        let heads: Vec<&str> = rows.iter().map(|(head, _)| head.as_str()).collect();
+        assert_eq!(heads, ["❯ ", "  ", "  "]);
+    }
+
+    #[test]
+    fn keeps_explicit_newlines() {
+        assert_eq!(wrap_text("a\n\nb", 10), ["a", "", "b"]);
+    }
+
+    #[test]
+    fn pasting_newlines_inserts_without_submitting() {
+        let mut model = editing("fix: ", 5);
+        let cmd = update(
+            &mut model,
+            Msg::Paste("line one\r\nline two\n\nline four\n".into()),
+        );
+        assert!(cmd.is_none());
+        assert_eq!(model.input, "fix: line one\nline two\n\nline four\n");
+        assert_eq!(model.cursor, model.input.chars().count());
+        assert!(model.history.is_empty());
+
+        // Enter afterwards submits the whole composed prompt at once.
+        let Some(Cmd::Submit(payload)) = update(&mut model, Msg::Submit) else {
+            panic!("Enter should submit");
+        };
+        assert_eq!(payload, "fix: line one\nline two\n\nline four");
+    }
+
+    #[test]
+    fn pasting_mid_input_keeps_the_tail() {
+        let mut model = editing("ab", 1);
+        assert!(update(&mut model, Msg::Paste("1\n2".into())).is_none());
+        assert_eq!((model.input.as_str(), model.cursor), ("a1\n2b", 4));
     }
 }
@@ -0,0 +1,63 @@
+use super::{MAX_HISTORY, push_history};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// `$XDG_DATA_HOME/agent-bob/history`, falling back to `~/.local/share/agent-bob/history`.
+pub fn default_history_file() -> Option<PathBuf> {
+    Some(crate::data_dir()?.join("history"))
+}
+
+/// The newest `MAX_HISTORY` entries in `path`, oldest first. A missing or
+/// unreadable file is an empty history. Once the file holds well over the cap
+/// it is rewritten with just the entries kept.
+pub(super) fn load(path: &Path) -> Vec<String> {
+    let Ok(text) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    let lines: Vec<&str> = text.lines().filter(|l| !l.is_empty()).collect();
+
+    let mut history = Vec::new();
+    for line in &lines {
+        push_history(&mut history, &unescape(line));
+    }
+
+    if lines.len() > 2 * MAX_HISTORY {
+        let compacted: String = history.iter().map(|e| escape(e) + "\n").collect();
+        let _ = fs::write(path, compacted);
+    }
+    history
+}
+
+/// Append one entry; written as it's submitted so a crash doesn't lose it.
+/// History is a convenience, so failures are ignored.
+pub(super) fn append(path: &Path, entry: &str) {
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+        let _ = writeln!(file, "{}", escape(entry));
+    }
+}
+
+/// One line per entry: newlines become `\n` and backslashes are doubled.
+fn escape(entry: &str) -> String {
+    entry.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn unescape(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            out.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
@@ -0,0 +1,192 @@
+use super::wrap_ranges;
+use once_cell::sync::Lazy;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use regex::Regex;
+use unicode_width::UnicodeWidthStr;
+
+static RE_HEADING: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(#{1,6})\s+(.*?)\s*#*\s*$").unwrap());
+static RE_LIST_ITEM: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(\s*)([-*+]|\d{1,9}[.)])\s+(.*)$").unwrap());
+
+static RE_RULE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\s*([-*_])(\s*[-*_]){2,}\s*$").unwrap());
+
+const CODE: Style = Style::new().fg(Color::Yellow);
+/// Fenced blocks, padded to the full width so the background reads as a block.
+const CODE_BLOCK: Style = Style::new().fg(Color::Yellow).bg(Color::Indexed(236));
+const QUOTE: Style = Style::new().add_modifier(Modifier::ITALIC);
+const DIM: Style = Style::new().add_modifier(Modifier::DIM);
+
+/// Line-oriented markdown styling for handler output: headings, lists, quotes,
+/// rules, `**bold**`, `*italic*`, inline code, tables and fenced blocks. Not
+/// CommonMark; it keeps just enough state (being inside a fence) to render
+/// streamed lines.
+#[derive(Default)]
+pub(super) struct MarkdownRenderer {
+    in_fence: bool,
+}
+
+impl MarkdownRenderer {
+    pub(super) fn render(&mut self, text: &str, width: u16) -> Vec<Line<'static>> {
+        let width = width.max(1) as usize;
+        text.split('\n')
+            .flat_map(|line| self.render_line(line, width))
+            .collect()
+    }
+
+    fn render_line(&mut self, line: &str, width: usize) -> Vec<Line<'static>> {
+        if line.trim_start().starts_with("```") {
+            self.in_fence = !self.in_fence;
+            return vec![block_line(
+                line,
+                CODE_BLOCK.add_modifier(Modifier::DIM),
+                width,
+            )];
+        }
+        // Code keeps its indentation and is never re-wrapped.
+        if self.in_fence {
+            return vec![block_line(line, CODE_BLOCK, width)];
+        }
+        if line.trim().is_empty() {
+            return vec![Line::default()];
+        }
+        if RE_RULE.is_match(line) {
+            return vec![Line::styled("─".repeat(width), DIM)];
+        }
+        // Table rows only line up unwrapped; the terminal clips them.
+        if line.trim_start().starts_with('|') {
+            return vec![Line::raw(line.to_string())];
+        }
+        if let Some(quoted) = line.strip_prefix('>') {
+            let quoted = quoted.strip_prefix(' ').unwrap_or(quoted);
+            let mut lines = wrap_spans(inline_spans(quoted, QUOTE), "│ ", width);
+            for line in &mut lines {
+                if let Some(bar) = line.spans.first_mut() {
+                    bar.style = DIM;
+                }
+            }
+            return lines;
+        }
+
+        if let Some(c) = RE_HEADING.captures(line) {
+            let style = heading_style(c[1].len());
+            return wrap_spans(inline_spans(&c[2], style), "", width);
+        }
+        if let Some(c) = RE_LIST_ITEM.captures(line) {
+            let bullet = match &c[2] {
+                "-" | "*" | "+" => "•",
+                ordinal => ordinal,
+            };
+            let prefix = format!("{}{bullet} ", &c[1]);
+            return wrap_spans(inline_spans(&c[3], Style::new()), &prefix, width);
+        }
+        wrap_spans(inline_spans(line, Style::new()), "", width)
+    }
+}
+
+/// `text` unwrapped, padded with spaces to `width` so `style`'s background
+/// covers the row.
+fn block_line(text: &str, style: Style, width: usize) -> Line<'static> {
+    let pad = width.saturating_sub(text.width());
+    Line::styled(format!("{text}{}", " ".repeat(pad)), style)
+}
+
+fn heading_style(level: usize) -> Style {
+    let style = Style::new().add_modifier(Modifier::BOLD);
+    match level {
+        1 => style.fg(Color::Cyan).add_modifier(Modifier::UNDERLINED),
+        2 => style.fg(Color::Cyan),
+        3 => style.fg(Color::Blue),
+        _ => style,
+    }
+}
+
+/// Split `text` into styled runs for `**bold**`, `*italic*` and `` `code` ``.
+/// A marker only opens when its closing marker appears later on the line, so
+/// stray asterisks (`2 * 3`) stay literal. Underscores are left alone since
+/// they mostly show up in identifiers.
+fn inline_spans(text: &str, base: Style) -> Vec<(String, Style)> {
+    let mut spans = Vec::new();
+    let mut buf = String::new();
+    let (mut bold, mut italic) = (false, false);
+    let mut rest = text;
+
+    let style = |bold: bool, italic: bool| {
+        let mut style = base;
+        if bold {
+            style = style.add_modifier(Modifier::BOLD);
+        }
+        if italic {
+            style = style.add_modifier(Modifier::ITALIC);
+        }
+        style
+    };
+
+    while let Some(ch) = rest.chars().next() {
+        if ch == '`'
+            && let Some(end) = rest[1..].find('`')
+        {
+            flush(&mut spans, &mut buf, style(bold, italic));
+            spans.push((rest[1..1 + end].to_string(), CODE));
+            rest = &rest[end + 2..];
+            continue;
+        }
+
+        let marker = if rest.starts_with("**") && (bold || opens(&rest[2..], "**")) {
+            "**"
+        } else if ch == '*' && (italic || opens(&rest[1..], "*")) {
+            "*"
+        } else {
+            buf.push(ch);
+            rest = &rest[ch.len_utf8()..];
+            continue;
+        };
+
+        flush(&mut spans, &mut buf, style(bold, italic));
+        match marker {
+            "**" => bold = !bold,
+            _ => italic = !italic,
+        }
+        rest = &rest[marker.len()..];
+    }
+
+    flush(&mut spans, &mut buf, style(bold, italic));
+    spans
+}
+
+/// Whether a marker followed by `after` starts an emphasis run.
+fn opens(after: &str, marker: &str) -> bool {
+    after.starts_with(|c: char| !c.is_whitespace()) && after.contains(marker)
+}
+
+fn flush(spans: &mut Vec<(String, Style)>, buf: &mut String, style: Style) {
+    if !buf.is_empty() {
+        spans.push((std::mem::take(buf), style));
+    }
+}
+
+/// Wrap styled runs to `width`, `prefix` on the first row and a hanging
+/// indent of the same width on the rest.
+fn wrap_spans(spans: Vec<(String, Style)>, prefix: &str, width: usize) -> Vec<Line<'static>> {
+    let text: String = spans.iter().map(|(t, _)| t.as_str()).collect();
+    let prefix_width = prefix.width();
+    let indent = " ".repeat(prefix_width);
+    let content_width = width.saturating_sub(prefix_width).max(1);
+
+    let mut lines = Vec::new();
+    for (idx, row) in wrap_ranges(&text, content_width).into_iter().enumerate() {
+        let head = if idx == 0 { prefix } else { &indent };
+        let mut line = vec![Span::raw(head.to_string())];
+
+        let mut offset = 0;
+        for (run, style) in &spans {
+            let (start, end) = (offset.max(row.start), (offset + run.len()).min(row.end));
+            if start < end {
+                line.push(Span::styled(text[start..end].to_string(), *style));
+            }
+            offset += run.len();
+        }
+        lines.push(Line::from(line));
+    }
+    lines
+}
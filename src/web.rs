@@ -1,243 +1,2365 @@
-use anyhow::{Context, Result, anyhow};
+use anyhow::{Context, Result, bail};
 use html2md::parse_html;
 use once_cell::sync::Lazy;
 use regex::Regex;
-use reqwest::Client;
-use reqwest::header::CONTENT_TYPE;
+use reqwest::header::{ACCEPT, IF_MODIFIED_SINCE, IF_NONE_MATCH};
 use scraper::{Html, Selector};
-use std::collections::HashSet;
-use std::sync::Arc;
-use tokio::sync::Semaphore;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{Semaphore, mpsc};
 use tokio::task::JoinSet;
-use websearch::providers::duckduckgo::{DuckDuckGoConfig, DuckDuckGoProvider};
-use websearch::{SearchOptions, web_search};
+pub use tokio_util::sync::CancellationToken;
+use tracing::{Instrument, Span, debug, error, info_span, instrument, warn};
 
-#[derive(Debug, Clone)]
+mod boilerplate;
+#[cfg(feature = "browser")]
+mod browser;
+mod cache;
+mod charset;
+mod chunk;
+mod code;
+mod config;
+mod convert;
+mod dedup;
+mod domains;
+mod expand;
+mod fallback;
+mod fetch;
+mod fragment;
+mod header;
+mod identity;
+mod language;
+mod links;
+mod metadata;
+mod pdf;
+mod providers;
+mod ratelimit;
+mod readability;
+mod redirect;
+mod relevance;
+mod report;
+mod retry;
+mod rewrite;
+mod robots;
+mod sections;
+mod sitemap;
+mod sites;
+mod stats;
+mod tables;
+#[cfg(test)]
+mod tests;
+mod tokens;
+
+pub use cache::default_cache_dir;
+use cache::{CachedResponse, Lookup, PageCache, normalize_url};
+pub use chunk::{ChunkConfig, MdChunk, chunk_markdown};
+use code::CodeBlocks;
+pub use config::{ConfigError, LlmCleanConfigBuilder};
+use convert::BodyKind;
+use dedup::Deduper;
+pub use expand::{QueryExpander, QueryExpansion};
+pub use fetch::{FetchConfig, FetchRequest, FetchedResponse, Fetcher, ReqwestFetcher};
+pub use header::{HeaderField, OutputFormat, strip_header};
+pub use identity::{DEFAULT_USER_AGENT, FetchIdentity, UserAgent};
+use metadata::PageMeta;
+pub use providers::{SearchConfig, SearchHit, SearchProvider};
+use ratelimit::HostLimiter;
+pub use relevance::score_relevance;
+pub use report::{CrawlFailure, FailureReason, SearchReport};
+pub use retry::RetryPolicy;
+use robots::Robots;
+pub use sitemap::{crawl_site, crawl_sitemap};
+pub use stats::CrawlStats;
+use stats::FetchMetrics;
+use tables::Tables;
+pub use tokens::{TokenModel, Tokenizer};
+
+/// Version of the serialized [`MdPage`] and [`LlmCleanConfig`] layout:
+/// bumped when a field is renamed, removed or changes meaning. Added fields
+/// don't bump it; they deserialize to their defaults from older data.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// One crawled page: its cleaned markdown (header, outline, then content)
+/// and what is known about where it came from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MdPage {
+    /// [`SCHEMA_VERSION`] of the code that produced the page.
+    #[serde(default = "unversioned")]
+    pub schema_version: u32,
     pub query: String,
+    /// The URL as found (search hit, sitemap entry or followed link).
     pub url: String,
+    /// Where `url` ended up after redirects; the URL to cite.
+    pub final_url: String,
+    /// `final_url` was reached through a meta-refresh or script redirect.
+    pub client_redirect: bool,
+    /// Anything but `Content` or `Pdf` means the page is partial.
+    pub extraction: Extraction,
+    /// `0` for `Extraction::Snippet` pages, which weren't fetched.
     pub status: u16,
     pub title: Option<String>,
-    pub outline: Vec<String>,
+    /// The search engine's summary of the page, for search hits.
+    pub snippet: Option<String>,
+    /// The page's own summary, from JSON-LD, OpenGraph or `<meta name="description">`.
+    pub description: Option<String>,
+    /// Publication date as the page gives it, usually ISO 8601.
+    pub published_at: Option<String>,
+    /// Authors, comma-separated when there are several.
+    pub author: Option<String>,
+    pub site_name: Option<String>,
+    /// The URL the page declares as canonical, when it declares one.
+    pub canonical_url: Option<String>,
+    pub outline: Vec<OutlineItem>,
     pub markdown: String,
+    /// Relevance to `query`, see [`score_relevance`].
+    pub score: f32,
+    /// The page this one was linked from, for pages reached by link following.
+    pub via: Option<String>,
+    /// Later queries whose hits also had this URL; the page is listed under `query` only.
+    pub also_matched: Vec<String>,
 }
 
+/// Pages and failures for one query.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryResults {
+    pub query: String,
+    pub pages: Vec<MdPage>,
+    /// Search and fetch failures for this query and its URLs.
+    pub errors: Vec<String>,
+}
+
+/// One heading from a page, with its level (1 for `#` .. 6 for `######`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutlineItem {
+    pub level: u8,
+    pub text: String,
+}
+
+/// Renders the bare heading text (the old flat outline form).
+impl fmt::Display for OutlineItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// Why a search hit did not make it into the results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DropReason {
+    NonSuccessStatus,
+    NotHtml,
+    TooShort,
+    /// Mostly links with little prose (navigation hubs, tag pages).
+    LowQuality,
+    /// Same content as a higher-ranked page.
+    Duplicate,
+    /// The search was cancelled before this page finished.
+    Cancelled,
+    /// Too little content, and no noscript/JSON-LD/meta text to fall back on.
+    JsRendered,
+    /// Declares (or is) the same canonical URL as a page already fetched.
+    CanonicalDuplicate,
+    /// The site's robots.txt disallows the URL.
+    RobotsDisallowed,
+    /// Written in a language outside `LlmCleanConfig::allowed_languages`.
+    WrongLanguage,
+    /// On a site outside `allowed_domains`, or in `blocked_domains`.
+    BlockedDomain,
+    /// Unfinished when `search_deadline_secs` or `max_total_bytes` ran out.
+    OverBudget,
+}
+
+impl fmt::Display for DropReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DropReason::NonSuccessStatus => "non-success status",
+            DropReason::NotHtml => "not html",
+            DropReason::TooShort => "too short",
+            DropReason::LowQuality => "link-heavy, too little prose",
+            DropReason::Duplicate => "duplicate content",
+            DropReason::Cancelled => "cancelled",
+            DropReason::JsRendered => "js-rendered/empty",
+            DropReason::CanonicalDuplicate => "duplicate canonical url",
+            DropReason::RobotsDisallowed => "disallowed by robots.txt",
+            DropReason::WrongLanguage => "not in an allowed language",
+            DropReason::BlockedDomain => "blocked domain",
+            DropReason::OverBudget => "search budget spent",
+        })
+    }
+}
+
+/// Where a page's content came from. Serializes as its `Display` form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Extraction {
+    /// The page body, as rendered without JavaScript.
+    #[default]
+    Content,
+    /// The page body after a headless browser ran its scripts.
+    Rendered,
+    /// JSON-LD `articleBody` (usually the full article).
+    #[serde(rename = "fallback-jsonld")]
+    FallbackJsonLd,
+    /// `<noscript>` blocks.
+    FallbackNoscript,
+    /// A description only (JSON-LD, OpenGraph or meta); a summary, not the page.
+    FallbackMeta,
+    /// Text extracted from a PDF document.
+    Pdf,
+    /// A JSON body, pretty-printed in a fenced block.
+    Json,
+    /// An RSS or Atom feed, as a list of its items.
+    Feed,
+    /// The search result's snippet alone; the page wasn't fetched.
+    Snippet,
+    /// A `text/plain` or `text/markdown` body, used as is.
+    Text,
+}
+
+impl fmt::Display for Extraction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Extraction::Content => "content",
+            Extraction::Rendered => "rendered",
+            Extraction::FallbackJsonLd => "fallback-jsonld",
+            Extraction::FallbackNoscript => "fallback-noscript",
+            Extraction::FallbackMeta => "fallback-meta",
+            Extraction::Pdf => "pdf",
+            Extraction::Json => "json",
+            Extraction::Feed => "feed",
+            Extraction::Snippet => "snippet",
+            Extraction::Text => "text",
+        })
+    }
+}
+
+/// A way of picking a page's main content, see [`LlmCleanConfig::extractors`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentExtractor {
+    /// The first of `main`, `article`, `#content` and similar with enough text.
+    Selectors,
+    /// Readability-style scoring by text and link density, with DOM pruning.
+    /// Better on sites without semantic markup.
+    Readability,
+}
+
+/// What happens to `<img>` tags, see [`LlmCleanConfig::images`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImagePolicy {
+    /// Keep images, with relative sources resolved against the page URL.
+    #[default]
+    Absolute,
+    /// Keep images exactly as the page has them.
+    Keep,
+    /// Replace each image with `[image: alt]`; images without alt text go.
+    AltText,
+    /// Remove images entirely.
+    Drop,
+}
+
+/// What happens to links, see [`LlmCleanConfig::links`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkPolicy {
+    /// Resolve relative hrefs against the page URL, so links work out of context.
+    #[default]
+    Absolute,
+    /// Keep hrefs exactly as the page has them.
+    Keep,
+    /// Replace each link with its text.
+    Strip,
+}
+
+/// Lifecycle events reported while a search runs.
 #[derive(Debug, Clone)]
+pub enum CrawlEvent {
+    SearchStarted {
+        query: String,
+    },
+    SearchCompleted {
+        query: String,
+        results: usize,
+    },
+    FetchStarted {
+        url: String,
+    },
+    FetchCompleted {
+        url: String,
+        status: u16,
+        elapsed: Duration,
+    },
+    FetchFailed {
+        url: String,
+        error: String,
+        elapsed: Duration,
+    },
+    PageKept {
+        url: String,
+    },
+    PageDropped {
+        url: String,
+        reason: DropReason,
+    },
+}
+
+/// Progress callback; invoked from the crawl tasks, so keep it cheap.
+pub type ProgressFn = Arc<dyn Fn(CrawlEvent) + Send + Sync>;
+
+/// Optional hooks into a running search.
+#[derive(Clone, Default)]
+pub struct SearchHooks {
+    pub progress: Option<ProgressFn>,
+    /// Cancelling stops new fetches, aborts in-flight ones, and makes the
+    /// search return the pages that already completed.
+    pub cancel: Option<CancellationToken>,
+    /// Replaces the default [`ReqwestFetcher`], e.g. with canned responses in tests.
+    pub fetcher: Option<Arc<dyn Fetcher>>,
+    /// Replaces the provider `LlmCleanConfig::search` selects.
+    pub search: Option<Arc<dyn SearchProvider>>,
+    /// Replaces the variants `LlmCleanConfig::query_expansion` generates.
+    pub expander: Option<Arc<dyn QueryExpander>>,
+    /// Replaces the estimate `LlmCleanConfig::token_model` selects.
+    pub tokenizer: Option<Arc<dyn Tokenizer>>,
+}
+
+impl fmt::Debug for SearchHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SearchHooks")
+            .field("cancel", &self.cancel)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SearchHooks {
+    fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(|t| t.is_cancelled())
+    }
+
+    async fn cancelled(&self) {
+        match &self.cancel {
+            Some(token) => token.cancelled().await,
+            None => std::future::pending().await,
+        }
+    }
+
+    fn emit(&self, event: CrawlEvent) {
+        if let Some(progress) = &self.progress {
+            progress(event);
+        }
+    }
+
+    fn tokenizer(&self, cfg: &LlmCleanConfig) -> Arc<dyn Tokenizer> {
+        match &self.tokenizer {
+            Some(tokenizer) => tokenizer.clone(),
+            None => cfg.token_model.tokenizer(),
+        }
+    }
+
+    fn dropped(&self, url: &str, reason: DropReason) {
+        warn!(url, %reason, "dropped page");
+        self.emit(CrawlEvent::PageDropped {
+            url: url.to_string(),
+            reason,
+        });
+    }
+}
+
+/// A fetched and cleaned page, before the header is added and the size cap applied.
+struct CleanPage {
+    query: String,
+    url: String,
+    final_url: String,
+    /// The fragment whose section `md` holds, when only that section was kept.
+    section: Option<String>,
+    client_redirect: bool,
+    extraction: Extraction,
+    status: u16,
+    title: Option<String>,
+    snippet: Option<String>,
+    meta: PageMeta,
+    via: Option<String>,
+    md: String,
+}
+
+impl CleanPage {
+    /// Where the page landed; separate sections of one page are separate pages.
+    fn dedup_key(&self) -> String {
+        canonical_key(&self.final_url, self.section.as_deref())
+    }
+}
+
+/// One URL to fetch. `rank` orders the results: a followed page sorts right
+/// after its parent and before the parent's next sibling.
+struct CrawlJob {
+    rank: Vec<usize>,
+    query: String,
+    url: String,
+    title: Option<String>,
+    /// The search result's summary of the page.
+    snippet: Option<String>,
+    via: Option<String>,
+    depth: u8,
+}
+
+type CrawlDone = (CrawlJob, Result<Crawled>, FetchMetrics, Vec<String>);
+
+enum Crawled {
+    Page(Box<CleanPage>),
+    Dropped(DropReason),
+}
+
+/// Shared state for the crawl tasks of one search call.
+struct CrawlCtx {
+    fetcher: Arc<dyn Fetcher>,
+    cfg: LlmCleanConfig,
+    hooks: SearchHooks,
+    sem: Semaphore,
+    hosts: HostLimiter,
+    cache: Option<PageCache>,
+    robots: Option<Robots>,
+    tokenizer: Arc<dyn Tokenizer>,
+    /// Set by `crawl_site`: follow every link within this site instead of the
+    /// query-matched candidates.
+    site: Option<reqwest::Url>,
+    /// Canonical and final URLs of pages fetched so far, normalized.
+    canonicals: Mutex<HashSet<String>>,
+    /// When `search_deadline_secs` runs out.
+    deadline: Option<tokio::time::Instant>,
+    /// Body bytes fetched so far, against `max_total_bytes`.
+    bytes: AtomicUsize,
+    /// Cancelled once the deadline passes or the byte budget is spent.
+    spent: CancellationToken,
+    /// Set when `render_js` is on.
+    #[cfg(feature = "browser")]
+    renderer: Option<browser::Renderer>,
+}
+
+/// Dedup key for `url`: the cache key, plus `#section` when only that section is kept.
+fn canonical_key(url: &str, section: Option<&str>) -> String {
+    let key = normalize_url(url);
+    match section {
+        Some(section) => format!("{key}#{section}"),
+        None => key,
+    }
+}
+
+impl CrawlCtx {
+    /// `section` is the fragment of a page kept as just that section; each such
+    /// section is its own entry, so other fragments of the page still get through.
+    fn is_known_canonical(&self, url: &str, section: Option<&str>) -> bool {
+        self.canonicals
+            .lock()
+            .unwrap()
+            .contains(&canonical_key(url, section))
+    }
+
+    /// Record the page's URLs; false when another page already claimed its canonical.
+    fn claim_canonical(&self, canonical: &str, final_url: &str, section: Option<&str>) -> bool {
+        let mut seen = self.canonicals.lock().unwrap();
+        if !seen.insert(canonical_key(canonical, section)) {
+            return false;
+        }
+        seen.insert(canonical_key(final_url, section));
+        true
+    }
+
+    fn over_budget(&self) -> bool {
+        self.spent.is_cancelled()
+            || self
+                .deadline
+                .is_some_and(|d| tokio::time::Instant::now() >= d)
+    }
+
+    /// Resolves once the deadline passes or the byte budget is spent.
+    async fn budget_spent(&self) {
+        let deadline = async {
+            match self.deadline {
+                Some(d) => tokio::time::sleep_until(d).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::select! {
+            _ = deadline => self.spent.cancel(),
+            _ = self.spent.cancelled() => {}
+        }
+    }
+
+    fn add_bytes(&self, n: usize) {
+        if let Some(max) = self.cfg.max_total_bytes
+            && self.bytes.fetch_add(n, Ordering::Relaxed) + n >= max
+        {
+            self.spent.cancel();
+        }
+    }
+}
+
+/// Build with [`LlmCleanConfig::builder`] to get the invariants checked up front.
+///
+/// Serializes with its field names; missing fields deserialize to their
+/// defaults, so a config saved by an older version still loads. Secrets
+/// (API keys, tokens, proxy and header values) are never serialized.
+/// Deserialized configs aren't validated, call [`LlmCleanConfig::validate`].
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
 pub struct LlmCleanConfig {
     pub concurrency: usize,
-    pub timeout_secs: u64,
+    /// Queries searched at once, each with its expansion variants.
+    pub search_concurrency: usize,
+    /// Requests in flight to any one host, within `concurrency`.
+    pub per_host_concurrency: usize,
+    /// Request starts per second to any one host; `None` for no limit.
+    pub per_host_requests_per_sec: Option<f32>,
+    /// Deadline for the whole request, connect through last body byte.
+    pub total_timeout_secs: u64,
+    /// Deadline for establishing the connection alone.
+    pub connect_timeout_secs: u64,
+    /// Abort when no bytes arrive for this long, for hosts that accept and then stall.
+    pub read_idle_timeout_secs: Option<u64>,
+    /// Tries again after 429/503s, timeouts and failed connects.
+    pub retry: RetryPolicy,
+    pub max_redirects: usize,
+    /// The `User-Agent` sent with every request, robots.txt included.
+    pub identity: FetchIdentity,
+    /// Proxy, extra headers and cookies for the default fetcher.
+    pub fetch: FetchConfig,
     pub require_html_content_type: bool,
     pub drop_non_success_status: bool,
     pub max_html_bytes: usize,
+    /// Turn PDF responses into markdown instead of dropping them as not HTML.
+    pub ingest_pdfs: bool,
+    /// Turn JSON, RSS/Atom and plain-text or markdown responses into
+    /// markdown instead of dropping them as not HTML.
+    pub convert_non_html: bool,
+    /// Body cap for PDFs, which are rarely usable once cut short.
+    pub max_pdf_bytes: usize,
     pub max_md_chars: usize,
+    /// Token cap per page on top of `max_md_chars`, counted with `token_model`'s
+    /// tokenizer (or `SearchHooks::tokenizer`).
+    pub max_md_tokens: Option<usize>,
+    /// The tokenizer family `max_md_tokens` is counted in.
+    pub token_model: TokenModel,
     pub min_md_chars: usize,
+    /// Drop pages where more than this fraction of text sits inside links.
+    pub max_link_density: f32,
+    /// Drop pages with fewer prose (non-link) paragraphs than this.
+    pub min_paragraphs: usize,
     pub max_link_lines_to_keep: usize,
     pub link_farm_run_threshold: usize,
     pub max_line_len: usize,
+    /// Render data tables as GitHub-flavored markdown tables instead of
+    /// leaving them to html2md.
+    pub gfm_tables: bool,
+    /// Longer table cells are cut, so one wordy cell can't blow up a row.
+    pub max_table_cell_chars: usize,
+    /// Lift `<pre>` blocks out before html2md and put them back fenced, with
+    /// their language, where line-length pruning can't reach them.
+    pub fence_code_blocks: bool,
+    /// Use the site-specific extractor for sites that have one (Stack
+    /// Exchange, GitHub, rustdoc, Wikipedia, ...) instead of the generic
+    /// extraction.
+    pub site_extractors: bool,
+    /// Sent to the GitHub API for READMEs and issues; without one, GitHub
+    /// allows 60 requests an hour, after which pages are scraped instead.
+    /// Never serialized, and redacted from `Debug` output.
+    #[serde(skip_serializing)]
+    pub github_token: Option<String>,
+    /// For URLs with a `#fragment`, keep only the section it points at (a
+    /// heading and what follows it, or the element itself), when that
+    /// section is at least `min_md_chars` long.
+    pub extract_fragments: bool,
+    /// Image handling; `data:` images are stripped whatever this says.
+    pub images: ImagePolicy,
+    /// Whether links are made absolute, kept as written, or reduced to their text.
+    pub links: LinkPolicy,
+    /// ISO 639-3 codes (`eng`, `deu`, `cmn`, ...) of the languages pages may
+    /// be in; others are dropped. Empty allows any, and pages too short to
+    /// tell are always kept.
+    pub allowed_languages: Vec<String>,
     pub max_outline_headings: usize,
+    /// The metadata header each page's markdown starts with.
+    pub output_format: OutputFormat,
+    /// Which entries the header has, in this order.
+    pub header_fields: Vec<HeaderField>,
+    /// Main-content extractors, tried in order until one finds enough text;
+    /// the whole page is used when none does.
+    pub extractors: Vec<ContentExtractor>,
+    /// Only fetch from these sites, when not empty. `docs.rs` also matches its
+    /// subdomains; `*` is a wildcard, and `*.rust-lang.org` covers the bare
+    /// domain too.
+    pub allowed_domains: Vec<String>,
+    /// Never fetch from these sites, matched like `allowed_domains`.
+    pub blocked_domains: Vec<String>,
+    /// Render pages that come out near-empty in a headless Chrome, when their
+    /// markup suggests scripts hold the content. Needs the `browser` feature.
+    pub render_js: bool,
+    /// The Chrome or Chromium to run for `render_js`; found on `PATH` when unset.
+    pub browser_executable: Option<PathBuf>,
+    /// Drop pages whose cleaned content duplicates a higher-ranked page.
+    pub dedupe_content: bool,
+    /// Estimated Jaccard similarity of word shingles (0.0..=1.0) at which two
+    /// pages count as duplicates.
+    pub near_duplicate_threshold: f32,
+    /// Drop short lines that are stock cookie banners, newsletter prompts,
+    /// share bars and footer notices.
+    pub strip_boilerplate_phrases: bool,
+    /// Strip paragraphs repeated verbatim across pages from the same host.
+    pub dedupe_boilerplate: bool,
+    /// A paragraph is boilerplate once it appears on this many pages of a host.
+    pub boilerplate_min_pages: usize,
+    /// Shorter paragraphs are never treated as boilerplate.
+    pub boilerplate_min_block_chars: usize,
+    /// Skip fetching: return each search hit as a page of its snippet.
+    pub snippets_only: bool,
+    /// Variants searched alongside each query; their hits are merged in.
+    pub query_expansion: QueryExpansion,
+    /// Order each query's pages by relevance score instead of search rank.
+    pub sort_by_relevance: bool,
+    /// Keep raw responses here and reuse them instead of re-fetching. `None` disables the cache.
+    pub cache_dir: Option<PathBuf>,
+    /// Cached responses older than this are fetched again.
+    pub cache_ttl_secs: u64,
+    /// Oldest entries are evicted once the cache directory grows past this.
+    pub cache_max_bytes: u64,
+    /// How many hops of links to follow from crawled pages (0 = direct hits only).
+    pub follow_links_depth: u8,
+    /// Same-host links followed per page, best anchor-text match first.
+    pub max_followed_links_per_page: usize,
+    /// Append followed pages to the page that links to them, each under a
+    /// heading with its URL, instead of returning them as pages of their own.
+    /// Streamed searches return them separately regardless.
+    pub merge_followed_links: bool,
+    /// Most URLs fetched per call, direct hits and followed links together.
+    pub max_pages: usize,
+    /// Wall-clock budget for a call's fetching, followed links included.
+    /// Pages still in flight when it runs out are reported as over budget.
+    pub search_deadline_secs: Option<u64>,
+    /// Body bytes a call may download in all; once spent, no new fetches
+    /// start and those in flight are reported as over budget.
+    pub max_total_bytes: Option<usize>,
+    /// Where queries are searched; DuckDuckGo by default.
+    pub search: SearchConfig,
+    /// Skip URLs robots.txt disallows and honor its `Crawl-delay` (capped at 10s).
+    pub respect_robots_txt: bool,
 }
 
 impl Default for LlmCleanConfig {
     fn default() -> Self {
         Self {
             concurrency: 16,
-            timeout_secs: 20,
+            search_concurrency: 4,
+            per_host_concurrency: 4,
+            per_host_requests_per_sec: Some(4.0),
+            total_timeout_secs: 20,
+            connect_timeout_secs: 20,
+            read_idle_timeout_secs: None,
+            retry: RetryPolicy::default(),
+            max_redirects: 10,
+            identity: FetchIdentity::default(),
+            fetch: FetchConfig::default(),
             require_html_content_type: true,
+            convert_non_html: true,
             drop_non_success_status: true,
             max_html_bytes: 2_000_000,
+            ingest_pdfs: true,
+            max_pdf_bytes: 20_000_000,
             max_md_chars: 24_000,
+            max_md_tokens: None,
+            token_model: TokenModel::default(),
             min_md_chars: 200,
+            max_link_density: 0.6,
+            min_paragraphs: 1,
             max_link_lines_to_keep: 40,
             link_farm_run_threshold: 25,
             max_line_len: 2_000,
+            gfm_tables: true,
+            max_table_cell_chars: 120,
+            fence_code_blocks: true,
+            site_extractors: true,
+            github_token: None,
+            extract_fragments: true,
+            images: ImagePolicy::default(),
+            links: LinkPolicy::default(),
+            allowed_languages: Vec::new(),
             max_outline_headings: 24,
+            output_format: OutputFormat::default(),
+            header_fields: HeaderField::ALL.to_vec(),
+            extractors: vec![ContentExtractor::Selectors],
+            allowed_domains: Vec::new(),
+            blocked_domains: Vec::new(),
+            render_js: false,
+            browser_executable: None,
+            dedupe_content: true,
+            near_duplicate_threshold: 0.8,
+            strip_boilerplate_phrases: true,
+            dedupe_boilerplate: true,
+            boilerplate_min_pages: 3,
+            boilerplate_min_block_chars: 40,
+            snippets_only: false,
+            query_expansion: QueryExpansion::default(),
+            sort_by_relevance: false,
+            cache_dir: None,
+            cache_ttl_secs: 24 * 60 * 60,
+            cache_max_bytes: 256 * 1024 * 1024,
+            follow_links_depth: 0,
+            max_followed_links_per_page: 3,
+            merge_followed_links: false,
+            max_pages: 100,
+            search_deadline_secs: None,
+            max_total_bytes: None,
+            search: SearchConfig::default(),
+            respect_robots_txt: true,
+        }
+    }
+}
+
+// Precompiled regex for speed
+static RE_SCRIPT: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)<script\b[^>]*>.*?</script>").unwrap());
+static RE_STYLE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<style\b[^>]*>.*?</style>").unwrap());
+static RE_NOSCRIPT: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)<noscript\b[^>]*>.*?</noscript>").unwrap());
+static RE_SVG: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<svg\b[^>]*>.*?</svg>").unwrap());
+
+// Responsive-image attributes turn into comma-separated URL soup
+static RE_IMG_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<(?:img|source)\b[^>]*>").unwrap());
+static RE_SRCSET_ATTR: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)\s(?:data-)?(?:srcset|sizes)\s*=\s*(?:"[^"]*"|'[^']*'|[^\s>]+)"#).unwrap()
+});
+
+// Remove inline data:... blobs (images, fonts, octet-streams) in markdown link targets (token poison)
+static RE_DATA_URI_LINK: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)\(data:([a-z0-9.+-]+)/[^)]*\)").unwrap());
+
+// Same blobs outside of a link target
+static RE_DATA_URI_BARE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\bdata:[a-z0-9.+-]+/[a-z0-9.+-]+(?:;[a-z0-9=.+-]+)*,[a-z0-9+/=%._-]+").unwrap()
+});
+
+// “Just a link bullet” line
+static RE_LINK_ONLY: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"^\s*[-*+]\s+\[[^\]]+\]\([^)]+\)\s*$"#).unwrap());
+
+// Inline markdown link, captures the anchor text
+static RE_MD_LINK: Lazy<Regex> = Lazy::new(|| Regex::new(r"\[([^\]]*)\]\([^)]*\)").unwrap());
+
+/// Public API: array of queries + results per query.
+pub async fn search(queries: &[String], results_per_query: u32) -> Result<Vec<MdPage>> {
+    search_with_config(queries, results_per_query, &LlmCleanConfig::default()).await
+}
+
+/// Same as `search`, but configurable.
+pub async fn search_with_config(
+    queries: &[String],
+    results_per_query: u32,
+    cfg: &LlmCleanConfig,
+) -> Result<Vec<MdPage>> {
+    search_with_hooks(queries, results_per_query, cfg, &SearchHooks::default()).await
+}
+
+/// Same as `search_with_config`, stopped early by cancelling `cancel`: no new
+/// fetches start, in-flight ones are aborted, and the pages that already
+/// completed are returned. Dropping the future instead aborts everything and
+/// returns nothing.
+pub async fn search_with_cancel(
+    queries: &[String],
+    results_per_query: u32,
+    cfg: &LlmCleanConfig,
+    cancel: CancellationToken,
+) -> Result<Vec<MdPage>> {
+    let hooks = SearchHooks {
+        cancel: Some(cancel),
+        ..Default::default()
+    };
+    search_with_hooks(queries, results_per_query, cfg, &hooks).await
+}
+
+/// Same as `search_with_config`, with progress reporting and cancellation through `hooks`.
+pub async fn search_with_hooks(
+    queries: &[String],
+    results_per_query: u32,
+    cfg: &LlmCleanConfig,
+    hooks: &SearchHooks,
+) -> Result<Vec<MdPage>> {
+    search_with_stats(queries, results_per_query, cfg, hooks)
+        .await
+        .map(|(pages, _)| pages)
+}
+
+/// Same as `search_with_hooks`, also returning counters and timings for the call.
+pub async fn search_with_stats(
+    queries: &[String],
+    results_per_query: u32,
+    cfg: &LlmCleanConfig,
+    hooks: &SearchHooks,
+) -> Result<(Vec<MdPage>, CrawlStats)> {
+    let report = search_with_report(queries, results_per_query, cfg, hooks).await?;
+    Ok((report.pages, report.stats))
+}
+
+/// Same as `search_with_stats`, also listing every search and URL that
+/// produced no page, with a typed reason.
+pub async fn search_with_report(
+    queries: &[String],
+    results_per_query: u32,
+    cfg: &LlmCleanConfig,
+    hooks: &SearchHooks,
+) -> Result<SearchReport> {
+    let (groups, stats, failures) = run_search(queries, results_per_query, cfg, hooks).await?;
+    Ok(SearchReport {
+        pages: groups.into_iter().flat_map(|g| g.pages).collect(),
+        failures,
+        stats,
+    })
+}
+
+/// Same as `search_with_hooks`, with pages and failures grouped per query, in query order.
+///
+/// A failed search only fails its own group; the call errors when every query failed.
+pub async fn search_grouped(
+    queries: &[String],
+    results_per_query: u32,
+    cfg: &LlmCleanConfig,
+    hooks: &SearchHooks,
+) -> Result<Vec<QueryResults>> {
+    run_search(queries, results_per_query, cfg, hooks)
+        .await
+        .map(|(groups, _, _)| groups)
+}
+
+/// Fetch and clean `urls` directly, without searching. Each page's query is
+/// its own URL. Fails only when no page could be fetched at all.
+pub async fn fetch_urls(
+    urls: &[String],
+    cfg: &LlmCleanConfig,
+    hooks: &SearchHooks,
+) -> Result<Vec<MdPage>> {
+    let mut seen: HashSet<String> = HashSet::new();
+    let jobs = urls
+        .iter()
+        .filter(|url| seen.insert(url.to_string()))
+        .enumerate()
+        .map(|(i, url)| CrawlJob {
+            rank: vec![i],
+            query: url.clone(),
+            url: url.clone(),
+            title: None,
+            snippet: None,
+            via: None,
+            depth: 0,
+        })
+        .collect();
+
+    let started = Instant::now();
+    let ctx = crawl_ctx(cfg, hooks, None)?;
+    let mut stats = CrawlStats::default();
+    let (pages, mut failures) = crawl_all(&ctx, jobs, &mut seen, &mut stats, None).await;
+    if pages.is_empty()
+        && let Some(failure) = failures.iter().find(|f| f.reason.is_error())
+    {
+        bail!("fetching {} failed: {}", failure.query, failure.message);
+    }
+    let out = finish_pages(pages, urls, cfg, hooks, &mut stats, &mut failures);
+    stats.total_time = started.elapsed();
+    stats.log_summary(out.len());
+    Ok(out)
+}
+
+async fn run_search(
+    queries: &[String],
+    results_per_query: u32,
+    cfg: &LlmCleanConfig,
+    hooks: &SearchHooks,
+) -> Result<(Vec<QueryResults>, CrawlStats, Vec<CrawlFailure>)> {
+    let started = Instant::now();
+    let mut stats = CrawlStats::default();
+    let mut groups: Vec<QueryResults> = queries
+        .iter()
+        .map(|q| QueryResults {
+            query: q.clone(),
+            pages: vec![],
+            errors: vec![],
+        })
+        .collect();
+
+    if queries.is_empty() || results_per_query == 0 {
+        return Ok((groups, stats, Vec::new()));
+    }
+
+    // 1) Search every query.
+    let Hits {
+        jobs,
+        mut seen,
+        mut also_matched,
+    } = search_hits(
+        queries,
+        results_per_query,
+        cfg,
+        hooks,
+        &mut groups,
+        &mut stats,
+    )
+    .await?;
+    // Only search failures are in the groups so far.
+    let mut failures: Vec<CrawlFailure> = groups
+        .iter()
+        .flat_map(|g| {
+            g.errors.iter().map(|e| CrawlFailure {
+                query: g.query.clone(),
+                url: None,
+                reason: FailureReason::Search,
+                message: e.clone(),
+            })
+        })
+        .collect();
+    if jobs.is_empty() {
+        stats.total_time = started.elapsed();
+        stats.log_summary(0);
+        return Ok((groups, stats, failures));
+    }
+
+    let out = if cfg.snippets_only {
+        snippet_pages(jobs, queries, cfg, hooks, &mut stats)
+    } else {
+        // 2) Fast parallel fetch + extract + clean + convert.
+        let ctx = crawl_ctx(cfg, hooks, None)?;
+        let (pages, crawl_failures) = crawl_all(&ctx, jobs, &mut seen, &mut stats, None).await;
+        failures.extend(crawl_failures);
+
+        // 3) Cross-page passes, header and cap.
+        finish_pages(pages, queries, cfg, hooks, &mut stats, &mut failures)
+    };
+    stats.total_time = started.elapsed();
+    stats.log_summary(out.len());
+
+    // 4) Group in query order. Filtered pages aren't errors.
+    for failure in failures
+        .iter()
+        .filter(|f| f.url.is_some() && f.reason.is_error())
+    {
+        if let Some(g) = groups.iter_mut().find(|g| g.query == failure.query) {
+            g.errors.push(failure.message.clone());
+        }
+    }
+    for mut page in out {
+        page.also_matched = also_matched.remove(&page.url).unwrap_or_default();
+        if let Some(g) = groups.iter_mut().find(|g| g.query == page.query) {
+            g.pages.push(page);
+        }
+    }
+
+    Ok((groups, stats, failures))
+}
+
+/// What [`search_stream`] yields.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum StreamItem {
+    Page(MdPage),
+    /// A search, or a URL, that produced no page, as in
+    /// [`SearchReport::failures`]; sent as it happens.
+    Failure(CrawlFailure),
+    /// The last item of a search that ran to the end, so "no pages" can be
+    /// told apart from "every fetch failed".
+    Done(CrawlStats),
+}
+
+/// Like `search_with_report`, but yields each page as soon as it is cleaned,
+/// so one slow host doesn't hold up the rest. Pages and failures arrive in
+/// completion order, then the stats.
+///
+/// Exact and near-duplicate dedup still apply against the pages sent so far;
+/// cross-page boilerplate stripping and `sort_by_relevance` need the whole set
+/// and are skipped. A search that fails outright yields a single `Err`.
+pub fn search_stream(
+    queries: &[String],
+    results_per_query: u32,
+    cfg: &LlmCleanConfig,
+    hooks: &SearchHooks,
+) -> mpsc::Receiver<Result<StreamItem>> {
+    let (tx, rx) = mpsc::channel(cfg.concurrency.max(1));
+    let (queries, cfg, hooks) = (queries.to_vec(), cfg.clone(), hooks.clone());
+
+    tokio::spawn(async move {
+        let res = stream_search(&queries, results_per_query, &cfg, &hooks, tx.clone()).await;
+        if let Err(e) = res {
+            let _ = tx.send(Err(e)).await;
+        }
+    });
+    rx
+}
+
+async fn stream_search(
+    queries: &[String],
+    results_per_query: u32,
+    cfg: &LlmCleanConfig,
+    hooks: &SearchHooks,
+    tx: mpsc::Sender<Result<StreamItem>>,
+) -> Result<()> {
+    let started = Instant::now();
+    let mut stats = CrawlStats::default();
+    if queries.is_empty() || results_per_query == 0 {
+        let _ = tx.send(Ok(StreamItem::Done(stats))).await;
+        return Ok(());
+    }
+
+    let mut groups: Vec<QueryResults> = queries
+        .iter()
+        .map(|q| QueryResults {
+            query: q.clone(),
+            pages: vec![],
+            errors: vec![],
+        })
+        .collect();
+    let Hits {
+        jobs,
+        mut seen,
+        also_matched,
+    } = search_hits(
+        queries,
+        results_per_query,
+        cfg,
+        hooks,
+        &mut groups,
+        &mut stats,
+    )
+    .await?;
+    for g in &groups {
+        for e in &g.errors {
+            let failure = CrawlFailure {
+                query: g.query.clone(),
+                url: None,
+                reason: FailureReason::Search,
+                message: e.clone(),
+            };
+            let _ = tx.send(Ok(StreamItem::Failure(failure))).await;
+        }
+    }
+
+    let kept = if cfg.snippets_only {
+        let pages = snippet_pages(jobs, queries, cfg, hooks, &mut stats);
+        let kept = pages.len();
+        for page in pages {
+            let _ = tx.send(Ok(StreamItem::Page(page))).await;
+        }
+        kept
+    } else {
+        let ctx = crawl_ctx(cfg, hooks, None)?;
+        let mut sink = PageSink {
+            tx: tx.clone(),
+            also_matched,
+            final_urls: HashSet::new(),
+            deduper: Deduper::default(),
+            kept: HashMap::new(),
+        };
+        crawl_all(&ctx, jobs, &mut seen, &mut stats, Some(&mut sink)).await;
+        stats.pages_per_query = queries
+            .iter()
+            .map(|q| (q.clone(), sink.kept.get(q).copied().unwrap_or(0)))
+            .collect();
+        sink.kept.values().sum()
+    };
+    stats.total_time = started.elapsed();
+    stats.log_summary(kept);
+    let _ = tx.send(Ok(StreamItem::Done(stats))).await;
+    Ok(())
+}
+
+/// Search hits as pages without fetching them, for `snippets_only`: the
+/// snippet is the content. Hits without a snippet are left out.
+fn snippet_pages(
+    jobs: Vec<CrawlJob>,
+    queries: &[String],
+    cfg: &LlmCleanConfig,
+    hooks: &SearchHooks,
+    stats: &mut CrawlStats,
+) -> Vec<MdPage> {
+    let tokenizer = hooks.tokenizer(cfg);
+    let mut out = Vec::new();
+    for job in jobs {
+        if !domains::is_allowed(&job.url, &cfg.allowed_domains, &cfg.blocked_domains) {
+            stats.record_drop(DropReason::BlockedDomain);
+            hooks.dropped(&job.url, DropReason::BlockedDomain);
+            continue;
+        }
+        let Some(snippet) = job.snippet else {
+            continue;
+        };
+        let page = CleanPage {
+            query: job.query,
+            url: job.url.clone(),
+            final_url: job.url,
+            section: None,
+            client_redirect: false,
+            extraction: Extraction::Snippet,
+            status: 0,
+            title: job.title,
+            md: snippet.clone(),
+            snippet: Some(snippet),
+            meta: PageMeta::default(),
+            via: None,
+        };
+        hooks.emit(CrawlEvent::PageKept {
+            url: page.url.clone(),
+        });
+        out.push(finish_page(page, cfg, tokenizer.as_ref()));
+    }
+    stats.pages_per_query = queries
+        .iter()
+        .map(|q| (q.clone(), out.iter().filter(|p| &p.query == q).count()))
+        .collect();
+    out
+}
+
+/// Where `search_stream` sends pages, with the per-page part of
+/// `finish_pages`, and failures.
+struct PageSink {
+    tx: mpsc::Sender<Result<StreamItem>>,
+    also_matched: HashMap<String, Vec<String>>,
+    final_urls: HashSet<String>,
+    deduper: Deduper,
+    /// Pages sent per query.
+    kept: HashMap<String, usize>,
+}
+
+impl PageSink {
+    async fn send(&mut self, page: CleanPage, ctx: &CrawlCtx, stats: &mut CrawlStats) {
+        let (cfg, hooks) = (&ctx.cfg, &ctx.hooks);
+        let started = Instant::now();
+
+        if !self.final_urls.insert(page.dedup_key()) {
+            stats.record_drop(DropReason::Duplicate);
+            hooks.dropped(&page.url, DropReason::Duplicate);
+            return;
+        }
+        if cfg.dedupe_content
+            && let Some(kept_url) = self
+                .deduper
+                .duplicate_of(&page, cfg.near_duplicate_threshold)
+        {
+            warn!(
+                url = %page.url,
+                reason = %DropReason::Duplicate,
+                same_as = %kept_url,
+                "dropped page"
+            );
+            stats.record_drop(DropReason::Duplicate);
+            hooks.emit(CrawlEvent::PageDropped {
+                url: page.url,
+                reason: DropReason::Duplicate,
+            });
+            return;
+        }
+
+        let mut page = finish_page(page, cfg, ctx.tokenizer.as_ref());
+        page.also_matched = self.also_matched.remove(&page.url).unwrap_or_default();
+        stats.clean_time += started.elapsed();
+        hooks.emit(CrawlEvent::PageKept {
+            url: page.url.clone(),
+        });
+        *self.kept.entry(page.query.clone()).or_default() += 1;
+        // A dropped receiver just means nobody is listening anymore.
+        let _ = self.tx.send(Ok(StreamItem::Page(page))).await;
+    }
+
+    async fn fail(&mut self, failure: CrawlFailure) {
+        let _ = self.tx.send(Ok(StreamItem::Failure(failure))).await;
+    }
+}
+
+/// Search hits turned into crawl jobs, deduped by URL across queries.
+struct Hits {
+    jobs: Vec<CrawlJob>,
+    /// Every URL already queued, for link-following dedup.
+    seen: HashSet<String>,
+    /// URL -> later queries that also hit it.
+    also_matched: HashMap<String, Vec<String>>,
+}
+
+/// Run every query through the search provider. Search failures go to the
+/// query's group; it's only an error when every query failed.
+async fn search_hits(
+    queries: &[String],
+    results_per_query: u32,
+    cfg: &LlmCleanConfig,
+    hooks: &SearchHooks,
+    groups: &mut [QueryResults],
+    stats: &mut CrawlStats,
+) -> Result<Hits> {
+    let mut jobs: Vec<CrawlJob> = Vec::new();
+    // URL -> the query it is crawled for, and any later queries that also hit it.
+    let mut owner: HashMap<String, String> = HashMap::new();
+    let mut also_matched: HashMap<String, Vec<String>> = HashMap::new();
+    let mut failed = 0usize;
+    let search_started = Instant::now();
+    let provider = hooks
+        .search
+        .clone()
+        .unwrap_or_else(|| cfg.search.provider());
+
+    // Search every query at once, up to `search_concurrency`; hits are then
+    // taken in query order so ranks and URL ownership don't depend on timing.
+    let shared = Arc::new(cfg.clone());
+    let permits = Arc::new(Semaphore::new(cfg.search_concurrency.max(1)));
+    let mut set = JoinSet::new();
+    for (qi, q) in queries.iter().enumerate() {
+        let (provider, cfg, hooks, permits) = (
+            provider.clone(),
+            shared.clone(),
+            hooks.clone(),
+            permits.clone(),
+        );
+        let q = q.clone();
+        set.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            hooks.emit(CrawlEvent::SearchStarted { query: q.clone() });
+            let results = expanded_search(provider.as_ref(), &q, results_per_query, &cfg, &hooks)
+                .instrument(info_span!("search", query = %q))
+                .await;
+            (qi, results)
+        });
+    }
+
+    let mut searched: Vec<Option<Result<Vec<SearchHit>>>> = queries.iter().map(|_| None).collect();
+    loop {
+        let done = tokio::select! {
+            done = set.join_next() => done,
+            _ = hooks.cancelled() => break,
+        };
+        match done {
+            Some(Ok((qi, results))) => searched[qi] = Some(results),
+            Some(Err(e)) => error!(error = %e, "search task failed to join"),
+            None => break,
+        }
+    }
+    // Cancelled searches are aborted, not waited for.
+    drop(set);
+
+    for (qi, (q, results)) in queries.iter().zip(searched).enumerate() {
+        let Some(results) = results else {
+            continue;
+        };
+        let results = match results {
+            Ok(results) => results,
+            Err(e) => {
+                let msg = format!("search failed for query='{q}': {e:#}");
+                error!(query = %q, error = %format!("{e:#}"), "search failed");
+                groups[qi].errors.push(msg);
+                failed += 1;
+                continue;
+            }
+        };
+
+        hooks.emit(CrawlEvent::SearchCompleted {
+            query: q.clone(),
+            results: results.len(),
+        });
+        stats.hits_per_query.push((q.clone(), results.len()));
+
+        for r in results {
+            if let Some(first) = owner.get(&r.url) {
+                stats.urls_deduped += 1;
+                let also = also_matched.entry(r.url).or_default();
+                if first != q && !also.contains(q) {
+                    also.push(q.clone());
+                }
+                continue;
+            }
+
+            owner.insert(r.url.clone(), q.clone());
+            // FIX #1: r.title is String, but we store Option<String>.
+            let title_opt = if r.title.trim().is_empty() {
+                None
+            } else {
+                Some(r.title.clone())
+            };
+            jobs.push(CrawlJob {
+                rank: vec![jobs.len()],
+                query: q.clone(),
+                url: r.url,
+                title: title_opt,
+                snippet: r.snippet.filter(|s| !s.trim().is_empty()),
+                via: None,
+                depth: 0,
+            });
+        }
+    }
+    stats.search_time = search_started.elapsed();
+
+    if failed == queries.len() {
+        bail!("{}", groups[0].errors[0]);
+    }
+
+    Ok(Hits {
+        jobs,
+        seen: owner.into_keys().collect(),
+        also_matched,
+    })
+}
+
+/// `query`'s hits, merged with its variants' when expansion is on. Only
+/// `query` failing is an error; a failed variant is skipped.
+async fn expanded_search(
+    provider: &dyn SearchProvider,
+    query: &str,
+    results_per_query: u32,
+    cfg: &LlmCleanConfig,
+    hooks: &SearchHooks,
+) -> Result<Vec<SearchHit>> {
+    let variants = match &hooks.expander {
+        Some(expander) => expander.expand(query).await,
+        None => cfg.query_expansion.expand(query).await,
+    };
+    let variants = match variants {
+        Ok(variants) => {
+            expand::distinct_variants(query, variants, cfg.query_expansion.max_variants)
+        }
+        Err(e) => {
+            warn!(query, error = %format!("{e:#}"), "query expansion failed");
+            Vec::new()
+        }
+    };
+    if variants.is_empty() {
+        return provider.search(query, results_per_query).await;
+    }
+
+    debug!(query, ?variants, "expanded query");
+    let mut lists = vec![provider.search(query, results_per_query).await?];
+    for variant in variants {
+        match provider.search(&variant, results_per_query).await {
+            Ok(hits) => lists.push(hits),
+            Err(e) => warn!(query = %variant, error = %format!("{e:#}"), "variant search failed"),
+        }
+    }
+    Ok(expand::interleave(lists))
+}
+
+fn crawl_ctx(
+    cfg: &LlmCleanConfig,
+    hooks: &SearchHooks,
+    site: Option<reqwest::Url>,
+) -> Result<Arc<CrawlCtx>> {
+    cfg.validate()?;
+
+    let fetcher: Arc<dyn Fetcher> = match &hooks.fetcher {
+        Some(fetcher) => fetcher.clone(),
+        None => Arc::new(ReqwestFetcher::new(cfg)?),
+    };
+
+    Ok(Arc::new(CrawlCtx {
+        fetcher,
+        cfg: cfg.clone(),
+        hooks: hooks.clone(),
+        sem: Semaphore::new(cfg.concurrency),
+        hosts: HostLimiter::new(cfg.per_host_concurrency, cfg.per_host_requests_per_sec),
+        cache: cfg
+            .cache_dir
+            .clone()
+            .map(|dir| PageCache::new(dir, cfg.cache_ttl_secs, cfg.cache_max_bytes)),
+        robots: cfg.respect_robots_txt.then(Robots::default),
+        tokenizer: hooks.tokenizer(cfg),
+        site,
+        canonicals: Mutex::new(HashSet::new()),
+        deadline: cfg
+            .search_deadline_secs
+            .map(|secs| tokio::time::Instant::now() + Duration::from_secs(secs)),
+        bytes: AtomicUsize::new(0),
+        spent: CancellationToken::new(),
+        #[cfg(feature = "browser")]
+        renderer: cfg.render_js.then(|| browser::Renderer::new(cfg)),
+    }))
+}
+
+/// Dedup, boilerplate stripping and `finish_page` over rank-ordered pages.
+fn finish_pages(
+    mut pages: Vec<CleanPage>,
+    queries: &[String],
+    cfg: &LlmCleanConfig,
+    hooks: &SearchHooks,
+    stats: &mut CrawlStats,
+    failures: &mut Vec<CrawlFailure>,
+) -> Vec<MdPage> {
+    let started = Instant::now();
+
+    // 0) Different URLs that redirected to the same page.
+    let mut final_urls: HashSet<String> = HashSet::new();
+    pages.retain(|page| {
+        let keep = final_urls.insert(page.dedup_key());
+        if !keep {
+            stats.record_drop(DropReason::Duplicate);
+            hooks.dropped(&page.url, DropReason::Duplicate);
+        }
+        keep
+    });
+
+    // 1) Content dedup across URLs (mirrors, print views, AMP), keeping the higher-ranked page.
+    if cfg.dedupe_content {
+        let (kept, dropped) = dedup::dedupe_pages(pages, cfg.near_duplicate_threshold);
+        for (page, kept_url) in dropped {
+            warn!(
+                url = %page.url,
+                reason = %DropReason::Duplicate,
+                same_as = %kept_url,
+                "dropped page"
+            );
+            stats.record_drop(DropReason::Duplicate);
+            hooks.emit(CrawlEvent::PageDropped {
+                url: page.url,
+                reason: DropReason::Duplicate,
+            });
+        }
+        pages = kept;
+    }
+
+    // 2) Cross-page boilerplate, before the size cap so the freed budget is usable.
+    if cfg.dedupe_boilerplate {
+        boilerplate::strip_boilerplate(&mut pages, cfg);
+        pages.retain(|page| {
+            let keep = page.md.chars().count() >= cfg.min_md_chars;
+            if !keep {
+                stats.record_drop(DropReason::TooShort);
+                hooks.dropped(&page.url, DropReason::TooShort);
+                failures.push(CrawlFailure {
+                    query: page.query.clone(),
+                    url: Some(page.url.clone()),
+                    reason: FailureReason::TooSmall,
+                    message: "too short after boilerplate stripping".to_string(),
+                });
+            }
+            keep
+        });
+    }
+
+    if cfg.merge_followed_links {
+        pages = merge_followed(pages);
+    }
+
+    let tokenizer = hooks.tokenizer(cfg);
+    let mut out: Vec<MdPage> = pages
+        .into_iter()
+        .map(|page| finish_page(page, cfg, tokenizer.as_ref()))
+        .collect();
+
+    // 3) Optional re-sort by score, keeping queries in their original order.
+    if cfg.sort_by_relevance {
+        let query_pos = |q: &str| queries.iter().position(|x| x == q);
+        out.sort_by(|a, b| {
+            query_pos(&a.query)
+                .cmp(&query_pos(&b.query))
+                .then(b.score.total_cmp(&a.score))
+        });
+    }
+    stats.clean_time += started.elapsed();
+
+    for page in &out {
+        hooks.emit(CrawlEvent::PageKept {
+            url: page.url.clone(),
+        });
+    }
+
+    stats.pages_per_query = queries
+        .iter()
+        .map(|q| (q.clone(), out.iter().filter(|p| &p.query == q).count()))
+        .collect();
+
+    out
+}
+
+/// Followed pages folded into the page that links to them, in link order
+/// and depth-first. `pages` is in rank order, so a parent comes before its
+/// children; a page whose parent was dropped stays a page of its own.
+fn merge_followed(pages: Vec<CleanPage>) -> Vec<CleanPage> {
+    let index: HashMap<&str, usize> = pages
+        .iter()
+        .enumerate()
+        .map(|(i, page)| (page.url.as_str(), i))
+        .collect();
+    let parents: Vec<Option<usize>> = pages
+        .iter()
+        .enumerate()
+        .map(|(i, page)| {
+            let parent = index.get(page.via.as_deref()?).copied()?;
+            (parent < i).then_some(parent)
+        })
+        .collect();
+    drop(index);
+
+    let mut children: Vec<Vec<CleanPage>> = pages.iter().map(|_| Vec::new()).collect();
+    let mut slots: Vec<Option<CleanPage>> = pages.into_iter().map(Some).collect();
+    // Last to first, so each page has its own children in before it moves up.
+    for i in (0..slots.len()).rev() {
+        let Some(mut page) = slots[i].take() else {
+            continue;
+        };
+        for child in children[i].drain(..).rev() {
+            let title = child.title.as_deref().unwrap_or(&child.final_url);
+            page.md.push_str(&format!(
+                "\n\n## {title}\n\nSource: <{}>\n\n{}",
+                child.final_url,
+                child.md.trim()
+            ));
+        }
+        match parents[i] {
+            Some(parent) => children[parent].push(page),
+            None => slots[i] = Some(page),
+        }
+    }
+    slots.into_iter().flatten().collect()
+}
+
+/// Run `jobs` through the fetch pipeline, following links as configured.
+///
+/// `seen` is the URL dedup set shared with the caller. Returns the kept pages
+/// in rank order, and the URLs that failed or were dropped. With a `sink`,
+/// kept pages and failures go there as they complete instead.
+async fn crawl_all(
+    ctx: &Arc<CrawlCtx>,
+    jobs: Vec<CrawlJob>,
+    seen: &mut HashSet<String>,
+    stats: &mut CrawlStats,
+    mut sink: Option<&mut PageSink>,
+) -> (Vec<CleanPage>, Vec<CrawlFailure>) {
+    let (cfg, hooks) = (&ctx.cfg, &ctx.hooks);
+    let fetch_started = Instant::now();
+    let mut set: JoinSet<CrawlDone> = JoinSet::new();
+    let mut spawned = 0usize;
+    let mut failures = Vec::new();
+
+    for job in jobs {
+        if hooks.is_cancelled() || spawned >= cfg.max_pages {
+            break;
+        }
+        if ctx.over_budget() {
+            stats.record_drop(DropReason::OverBudget);
+            hooks.dropped(&job.url, DropReason::OverBudget);
+            let failure = CrawlFailure {
+                query: job.query,
+                url: Some(job.url),
+                reason: FailureReason::OverBudget,
+                message: DropReason::OverBudget.to_string(),
+            };
+            match sink.as_deref_mut() {
+                Some(sink) => sink.fail(failure).await,
+                None => failures.push(failure),
+            }
+            continue;
+        }
+        spawn_crawl(&mut set, ctx, job);
+        spawned += 1;
+    }
+
+    let mut ranked = Vec::new();
+    let mut latencies = Vec::new();
+    while let Some(res) = set.join_next().await {
+        let (job, res, metrics, links) = match res {
+            Ok(done) => done,
+            Err(e) => {
+                error!(error = %e, "crawl task failed to join");
+                continue;
+            }
+        };
+
+        stats.record_fetch(&metrics);
+        latencies.extend(metrics.latency);
+
+        // Links are followed even when the page itself is dropped: index pages
+        // are usually too link-heavy to keep.
+        for (i, url) in links.into_iter().enumerate() {
+            if hooks.is_cancelled() || spawned >= cfg.max_pages || ctx.over_budget() {
+                break;
+            }
+            if !seen.insert(url.clone()) {
+                stats.urls_deduped += 1;
+                continue;
+            }
+
+            let mut rank = job.rank.clone();
+            rank.push(i);
+            spawn_crawl(
+                &mut set,
+                ctx,
+                CrawlJob {
+                    rank,
+                    query: job.query.clone(),
+                    url,
+                    title: None,
+                    snippet: None,
+                    via: Some(job.url.clone()),
+                    depth: job.depth + 1,
+                },
+            );
+            spawned += 1;
+        }
+
+        let failure = match res {
+            Ok(Crawled::Page(page)) => {
+                match sink.as_deref_mut() {
+                    Some(sink) => sink.send(*page, ctx, stats).await,
+                    None => ranked.push((job.rank, *page)),
+                }
+                continue;
+            }
+            Ok(Crawled::Dropped(reason)) => {
+                stats.record_drop(reason);
+                hooks.dropped(&job.url, reason);
+                let Some(failure) = FailureReason::from_drop(reason, metrics.status) else {
+                    continue;
+                };
+                CrawlFailure {
+                    query: job.query,
+                    url: Some(job.url),
+                    reason: failure,
+                    message: reason.to_string(),
+                }
+            }
+            Err(e) => {
+                stats.fetch_errors += 1;
+                error!(url = %job.url, error = %format!("{e:#}"), "fetch failed");
+                CrawlFailure {
+                    query: job.query,
+                    url: Some(job.url),
+                    reason: FailureReason::from_error(&e),
+                    message: format!("{e:#}"),
+                }
+            }
+        };
+        match sink.as_deref_mut() {
+            Some(sink) => sink.fail(failure).await,
+            None => failures.push(failure),
         }
     }
+    stats.fetch_time = fetch_started.elapsed();
+    stats.set_latencies(latencies);
+
+    ranked.sort_by(|(a, _), (b, _)| a.cmp(b));
+    (ranked.into_iter().map(|(_, page)| page).collect(), failures)
 }
 
-// Precompiled regex for speed
-static RE_SCRIPT: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"(?is)<script\b[^>]*>.*?</script>").unwrap());
-static RE_STYLE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<style\b[^>]*>.*?</style>").unwrap());
-static RE_NOSCRIPT: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"(?is)<noscript\b[^>]*>.*?</noscript>").unwrap());
+fn spawn_crawl(set: &mut JoinSet<CrawlDone>, ctx: &Arc<CrawlCtx>, job: CrawlJob) {
+    let ctx = ctx.clone();
+    set.spawn(async move {
+        let mut metrics = FetchMetrics::default();
+        let mut links = Vec::new();
+        let crawl = async {
+            let _permit = ctx.sem.acquire().await.expect("semaphore closed");
+            crawl_to_llm_markdown(&ctx, &job, &mut metrics, &mut links).await
+        };
+        // Dropping the crawl future aborts its in-flight request.
+        let res = tokio::select! {
+            res = crawl => res,
+            _ = ctx.hooks.cancelled() => Ok(Crawled::Dropped(DropReason::Cancelled)),
+            _ = ctx.budget_spent() => Ok(Crawled::Dropped(DropReason::OverBudget)),
+        };
+        ctx.add_bytes(metrics.bytes);
+        (job, res, metrics, links)
+    });
+}
 
-// Remove inline data:image/... blobs in markdown link targets (token poison)
-static RE_DATA_IMG: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)\(data:image/[^)]*\)").unwrap());
+#[instrument(skip_all, fields(url = %job.url, query = %job.query, status, elapsed_ms, bytes))]
+async fn crawl_to_llm_markdown(
+    ctx: &CrawlCtx,
+    job: &CrawlJob,
+    metrics: &mut FetchMetrics,
+    to_follow: &mut Vec<String>,
+) -> Result<Crawled> {
+    let cfg = &ctx.cfg;
+    let domain_ok =
+        |url: &str| domains::is_allowed(url, &cfg.allowed_domains, &cfg.blocked_domains);
 
-// “Just a link bullet” line
-static RE_LINK_ONLY: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r#"^\s*[-*+]\s+\[[^\]]+\]\([^)]+\)\s*$"#).unwrap());
+    if !domain_ok(&job.url) {
+        return Ok(Crawled::Dropped(DropReason::BlockedDomain));
+    }
+    let fragment = cfg
+        .extract_fragments
+        .then(|| {
+            reqwest::Url::parse(&job.url)
+                .ok()?
+                .fragment()
+                .map(str::to_string)
+        })
+        .flatten();
+    // Another page already declared this URL as its canonical: skip the fetch.
+    if ctx.is_known_canonical(&job.url, fragment.as_deref()) {
+        return Ok(Crawled::Dropped(DropReason::CanonicalDuplicate));
+    }
 
-/// Public API: array of queries + results per query.
-pub async fn search(queries: &[String], results_per_query: u32) -> Result<Vec<MdPage>> {
-    search_with_config(queries, results_per_query, &LlmCleanConfig::default()).await
-}
+    if cfg.site_extractors
+        && let Some(api) = sites::ApiPage::for_url(&job.url)
+        && let Some(crawled) = crawl_api(ctx, job, &api, metrics).await
+    {
+        return Ok(crawled);
+    }
 
-/// Same as `search`, but configurable.
-pub async fn search_with_config(
-    queries: &[String],
-    results_per_query: u32,
-    cfg: &LlmCleanConfig,
-) -> Result<Vec<MdPage>> {
-    if queries.is_empty() || results_per_query == 0 {
-        return Ok(vec![]);
+    let page = match fetch_page(ctx, &job.url, metrics).await? {
+        Ok(page) => page,
+        Err(reason) => return Ok(Crawled::Dropped(reason)),
+    };
+    // Redirected off the allowed sites.
+    if !domain_ok(&page.final_url) {
+        return Ok(Crawled::Dropped(DropReason::BlockedDomain));
+    }
+    if cfg.ingest_pdfs && pdf::is_pdf(page.content_type.as_deref(), &page.body) {
+        return crawl_pdf(ctx, job, page, metrics).await;
+    }
+    if cfg.convert_non_html
+        && let Some(kind) = BodyKind::of(page.content_type.as_deref())
+    {
+        return crawl_converted(ctx, job, page, kind, metrics);
     }
+    let (mut status, mut final_url) = (page.status, page.final_url);
 
-    // 1) DDG search via websearch (no API keys).
-    let mut jobs: Vec<(String, String, Option<String>)> = Vec::new();
-    let mut seen_urls: HashSet<String> = HashSet::new();
+    let clean_started = Instant::now();
+    let mut html = body_to_html(&page.body, page.content_type.as_deref(), cfg);
+    let (mut extracted_html, mut md) = html_to_markdown(&html, &final_url, cfg);
+    metrics.clean_time += clean_started.elapsed();
 
-    for q in queries {
-        let provider = DuckDuckGoProvider::with_config(DuckDuckGoConfig::default());
+    // Interstitials (meta refresh, `location = ...`) have no content of their own:
+    // follow one client-side redirect, never a chain.
+    let mut client_redirect = false;
+    if md.chars().count() < cfg.min_md_chars
+        && let Some(target) = redirect::client_redirect_target(&html, &final_url)
+        && target != final_url
+    {
+        if !domain_ok(&target) {
+            return Ok(Crawled::Dropped(DropReason::BlockedDomain));
+        }
+        if ctx.is_known_canonical(&target, None) {
+            return Ok(Crawled::Dropped(DropReason::CanonicalDuplicate));
+        }
+        debug!(target = %target, "following client-side redirect");
+        let page = match fetch_page(ctx, &target, metrics).await? {
+            Ok(page) => page,
+            Err(reason) => return Ok(Crawled::Dropped(reason)),
+        };
+        if !domain_ok(&page.final_url) {
+            return Ok(Crawled::Dropped(DropReason::BlockedDomain));
+        }
+        (status, final_url) = (page.status, page.final_url);
+        client_redirect = true;
 
-        let results = web_search(SearchOptions {
-            query: q.clone(),
-            max_results: Some(results_per_query),
-            provider: Box::new(provider),
-            ..Default::default()
-        })
-        .await
-        .map_err(|e| anyhow!("search failed for query='{q}': {e}"))?;
+        let clean_started = Instant::now();
+        html = body_to_html(&page.body, page.content_type.as_deref(), cfg);
+        (extracted_html, md) = html_to_markdown(&html, &final_url, cfg);
+        metrics.clean_time += clean_started.elapsed();
+    }
 
-        for r in results {
-            if seen_urls.insert(r.url.clone()) {
-                // FIX #1: r.title is String, but we store Option<String>.
-                let title_opt = if r.title.trim().is_empty() {
-                    None
-                } else {
-                    Some(r.title.clone())
-                };
-                jobs.push((q.clone(), r.url, title_opt));
+    // Q&A sites and the like, where knowing the markup beats guessing at it.
+    if cfg.site_extractors
+        && let Some(site_md) = sites::extract(&html, &final_url, cfg)
+    {
+        md = site_md;
+    }
+
+    // Still near-empty: let a headless browser run the page's scripts.
+    let mut extraction = Extraction::Content;
+    #[cfg(feature = "browser")]
+    if let Some(renderer) = &ctx.renderer
+        && md.chars().count() < cfg.min_md_chars
+        && browser::looks_js_rendered(&html, &md)
+    {
+        match renderer.render(&final_url, cfg).await {
+            Ok(rendered) => {
+                let clean_started = Instant::now();
+                html = rendered;
+                (extracted_html, md) = html_to_markdown(&html, &final_url, cfg);
+                metrics.clean_time += clean_started.elapsed();
+                extraction = Extraction::Rendered;
             }
+            Err(e) => warn!(url = %final_url, error = %format!("{e:#}"), "rendering failed"),
         }
     }
 
-    if jobs.is_empty() {
-        return Ok(vec![]);
+    // A link to `#anchor` is after that section, not the whole page.
+    let mut section = None;
+    if let Some(fragment) = &fragment
+        && let Some(section_src) = fragment::section_html(&html, fragment)
+    {
+        let clean_started = Instant::now();
+        let (section_html, section_md) = html_to_markdown(&section_src, &final_url, cfg);
+        metrics.clean_time += clean_started.elapsed();
+        if section_md.chars().count() >= cfg.min_md_chars {
+            debug!(fragment, "extracted the linked section");
+            (extracted_html, md) = (section_html, section_md);
+            section = Some(fragment.as_str());
+        }
+    }
+
+    // Mobile/AMP/localized variants share a canonical; whichever finishes first wins.
+    let declared = links::canonical_url(&html, &final_url);
+    let canonical = declared.clone().unwrap_or_else(|| final_url.clone());
+    if !ctx.claim_canonical(&canonical, &final_url, section) {
+        debug!(canonical = %canonical, "canonical already fetched");
+        return Ok(Crawled::Dropped(DropReason::CanonicalDuplicate));
     }
 
-    // 2) Fast parallel fetch + extract + clean + convert.
-    let client = Client::builder()
-        .user_agent("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
-        .timeout(std::time::Duration::from_secs(cfg.timeout_secs))
-        .pool_max_idle_per_host(8)
-        .redirect(reqwest::redirect::Policy::limited(10))
-        .build()
-        .context("failed to build reqwest client")?;
+    if job.depth < cfg.follow_links_depth {
+        *to_follow = match &ctx.site {
+            // Navigation is what connects a site, so look at the whole page.
+            Some(site) => links::site_links(&html, &final_url, site),
+            None => links::follow_candidates(
+                &extracted_html,
+                &final_url,
+                &job.query,
+                cfg.max_followed_links_per_page,
+            ),
+        };
+    }
+
+    if md.chars().count() < cfg.min_md_chars {
+        // Near-empty bodies are usually JS-rendered; the markup often embeds the text anyway.
+        match fallback::fallback_markdown(&html, cfg) {
+            Some((from, text)) => {
+                debug!(extraction = %from, chars = text.chars().count(), "using fallback content");
+                extraction = from;
+                md = text;
+            }
+            None => return Ok(Crawled::Dropped(DropReason::JsRendered)),
+        }
+    }
 
-    let sem = Arc::new(Semaphore::new(cfg.concurrency));
-    let mut set: JoinSet<Result<Option<MdPage>>> = JoinSet::new();
+    // Navigation hubs survive the length check but carry almost no prose.
+    if matches!(extraction, Extraction::Content | Extraction::Rendered)
+        && (link_density(&md) > cfg.max_link_density || prose_paragraphs(&md) < cfg.min_paragraphs)
+    {
+        return Ok(Crawled::Dropped(DropReason::LowQuality));
+    }
+    if !language::is_allowed(&md, &cfg.allowed_languages) {
+        return Ok(Crawled::Dropped(DropReason::WrongLanguage));
+    }
+    if cfg.links == LinkPolicy::Strip {
+        md = rewrite::strip_md_links(&md);
+    }
 
-    for (query, url, title) in jobs {
-        let client = client.clone();
-        let sem = sem.clone();
-        let cfg = cfg.clone();
+    Ok(Crawled::Page(Box::new(CleanPage {
+        query: job.query.clone(),
+        url: job.url.clone(),
+        final_url,
+        section: section.map(str::to_string),
+        client_redirect,
+        extraction,
+        status,
+        title: job.title.clone(),
+        snippet: job.snippet.clone(),
+        meta: PageMeta::extract(&html, declared),
+        via: job.via.clone(),
+        md,
+    })))
+}
 
-        set.spawn(async move {
-            let _permit = sem.acquire().await.expect("semaphore closed");
-            crawl_to_llm_markdown(&client, &cfg, &query, &url, title).await
-        });
+/// A page built from its site's API (see `sites::ApiPage`) instead of its
+/// HTML. `None` when the API doesn't answer usably, e.g. when rate limited:
+/// the page is then fetched as usual.
+async fn crawl_api(
+    ctx: &CrawlCtx,
+    job: &CrawlJob,
+    api: &sites::ApiPage,
+    metrics: &mut FetchMetrics,
+) -> Option<Crawled> {
+    let cfg = &ctx.cfg;
+    let mut bodies = Vec::new();
+    for call in api.calls(cfg) {
+        bodies.push(fetch_api(ctx, call, metrics).await?);
     }
 
-    let mut out = Vec::new();
-    while let Some(res) = set.join_next().await {
-        match res {
-            Ok(Ok(Some(page))) => out.push(page),
-            Ok(Ok(None)) => {} // dropped by filters
-            Ok(Err(e)) => eprintln!("crawl error: {e:#}"),
-            Err(e) => eprintln!("task join error: {e}"),
-        }
+    let clean_started = Instant::now();
+    let (title, mut md) = api.render(&bodies)?;
+    metrics.clean_time += clean_started.elapsed();
+    if !ctx.claim_canonical(&job.url, &job.url, None) {
+        return Some(Crawled::Dropped(DropReason::CanonicalDuplicate));
+    }
+    if md.chars().count() < cfg.min_md_chars {
+        return Some(Crawled::Dropped(DropReason::TooShort));
+    }
+    if !language::is_allowed(&md, &cfg.allowed_languages) {
+        return Some(Crawled::Dropped(DropReason::WrongLanguage));
     }
+    if cfg.links == LinkPolicy::Strip {
+        md = rewrite::strip_md_links(&md);
+    }
+    metrics.status = Some(200);
 
-    Ok(out)
+    Some(Crawled::Page(Box::new(CleanPage {
+        query: job.query.clone(),
+        url: job.url.clone(),
+        final_url: job.url.clone(),
+        section: None,
+        client_redirect: false,
+        extraction: Extraction::Content,
+        status: 200,
+        title: title.or_else(|| job.title.clone()),
+        snippet: job.snippet.clone(),
+        meta: PageMeta::default(),
+        via: job.via.clone(),
+        md,
+    })))
 }
 
-async fn crawl_to_llm_markdown(
-    client: &Client,
-    cfg: &LlmCleanConfig,
-    query: &str,
-    url: &str,
-    title_from_search: Option<String>,
-) -> Result<Option<MdPage>> {
-    let resp = client
-        .get(url)
-        .header("Accept", "text/html,application/xhtml+xml")
-        .send()
+/// One API request for `crawl_api`: no cache, robots.txt or retries, since
+/// the page itself is the fallback. `None` unless it answers 2xx.
+async fn fetch_api(
+    ctx: &CrawlCtx,
+    call: sites::ApiCall,
+    metrics: &mut FetchMetrics,
+) -> Option<Vec<u8>> {
+    let req = FetchRequest {
+        url: call.url.clone(),
+        headers: call.headers,
+        max_body_bytes: ctx.cfg.max_html_bytes,
+    };
+    let host_slot = ctx.hosts.acquire(&call.url).await;
+    let sent = Instant::now();
+    let result = ctx.fetcher.fetch(req).await;
+    drop(host_slot);
+
+    let resp = match result {
+        Ok(resp) => resp,
+        Err(e) => {
+            debug!(url = %call.url, error = %format!("{e:#}"), "api request failed");
+            return None;
+        }
+    };
+    metrics.latency = Some(metrics.latency.unwrap_or_default() + sent.elapsed());
+    metrics.bytes += resp.body.len();
+    if !(200..=299).contains(&resp.status) {
+        debug!(url = %call.url, status = resp.status, "api request refused");
+        return None;
+    }
+    Some(resp.body)
+}
+
+/// PDFs skip HTML extraction, client redirects, link following and the
+/// link-density check; outline and truncation happen in `finish_page` as usual.
+async fn crawl_pdf(
+    ctx: &CrawlCtx,
+    job: &CrawlJob,
+    page: Fetched,
+    metrics: &mut FetchMetrics,
+) -> Result<Crawled> {
+    let cfg = &ctx.cfg;
+    if !ctx.claim_canonical(&page.final_url, &page.final_url, None) {
+        return Ok(Crawled::Dropped(DropReason::CanonicalDuplicate));
+    }
+
+    let clean_started = Instant::now();
+    let text = pdf::pdf_to_markdown(page.body)
         .await
-        .with_context(|| format!("request failed: {url}"))?;
+        .with_context(|| format!("unreadable pdf: {}", job.url))?;
+    let md = clean_markdown_for_llm(&text, cfg);
+    metrics.clean_time += clean_started.elapsed();
 
-    let status = resp.status().as_u16();
+    if md.chars().count() < cfg.min_md_chars {
+        return Ok(Crawled::Dropped(DropReason::TooShort));
+    }
+    if !language::is_allowed(&md, &cfg.allowed_languages) {
+        return Ok(Crawled::Dropped(DropReason::WrongLanguage));
+    }
 
-    if cfg.drop_non_success_status && !(200..=299).contains(&status) {
-        return Ok(None);
+    Ok(Crawled::Page(Box::new(CleanPage {
+        query: job.query.clone(),
+        url: job.url.clone(),
+        final_url: page.final_url,
+        section: None,
+        client_redirect: false,
+        extraction: Extraction::Pdf,
+        status: page.status,
+        title: job.title.clone(),
+        snippet: job.snippet.clone(),
+        meta: PageMeta::default(),
+        via: job.via.clone(),
+        md,
+    })))
+}
+
+/// JSON, feeds and plain text: converted to markdown without HTML
+/// extraction, client redirects or link following.
+fn crawl_converted(
+    ctx: &CrawlCtx,
+    job: &CrawlJob,
+    page: Fetched,
+    kind: BodyKind,
+    metrics: &mut FetchMetrics,
+) -> Result<Crawled> {
+    let cfg = &ctx.cfg;
+    if !ctx.claim_canonical(&page.final_url, &page.final_url, None) {
+        return Ok(Crawled::Dropped(DropReason::CanonicalDuplicate));
     }
 
-    if cfg.require_html_content_type {
-        let is_html = resp
-            .headers()
-            .get(CONTENT_TYPE)
-            .and_then(|v| v.to_str().ok())
-            .map(|v| v.to_ascii_lowercase().contains("text/html"))
-            .unwrap_or(false);
+    let clean_started = Instant::now();
+    let body = body_to_html(&page.body, page.content_type.as_deref(), cfg);
+    let Some((title, md)) = convert::to_markdown(kind, &body, cfg.max_md_chars) else {
+        bail!("unreadable {kind:?} body: {}", job.url);
+    };
+    // Fenced JSON has no prose to clean, and long string values are content.
+    let md = match kind {
+        BodyKind::Json => md,
+        BodyKind::Feed | BodyKind::Text => clean_markdown_for_llm(&md, cfg),
+    };
+    metrics.clean_time += clean_started.elapsed();
 
-        if !is_html {
-            return Ok(None);
-        }
+    if md.chars().count() < cfg.min_md_chars {
+        return Ok(Crawled::Dropped(DropReason::TooShort));
+    }
+    if kind != BodyKind::Json && !language::is_allowed(&md, &cfg.allowed_languages) {
+        return Ok(Crawled::Dropped(DropReason::WrongLanguage));
     }
 
-    let bytes = resp
-        .bytes()
-        .await
-        .with_context(|| format!("failed reading body: {url}"))?;
+    Ok(Crawled::Page(Box::new(CleanPage {
+        query: job.query.clone(),
+        url: job.url.clone(),
+        final_url: page.final_url,
+        section: None,
+        client_redirect: false,
+        extraction: kind.extraction(),
+        status: page.status,
+        title: job.title.clone().or(title),
+        snippet: job.snippet.clone(),
+        meta: PageMeta::default(),
+        via: job.via.clone(),
+        md,
+    })))
+}
 
-    let slice = if bytes.len() > cfg.max_html_bytes {
-        &bytes[..cfg.max_html_bytes]
+/// A response body that passed the status and content-type checks.
+struct Fetched {
+    status: u16,
+    content_type: Option<String>,
+    body: Vec<u8>,
+    final_url: String,
+}
+
+/// Fetch `url` through the cache and the fetcher. The inner `Err` is a drop.
+async fn fetch_page(
+    ctx: &CrawlCtx,
+    url: &str,
+    metrics: &mut FetchMetrics,
+) -> Result<Result<Fetched, DropReason>> {
+    let (cfg, hooks) = (&ctx.cfg, &ctx.hooks);
+    let started = Instant::now();
+
+    let lookup = match &ctx.cache {
+        Some(cache) => cache.load(url).await,
+        None => Lookup::Miss,
+    };
+
+    // Cache hits were allowed when they were fetched; only new requests are checked.
+    // Checked before `FetchStarted`, which always gets a `FetchCompleted` or `FetchFailed`.
+    if !matches!(lookup, Lookup::Fresh(_))
+        && let Some(robots) = &ctx.robots
+        && !robots.admit(ctx.fetcher.as_ref(), url).await
+    {
+        return Ok(Err(DropReason::RobotsDisallowed));
+    }
+    hooks.emit(CrawlEvent::FetchStarted {
+        url: url.to_string(),
+    });
+
+    let fetched = if let Lookup::Fresh(hit) = lookup {
+        metrics.cache_hit = true;
+        metrics.status = Some(hit.status);
+        hooks.emit(CrawlEvent::FetchCompleted {
+            url: url.to_string(),
+            status: hit.status,
+            elapsed: started.elapsed(),
+        });
+        // The config may have changed since the entry was written.
+        if let Some(reason) = check_response(cfg, hit.status, hit.content_type.as_deref()) {
+            return Ok(Err(reason));
+        }
+        Fetched {
+            status: hit.status,
+            content_type: hit.content_type,
+            body: hit.body,
+            final_url: hit.final_url,
+        }
     } else {
-        bytes.as_ref()
+        let stale = match lookup {
+            Lookup::Stale(entry) => Some(entry),
+            _ => None,
+        };
+
+        let accept = if cfg.ingest_pdfs {
+            "text/html,application/xhtml+xml,application/pdf;q=0.9"
+        } else {
+            "text/html,application/xhtml+xml"
+        };
+        let mut headers = vec![(ACCEPT, accept.to_string())];
+        if let Some(entry) = &stale {
+            if let Some(etag) = &entry.etag {
+                headers.push((IF_NONE_MATCH, etag.clone()));
+            }
+            if let Some(modified) = &entry.last_modified {
+                headers.push((IF_MODIFIED_SINCE, modified.clone()));
+            }
+        }
+
+        let mut attempt = 1;
+        let (result, sent) = loop {
+            let req = FetchRequest {
+                url: url.to_string(),
+                headers: headers.clone(),
+                max_body_bytes: max_body_bytes(cfg),
+            };
+            let host_slot = ctx.hosts.acquire(url).await;
+            let sent = Instant::now();
+            let result = ctx.fetcher.fetch(req).await;
+            drop(host_slot);
+
+            let retry_in = match &result {
+                Ok(resp) if retry::is_retryable_status(resp.status) => {
+                    cfg.retry.delay(attempt, resp.retry_after.as_deref())
+                }
+                Err(e) if retry::is_transient(e) => cfg.retry.delay(attempt, None),
+                _ => None,
+            };
+            match retry_in {
+                Some(delay) if attempt < cfg.retry.max_attempts => {
+                    debug!(
+                        url,
+                        attempt,
+                        delay_ms = delay.as_millis() as u64,
+                        "retrying fetch"
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                _ => break (result, sent),
+            }
+        };
+        let resp = match result {
+            Ok(resp) => resp,
+            Err(e) => {
+                hooks.emit(CrawlEvent::FetchFailed {
+                    url: url.to_string(),
+                    error: e.to_string(),
+                    elapsed: started.elapsed(),
+                });
+                return Err(e).with_context(|| format!("request failed: {url}"));
+            }
+        };
+
+        let status = resp.status;
+        metrics.latency = Some(sent.elapsed());
+        metrics.status = Some(status);
+        Span::current()
+            .record("status", status)
+            .record("elapsed_ms", started.elapsed().as_millis() as u64)
+            .record("bytes", resp.body.len());
+        metrics.bytes += resp.body.len();
+        hooks.emit(CrawlEvent::FetchCompleted {
+            url: url.to_string(),
+            status,
+            elapsed: started.elapsed(),
+        });
+
+        // 304 only comes back for our conditional request: the cached body is still
+        // current, and its stored status (not 304) is what the drop checks see.
+        if status == 304
+            && let Some(mut entry) = stale
+        {
+            metrics.cache_hit = true;
+            metrics.revalidated = true;
+            metrics.status = Some(entry.status);
+            entry.etag = resp.etag.or(entry.etag);
+            entry.last_modified = resp.last_modified.or(entry.last_modified);
+            if let Some(cache) = &ctx.cache {
+                cache.refresh(url, &entry).await;
+            }
+            if let Some(reason) = check_response(cfg, entry.status, entry.content_type.as_deref()) {
+                return Ok(Err(reason));
+            }
+            Fetched {
+                status: entry.status,
+                content_type: entry.content_type,
+                body: entry.body,
+                final_url: entry.final_url,
+            }
+        } else {
+            if let Some(reason) = check_response(cfg, status, resp.content_type.as_deref()) {
+                return Ok(Err(reason));
+            }
+
+            let fresh = CachedResponse {
+                status,
+                content_type: resp.content_type,
+                etag: resp.etag,
+                last_modified: resp.last_modified,
+                body: resp.body,
+                final_url: resp.final_url,
+            };
+            if let Some(cache) = &ctx.cache {
+                cache.store(url, &fresh).await;
+            }
+            Fetched {
+                status,
+                content_type: fresh.content_type,
+                body: fresh.body,
+                final_url: fresh.final_url,
+            }
+        }
     };
 
-    let html = String::from_utf8_lossy(slice).into_owned();
+    Ok(Ok(fetched))
+}
+
+/// Bytes read per response; the type isn't known until the headers arrive.
+fn max_body_bytes(cfg: &LlmCleanConfig) -> usize {
+    if cfg.ingest_pdfs {
+        cfg.max_html_bytes.max(cfg.max_pdf_bytes)
+    } else {
+        cfg.max_html_bytes
+    }
+}
+
+fn body_to_html(body: &[u8], content_type: Option<&str>, cfg: &LlmCleanConfig) -> String {
+    // Fetchers cap the body already; cached entries may predate a smaller cap.
+    let slice = &body[..body.len().min(cfg.max_html_bytes)];
+    charset::decode_html(slice, content_type)
+}
 
+/// Main-content HTML (kept for link following) and its cleaned markdown.
+/// `url` is where the page came from, for resolving relative links and images.
+fn html_to_markdown(html: &str, url: &str, cfg: &LlmCleanConfig) -> (String, String) {
     // Extract “main-ish” HTML to reduce nav/boilerplate.
-    let extracted_html = extract_main_content_html(&html).unwrap_or_else(|| html.clone());
+    let extracted_html = cfg
+        .extractors
+        .iter()
+        .find_map(|extractor| match extractor {
+            ContentExtractor::Selectors => extract_main_content_html(html),
+            ContentExtractor::Readability => readability::extract(html),
+        })
+        .unwrap_or_else(|| html.to_string());
+
+    let base = rewrite::document_base(html, url);
+    let md = content_to_markdown(&extracted_html, base.as_ref(), cfg);
+    (extracted_html, md)
+}
+
+/// Cleaned markdown for `content`, HTML already cut down to what's worth
+/// keeping. `base` resolves its relative links and images.
+fn content_to_markdown(content: &str, base: Option<&reqwest::Url>, cfg: &LlmCleanConfig) -> String {
+    // Strip script/style/noscript/svg blocks and srcset noise before html2md.
+    let stripped_html = strip_non_content_html(content);
+    let stripped_html = rewrite::rewrite_images(&stripped_html, base, cfg.images);
+    let stripped_html = rewrite::rewrite_links(&stripped_html, base, cfg.links);
 
-    // Strip script/style/noscript blocks before html2md.
-    let stripped_html = strip_script_style_noscript(&extracted_html);
+    // Tables and code go back in after cleaning, which would drop their long
+    // lines. Tables first: code inside a cell is flattened with the cell.
+    let (stripped_html, tables) = if cfg.gfm_tables {
+        Tables::extract(&stripped_html, cfg.max_table_cell_chars)
+    } else {
+        (stripped_html, Tables::default())
+    };
+    let (stripped_html, code) = if cfg.fence_code_blocks {
+        CodeBlocks::extract(&stripped_html)
+    } else {
+        (stripped_html, CodeBlocks::default())
+    };
 
     // Convert.
-    let mut md = parse_html(&stripped_html);
+    let md = parse_html(&stripped_html);
 
     // Clean for LLMs.
-    md = clean_markdown_for_llm(&md, cfg);
+    let md = clean_markdown_for_llm(&md, cfg);
+    tables.restore(&code.restore(&md))
+}
 
-    if md.chars().count() < cfg.min_md_chars {
-        return Ok(None);
+/// Status and content-type checks, shared by fresh and cached responses.
+fn check_response(
+    cfg: &LlmCleanConfig,
+    status: u16,
+    content_type: Option<&str>,
+) -> Option<DropReason> {
+    if cfg.drop_non_success_status && !(200..=299).contains(&status) {
+        return Some(DropReason::NonSuccessStatus);
+    }
+
+    let content_type = content_type
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+    let is_html = content_type.contains("text/html");
+    let is_pdf = cfg.ingest_pdfs && content_type.contains("application/pdf");
+    let is_convertible = cfg.convert_non_html && BodyKind::of(Some(&content_type)).is_some();
+    if cfg.require_html_content_type && !is_html && !is_pdf && !is_convertible {
+        return Some(DropReason::NotHtml);
+    }
+
+    None
+}
+
+/// Cap the cleaned markdown and prepend the front matter and outline.
+fn finish_page(page: CleanPage, cfg: &LlmCleanConfig, tokenizer: &dyn Tokenizer) -> MdPage {
+    let CleanPage {
+        query,
+        url,
+        final_url,
+        section: _,
+        client_redirect,
+        extraction,
+        status,
+        title,
+        snippet,
+        meta,
+        via,
+        mut md,
+    } = page;
+
+    // Cap before headers get added.
+    let chars = md.chars().count();
+    let budget = md_budget(&md, cfg, tokenizer);
+    if chars > budget {
+        debug!(url = %url, chars, max = budget, "truncating page");
+        md = truncate_at_boundary(&md, budget);
+        md.push_str("\n\n[...truncated...]\n");
     }
 
     let outline = extract_outline(&md, cfg.max_outline_headings);
 
-    let inferred_title = outline.get(0).cloned();
-    let title = title_from_search.or(inferred_title);
+    let inferred_title = outline.first().map(|h| h.text.clone());
+    let title = title.or(inferred_title);
 
     // Compact header to help downstream ingestion/ranking.
-    let mut final_md = String::new();
-    final_md.push_str("---\n");
-    final_md.push_str(&format!("query: {query}\n"));
-    final_md.push_str(&format!("url: {url}\n"));
-    final_md.push_str(&format!("status: {status}\n"));
-    if let Some(t) = &title {
-        final_md.push_str(&format!("title: {t}\n"));
-    }
-    final_md.push_str("---\n\n");
+    let entries: Vec<(HeaderField, serde_json::Value)> = cfg
+        .header_fields
+        .iter()
+        .filter_map(|&field| {
+            let value = match field {
+                HeaderField::Query => query.as_str().into(),
+                HeaderField::Url => url.as_str().into(),
+                HeaderField::FinalUrl if final_url != url => final_url.as_str().into(),
+                HeaderField::ClientRedirect if client_redirect => true.into(),
+                HeaderField::Extraction if extraction != Extraction::Content => {
+                    extraction.to_string().into()
+                }
+                HeaderField::Via => via.as_deref()?.into(),
+                HeaderField::Status if extraction != Extraction::Snippet => status.into(),
+                HeaderField::Title => title.as_deref()?.into(),
+                HeaderField::SiteName => meta.site_name.as_deref()?.into(),
+                HeaderField::Author => meta.author.as_deref()?.into(),
+                HeaderField::PublishedAt => meta.published_at.as_deref()?.into(),
+                HeaderField::CanonicalUrl => match meta.canonical_url.as_deref()? {
+                    canonical if canonical != final_url => canonical.into(),
+                    _ => return None,
+                },
+                HeaderField::Description => meta.description.as_deref()?.into(),
+                HeaderField::Snippet if extraction != Extraction::Snippet => {
+                    snippet.as_deref()?.into()
+                }
+                _ => return None,
+            };
+            Some((field, value))
+        })
+        .collect();
+    let mut final_md = header::render_header(cfg.output_format, &entries);
 
     if !outline.is_empty() {
         final_md.push_str("## Outline\n");
-        for h in &outline {
-            final_md.push_str("- ");
-            final_md.push_str(h);
-            final_md.push('\n');
-        }
+        final_md.push_str(&render_outline(&outline));
         final_md.push('\n');
     }
 
@@ -245,19 +2367,51 @@ async fn crawl_to_llm_markdown(
     final_md.push_str(&md);
 
     // Hard cap final size.
-    if final_md.chars().count() > cfg.max_md_chars {
-        final_md = truncate_at_char_boundary(&final_md, cfg.max_md_chars);
+    let budget = md_budget(&final_md, cfg, tokenizer);
+    if final_md.chars().count() > budget {
+        final_md = truncate_at_boundary(&final_md, budget);
         final_md.push_str("\n\n[...truncated...]\n");
     }
 
-    Ok(Some(MdPage {
-        query: query.to_string(),
-        url: url.to_string(),
+    let mut page = MdPage {
+        schema_version: SCHEMA_VERSION,
+        query,
+        url,
+        final_url,
+        client_redirect,
+        extraction,
         status,
         title,
+        snippet,
+        description: meta.description,
+        published_at: meta.published_at,
+        author: meta.author,
+        site_name: meta.site_name,
+        canonical_url: meta.canonical_url,
         outline,
         markdown: final_md,
-    }))
+        score: 0.0,
+        via,
+        also_matched: Vec::new(),
+    };
+    page.score = score_relevance(&page.query, &page);
+    page
+}
+
+/// Pages serialized before `schema_version` existed are laid out as version 1.
+fn unversioned() -> u32 {
+    1
+}
+
+/// How many chars of `md` the caps allow: `max_md_chars`, lowered to what
+/// fits in `max_md_tokens` when that is set and exceeded.
+fn md_budget(md: &str, cfg: &LlmCleanConfig, tokenizer: &dyn Tokenizer) -> usize {
+    match cfg.max_md_tokens {
+        Some(max) if tokenizer.count_tokens(md) > max => {
+            tokens::chars_within(tokenizer, md, max).min(cfg.max_md_chars)
+        }
+        _ => cfg.max_md_chars,
+    }
 }
 
 /// Heuristic “main content” extractor.
@@ -293,22 +2447,52 @@ fn extract_main_content_html(html: &str) -> Option<String> {
     None
 }
 
-fn strip_script_style_noscript(html: &str) -> String {
+fn strip_non_content_html(html: &str) -> String {
     let s = RE_SCRIPT.replace_all(html, "");
     let s = RE_STYLE.replace_all(&s, "");
     let s = RE_NOSCRIPT.replace_all(&s, "");
+    let s = RE_SVG.replace_all(&s, "");
+    let s = RE_IMG_TAG.replace_all(&s, |caps: &regex::Captures| {
+        RE_SRCSET_ATTR.replace_all(&caps[0], "").into_owned()
+    });
     s.into_owned()
 }
 
 fn clean_markdown_for_llm(md: &str, cfg: &LlmCleanConfig) -> String {
     let mut s = md.replace("\r\n", "\n").replace('\0', "");
-    s = RE_DATA_IMG.replace_all(&s, "(image omitted)").into_owned();
+    s = RE_DATA_URI_LINK
+        .replace_all(&s, |caps: &regex::Captures| {
+            if caps[1].eq_ignore_ascii_case("image") {
+                "(image omitted)"
+            } else {
+                "(data omitted)"
+            }
+        })
+        .into_owned();
+    s = RE_DATA_URI_BARE
+        .replace_all(&s, "[data omitted]")
+        .into_owned();
 
     // 1) Drop absurdly long lines early (minified junk, blobs).
     let mut lines: Vec<String> = Vec::new();
+    let mut long_lines = 0usize;
     for line in s.lines() {
         if line.chars().count() <= cfg.max_line_len {
             lines.push(line.to_string());
+        } else {
+            long_lines += 1;
+        }
+    }
+    if long_lines > 0 {
+        debug!(long_lines, "dropped over-long lines");
+    }
+
+    // 1b) Drop cookie banners, newsletter prompts and share bars.
+    if cfg.strip_boilerplate_phrases {
+        let before = lines.len();
+        lines.retain(|line| !boilerplate::is_boilerplate_line(line));
+        if lines.len() < before {
+            debug!(lines = before - lines.len(), "dropped boilerplate lines");
         }
     }
 
@@ -321,8 +2505,13 @@ fn clean_markdown_for_llm(md: &str, cfg: &LlmCleanConfig) -> String {
             for l in run.iter().take(cfg.max_link_lines_to_keep) {
                 out.push(l.clone());
             }
+            debug!(
+                run = run.len(),
+                kept = run.len().min(cfg.max_link_lines_to_keep),
+                "pruned link farm"
+            );
         } else {
-            out.extend(run.drain(..));
+            out.append(run);
         }
         run.clear();
     };
@@ -354,55 +2543,66 @@ fn clean_markdown_for_llm(md: &str, cfg: &LlmCleanConfig) -> String {
         }
     }
 
-    // 4) Cap before headers get added.
-    let mut normalized = normalized.trim().to_string();
-    if normalized.chars().count() > cfg.max_md_chars {
-        normalized = truncate_at_char_boundary(&normalized, cfg.max_md_chars);
-        normalized.push_str("\n\n[...truncated...]\n");
+    // The size cap happens in `finish_page`, after the cross-page passes.
+    normalized.trim().to_string()
+}
+
+/// Fraction of non-whitespace characters that sit inside markdown links.
+fn link_density(md: &str) -> f32 {
+    let visible = |s: &str| s.chars().filter(|c| !c.is_whitespace()).count();
+
+    let total = visible(md);
+    if total == 0 {
+        return 0.0;
     }
+    let in_links: usize = RE_MD_LINK.find_iter(md).map(|m| visible(m.as_str())).sum();
+    in_links as f32 / total as f32
+}
 
-    normalized
+/// Paragraphs with a meaningful amount of text once links are removed.
+/// Code blocks count as content.
+fn prose_paragraphs(md: &str) -> usize {
+    const MIN_PROSE_CHARS: usize = 60;
+
+    md.split("\n\n")
+        .map(str::trim)
+        .filter(|p| {
+            p.starts_with("```")
+                || RE_MD_LINK
+                    .replace_all(p, "")
+                    .chars()
+                    .filter(|c| c.is_alphanumeric())
+                    .count()
+                    >= MIN_PROSE_CHARS
+        })
+        .count()
 }
 
-fn extract_outline(md: &str, max_items: usize) -> Vec<String> {
+fn extract_outline(md: &str, max_items: usize) -> Vec<OutlineItem> {
     let mut out = Vec::new();
+    let mut in_fence = false;
 
     for line in md.lines() {
-        let t = line.trim_start();
-        if !t.starts_with('#') {
+        // `# comment` lines in shell snippets are not headings.
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
             continue;
         }
-
-        // Count leading hashes safely.
-        let mut hash_count = 0usize;
-        for ch in t.chars() {
-            if ch == '#' {
-                hash_count += 1;
-            } else {
-                break;
-            }
-        }
-
-        if hash_count == 0 {
+        if in_fence {
             continue;
         }
 
-        // Slice after the leading hashes (find byte index by iterating char_indices).
-        let mut cut = 0usize;
-        let mut seen = 0usize;
-        for (i, ch) in t.char_indices() {
-            if ch == '#' {
-                seen += 1;
-                if seen == hash_count {
-                    cut = i + ch.len_utf8();
-                    break;
-                }
-            }
-        }
+        let Some((level, text)) = parse_heading(line) else {
+            continue;
+        };
 
-        let rest = t[cut..].trim();
-        if !rest.is_empty() && rest.len() <= 120 {
-            out.push(rest.to_string());
+        let text = RE_MD_LINK.replace_all(text, "$1");
+        let text = text.trim();
+        if !text.is_empty() && text.len() <= 120 {
+            out.push(OutlineItem {
+                level,
+                text: text.to_string(),
+            });
             if out.len() >= max_items {
                 break;
             }
@@ -412,18 +2612,114 @@ fn extract_outline(md: &str, max_items: usize) -> Vec<String> {
     out
 }
 
-fn truncate_at_char_boundary(s: &str, max_chars: usize) -> String {
+/// Render the outline as a nested bullet list, indented relative to the shallowest heading.
+fn render_outline(outline: &[OutlineItem]) -> String {
+    let base = outline.iter().map(|h| h.level).min().unwrap_or(1);
+    let mut out = String::new();
+    for h in outline {
+        out.push_str(&"  ".repeat((h.level - base) as usize));
+        out.push_str("- ");
+        out.push_str(&h.text);
+        out.push('\n');
+    }
+    out
+}
+
+/// Strip the header and Outline that `finish_page` prepends.
+fn content_body(markdown: &str) -> &str {
+    const CONTENT_HEADER: &str = "## Content\n\n";
+
+    let rest = strip_header(markdown);
+
+    let head = rest.trim_start();
+    if (head.starts_with(CONTENT_HEADER) || head.starts_with("## Outline\n"))
+        && let Some(i) = rest.find(CONTENT_HEADER)
+    {
+        return &rest[i + CONTENT_HEADER.len()..];
+    }
+    rest
+}
+
+/// Parse an ATX heading line (`## Title`) into its level and text.
+fn parse_heading(line: &str) -> Option<(u8, &str)> {
+    let t = line.trim();
+    let level = t.chars().take_while(|&c| c == '#').count();
+    if level == 0 || level > 6 {
+        return None;
+    }
+
+    let rest = &t[level..];
+    if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    // Drop an optional closing sequence (`## Title ##`), but keep `C#`.
+    let mut text = rest.trim();
+    let unclosed = text.trim_end_matches('#');
+    if unclosed.is_empty() || unclosed.ends_with(char::is_whitespace) {
+        text = unclosed.trim_end();
+    }
+
+    if text.is_empty() {
+        None
+    } else {
+        Some((level as u8, text))
+    }
+}
+
+/// Truncate to `max_chars`, backing up to the nearest blank line or heading
+/// within the last 10% of the budget. Never leaves a ``` fence open.
+fn truncate_at_boundary(s: &str, max_chars: usize) -> String {
     if s.chars().count() <= max_chars {
         return s.to_string();
     }
-    let mut end_byte = 0usize;
-    let mut count = 0usize;
-    for (i, ch) in s.char_indices() {
-        if count >= max_chars {
-            break;
+
+    let hard = truncate_at_char_boundary(s, max_chars);
+    let slack = max_chars / 10;
+    let floor = hard
+        .char_indices()
+        .nth(max_chars - slack)
+        .map(|(i, _)| i)
+        .unwrap_or(hard.len());
+
+    // Prefer a paragraph break or the start of a heading; fall back to the hard cut.
+    let window = &hard[floor..];
+    let mut kept = match window.rfind("\n\n").max(window.rfind("\n#")) {
+        Some(i) => hard[..floor + i].trim_end().to_string(),
+        None => hard,
+    };
+
+    // An unbalanced fence swallows everything after it, so back up before it
+    // if that stays inside the window, otherwise close it.
+    if let Some(start) = open_fence_start(&kept) {
+        if start >= floor {
+            kept.truncate(start);
+            kept.truncate(kept.trim_end().len());
+        } else {
+            kept.push_str("\n```");
+        }
+    }
+
+    kept
+}
+
+/// Byte offset of the ``` line that opens a fence left unclosed at the end of `s`.
+fn open_fence_start(s: &str) -> Option<usize> {
+    let mut open = None;
+    let mut offset = 0usize;
+    for line in s.split_inclusive('\n') {
+        if line.trim_start().starts_with("```") {
+            open = if open.is_some() { None } else { Some(offset) };
         }
-        end_byte = i + ch.len_utf8();
-        count += 1;
+        offset += line.len();
+    }
+    open
+}
+
+fn truncate_at_char_boundary(s: &str, max_chars: usize) -> String {
+    if s.chars().count() <= max_chars {
+        return s.to_string();
     }
+    let end_byte = s.char_indices().nth(max_chars).map_or(s.len(), |(i, _)| i);
     s[..end_byte].to_string()
 }
@@ -0,0 +1,143 @@
+use super::{CleanPage, LlmCleanConfig};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use reqwest::Url;
+use std::collections::{HashMap, HashSet};
+
+static RE_MD_LINK: Lazy<Regex> = Lazy::new(|| Regex::new(r"!?\[([^\]]*)\]\([^)]*\)").unwrap());
+/// Cookie banners, newsletter prompts, share bars and footer lines, matched
+/// against the start of a line's text.
+static RE_PHRASE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?ix)^(?:
+            (?:accept|allow|reject|decline)\s+(?:all\s+)?cookies
+            | we\s+(?:use|value)\s+(?:cookies|your\s+privacy)
+            | this\s+(?:web)?site\s+uses\s+cookies
+            | by\s+(?:continuing|using)\s+(?:to\s+use\s+)?this\s+(?:web)?site
+            | cookie\s+(?:settings|preferences|policy|consent)
+            | manage\s+(?:cookies|consent|preferences)
+            | (?:subscribe|sign\s+up)\s+(?:to|for)\s+(?:our|the)\s+newsletter
+            | (?:get|join)\s+our\s+newsletter
+            | share\s+(?:this|on)\b
+            | follow\s+us\b
+            | (?:©|\(c\)|copyright\b).*all\s+rights\s+reserved
+            | all\s+rights\s+reserved
+            | skip\s+to\s+(?:main\s+)?content
+            | back\s+to\s+top$
+            | (?:related\s+(?:posts|articles)|you\s+(?:may|might)\s+also\s+like)$
+            | advertisement$
+            | (?:read\s+more|continue\s+reading)$
+        )",
+    )
+    .unwrap()
+});
+/// A line that is nothing but share buttons.
+static RE_SHARE_BAR: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)^(?:(?:share|tweet|facebook|twitter|x|linkedin|reddit|e-?mail|whatsapp|pinterest|telegram|copy\s+link|print)[\s,|·•/]*)+$",
+    )
+    .unwrap()
+});
+
+/// Lines longer than this are content, whatever they start with.
+const MAX_PHRASE_LINE_CHARS: usize = 160;
+
+/// Whether `line` is a stock banner, prompt or share bar rather than content.
+pub(super) fn is_boilerplate_line(line: &str) -> bool {
+    let text = RE_MD_LINK.replace_all(line, "$1");
+    let text = text
+        .trim()
+        .trim_start_matches(['#', '>', '-', '*', '+', '_', ' '])
+        .trim_end_matches(['*', '_', ' ', '.', '!', ':']);
+    !text.is_empty()
+        && text.chars().count() <= MAX_PHRASE_LINE_CHARS
+        && (RE_PHRASE.is_match(text) || RE_SHARE_BAR.is_match(text))
+}
+
+/// Remove paragraphs that repeat verbatim across pages from the same host
+/// (header blurbs, sidebar remnants, footers).
+///
+/// `pages` must be in search-rank order; each block is kept on the first page
+/// of its host and stripped from the rest. Blocks inside code fences and blocks
+/// repeated within a single page are left alone.
+pub(super) fn strip_boilerplate(pages: &mut [CleanPage], cfg: &LlmCleanConfig) {
+    let mut by_host: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, page) in pages.iter().enumerate() {
+        if let Some(host) = Url::parse(&page.final_url)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_string))
+        {
+            by_host.entry(host).or_default().push(i);
+        }
+    }
+
+    for group in by_host.values() {
+        if group.len() < cfg.boilerplate_min_pages.max(2) {
+            continue;
+        }
+
+        let mut pages_with: HashMap<String, usize> = HashMap::new();
+        for &i in group {
+            let mut in_page: HashMap<&str, usize> = HashMap::new();
+            for block in blocks(&pages[i].md) {
+                if !block.protected && block.text.chars().count() >= cfg.boilerplate_min_block_chars
+                {
+                    *in_page.entry(block.text).or_default() += 1;
+                }
+            }
+            for (text, n) in in_page {
+                if n == 1 {
+                    *pages_with.entry(text.to_string()).or_default() += 1;
+                }
+            }
+        }
+
+        let boiler: HashSet<String> = pages_with
+            .into_iter()
+            .filter(|(_, n)| *n >= cfg.boilerplate_min_pages)
+            .map(|(text, _)| text)
+            .collect();
+        if boiler.is_empty() {
+            continue;
+        }
+
+        for &i in group.iter().skip(1) {
+            let kept: Vec<&str> = blocks(&pages[i].md)
+                .into_iter()
+                .filter(|b| b.protected || !boiler.contains(b.text))
+                .map(|b| b.text)
+                .collect();
+            pages[i].md = kept.join("\n\n");
+        }
+    }
+}
+
+struct Block<'a> {
+    text: &'a str,
+    /// Inside or part of a code fence.
+    protected: bool,
+}
+
+fn blocks(md: &str) -> Vec<Block<'_>> {
+    let mut out = Vec::new();
+    let mut in_fence = false;
+
+    for text in md.split("\n\n").map(str::trim) {
+        if text.is_empty() {
+            continue;
+        }
+        let fences = text
+            .lines()
+            .filter(|l| l.trim_start().starts_with("```"))
+            .count();
+        out.push(Block {
+            text,
+            protected: in_fence || fences > 0,
+        });
+        if fences % 2 == 1 {
+            in_fence = !in_fence;
+        }
+    }
+
+    out
+}
@@ -0,0 +1,91 @@
+use super::LlmCleanConfig;
+use anyhow::{Context, Result, anyhow};
+use chromiumoxide::browser::{Browser, BrowserConfig};
+use futures::StreamExt;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::OnceCell;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+/// Markup this many times the size of its markdown is mostly scripts and
+/// empty app shells, worth rendering.
+const MIN_HTML_TO_MD_RATIO: usize = 20;
+
+/// Whether a page whose markdown came out short would likely have content
+/// after its scripts run: it has scripts, and they dwarf the text.
+pub(super) fn looks_js_rendered(html: &str, md: &str) -> bool {
+    let lower = html.to_ascii_lowercase();
+    lower.contains("<script") && md.len().max(1) * MIN_HTML_TO_MD_RATIO < html.len()
+}
+
+/// A headless Chrome, launched on the first page that needs it and shared
+/// by the rest of the search. A launch failure is logged once and turns
+/// rendering off.
+pub(super) struct Renderer {
+    executable: Option<PathBuf>,
+    browser: OnceCell<Option<(Browser, JoinHandle<()>)>>,
+}
+
+impl Renderer {
+    pub(super) fn new(cfg: &LlmCleanConfig) -> Self {
+        Self {
+            executable: cfg.browser_executable.clone(),
+            browser: OnceCell::new(),
+        }
+    }
+
+    /// The DOM of `url` once its scripts have run and the page has loaded.
+    pub(super) async fn render(&self, url: &str, cfg: &LlmCleanConfig) -> Result<String> {
+        let Some((browser, _)) = self
+            .browser
+            .get_or_init(|| async {
+                match self.launch().await {
+                    Ok(browser) => Some(browser),
+                    Err(e) => {
+                        warn!(error = %format!("{e:#}"), "headless browser unavailable");
+                        None
+                    }
+                }
+            })
+            .await
+        else {
+            return Err(anyhow!("headless browser unavailable"));
+        };
+
+        debug!(url, "rendering page");
+        let render = async {
+            let page = browser.new_page(url).await?;
+            page.wait_for_navigation().await?;
+            let html = page.content().await?;
+            let _ = page.close().await;
+            anyhow::Ok(html)
+        };
+        tokio::time::timeout(Duration::from_secs(cfg.total_timeout_secs), render)
+            .await
+            .with_context(|| format!("rendering {url} timed out"))?
+            .with_context(|| format!("rendering {url}"))
+    }
+
+    async fn launch(&self) -> Result<(Browser, JoinHandle<()>)> {
+        let mut config = BrowserConfig::builder();
+        if let Some(path) = &self.executable {
+            config = config.chrome_executable(path);
+        }
+        let config = config.build().map_err(|e| anyhow!(e))?;
+        let (browser, mut handler) = Browser::launch(config)
+            .await
+            .context("launching headless Chrome")?;
+        // The browser only makes progress while its event stream is polled.
+        let events = tokio::spawn(async move { while handler.next().await.is_some() {} });
+        Ok((browser, events))
+    }
+}
+
+impl Drop for Renderer {
+    fn drop(&mut self) {
+        if let Some(Some((_, events))) = self.browser.get() {
+            events.abort();
+        }
+    }
+}
@@ -0,0 +1,244 @@
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::fs;
+use tracing::warn;
+
+/// Stores between size checks; listing the directory on every write is wasteful.
+const PRUNE_EVERY: usize = 32;
+
+/// Raw responses on disk, one `<hash>.html` body plus a `<hash>.json` sidecar per URL.
+///
+/// Every failure is treated as a miss: a broken cache slows a run down but
+/// never fails it.
+pub(super) struct PageCache {
+    dir: PathBuf,
+    ttl: Duration,
+    max_bytes: u64,
+    tmp_seq: AtomicUsize,
+    stores: AtomicUsize,
+}
+
+/// A response as it came off the wire, before any extraction.
+pub(super) struct CachedResponse {
+    pub(super) status: u16,
+    pub(super) content_type: Option<String>,
+    pub(super) etag: Option<String>,
+    pub(super) last_modified: Option<String>,
+    pub(super) body: Vec<u8>,
+    /// Where the request ended up after redirects.
+    pub(super) final_url: String,
+}
+
+pub(super) enum Lookup {
+    /// Within TTL: use as-is.
+    Fresh(CachedResponse),
+    /// Expired but carries validators: revalidate with a conditional request.
+    Stale(CachedResponse),
+    Miss,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Meta {
+    url: String,
+    status: u16,
+    content_type: Option<String>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// Missing in entries written before redirects were tracked.
+    final_url: Option<String>,
+    /// Unix seconds.
+    fetched_at: u64,
+    /// Hex SHA-256 of the body file, so a torn or edited body reads as a miss.
+    #[serde(default)]
+    body_sha256: Option<String>,
+}
+
+impl PageCache {
+    pub(super) fn new(dir: PathBuf, ttl_secs: u64, max_bytes: u64) -> Self {
+        Self {
+            dir,
+            ttl: Duration::from_secs(ttl_secs),
+            max_bytes,
+            tmp_seq: AtomicUsize::new(0),
+            stores: AtomicUsize::new(0),
+        }
+    }
+
+    pub(super) async fn load(&self, url: &str) -> Lookup {
+        self.try_load(url).await.unwrap_or(Lookup::Miss)
+    }
+
+    async fn try_load(&self, url: &str) -> Option<Lookup> {
+        let (meta_path, body_path) = self.paths(url);
+
+        let meta: Meta = serde_json::from_slice(&fs::read(&meta_path).await.ok()?).ok()?;
+        if meta.url != normalize_url(url) {
+            return None;
+        }
+        let expired = unix_now().saturating_sub(meta.fetched_at) > self.ttl.as_secs();
+        if expired && meta.etag.is_none() && meta.last_modified.is_none() {
+            return None;
+        }
+
+        let body = fs::read(&body_path).await.ok()?;
+        if meta
+            .body_sha256
+            .is_some_and(|sum| sum != format!("{:x}", Sha256::digest(&body)))
+        {
+            return None;
+        }
+        let entry = CachedResponse {
+            status: meta.status,
+            content_type: meta.content_type,
+            etag: meta.etag,
+            last_modified: meta.last_modified,
+            body,
+            final_url: meta.final_url.unwrap_or_else(|| url.to_string()),
+        };
+        Some(if expired {
+            Lookup::Stale(entry)
+        } else {
+            Lookup::Fresh(entry)
+        })
+    }
+
+    pub(super) async fn store(&self, url: &str, resp: &CachedResponse) {
+        if let Err(e) = self.try_store(url, resp).await {
+            warn!(url, error = %e, "cache write failed");
+        }
+        if self
+            .stores
+            .fetch_add(1, Ordering::Relaxed)
+            .is_multiple_of(PRUNE_EVERY)
+            && let Err(e) = self.prune().await
+        {
+            warn!(dir = %self.dir.display(), error = %e, "cache prune failed");
+        }
+    }
+
+    /// Restart the TTL of an entry the server confirmed unchanged (304), keeping its body.
+    pub(super) async fn refresh(&self, url: &str, resp: &CachedResponse) {
+        if let Err(e) = self.write_meta(url, resp).await {
+            warn!(url, error = %e, "cache write failed");
+        }
+    }
+
+    async fn try_store(&self, url: &str, resp: &CachedResponse) -> std::io::Result<()> {
+        let (_, body_path) = self.paths(url);
+
+        fs::create_dir_all(&self.dir).await?;
+        // Body first: a sidecar on disk always points at a complete body.
+        self.write_atomic(&body_path, &resp.body).await?;
+        self.write_meta(url, resp).await
+    }
+
+    async fn write_meta(&self, url: &str, resp: &CachedResponse) -> std::io::Result<()> {
+        let (meta_path, _) = self.paths(url);
+        let meta = Meta {
+            url: normalize_url(url),
+            status: resp.status,
+            content_type: resp.content_type.clone(),
+            etag: resp.etag.clone(),
+            last_modified: resp.last_modified.clone(),
+            final_url: Some(resp.final_url.clone()),
+            fetched_at: unix_now(),
+            body_sha256: Some(format!("{:x}", Sha256::digest(&resp.body))),
+        };
+        self.write_atomic(&meta_path, &serde_json::to_vec(&meta)?)
+            .await
+    }
+
+    /// Delete the least recently written entries until the cache fits in `max_bytes`.
+    async fn prune(&self) -> std::io::Result<()> {
+        // Entry key -> (newest write, bytes across its body and sidecar).
+        let mut entries: HashMap<String, (SystemTime, u64)> = HashMap::new();
+        let mut total = 0u64;
+
+        let mut dir = fs::read_dir(&self.dir).await?;
+        while let Some(file) = dir.next_entry().await? {
+            let path = file.path();
+            let ext = path.extension().and_then(|e| e.to_str());
+            if !matches!(ext, Some("json" | "html")) {
+                continue;
+            }
+            let Some(key) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            let meta = file.metadata().await?;
+            let modified = meta.modified().unwrap_or(UNIX_EPOCH);
+
+            total += meta.len();
+            let entry = entries.entry(key.to_string()).or_insert((UNIX_EPOCH, 0));
+            entry.0 = entry.0.max(modified);
+            entry.1 += meta.len();
+        }
+        if total <= self.max_bytes {
+            return Ok(());
+        }
+
+        let mut oldest_first: Vec<_> = entries.into_iter().collect();
+        oldest_first.sort_by_key(|(_, (modified, _))| *modified);
+        for (key, (_, bytes)) in oldest_first {
+            if total <= self.max_bytes {
+                break;
+            }
+            let _ = fs::remove_file(self.dir.join(format!("{key}.json"))).await;
+            let _ = fs::remove_file(self.dir.join(format!("{key}.html"))).await;
+            total = total.saturating_sub(bytes);
+        }
+        Ok(())
+    }
+
+    /// Write to a unique temp file in the same dir, then rename over `path`.
+    async fn write_atomic(&self, path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+        let seq = self.tmp_seq.fetch_add(1, Ordering::Relaxed);
+        let tmp = path.with_extension(format!("{}.{seq}.tmp", std::process::id()));
+
+        if let Err(e) = fs::write(&tmp, bytes).await {
+            let _ = fs::remove_file(&tmp).await;
+            return Err(e);
+        }
+        fs::rename(&tmp, path).await
+    }
+
+    fn paths(&self, url: &str) -> (PathBuf, PathBuf) {
+        let key = format!("{:x}", Sha256::digest(normalize_url(url).as_bytes()));
+        (
+            self.dir.join(format!("{key}.json")),
+            self.dir.join(format!("{key}.html")),
+        )
+    }
+}
+
+/// `$XDG_CACHE_HOME/agent-bob`, falling back to `~/.cache/agent-bob`.
+pub fn default_cache_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CACHE_HOME")
+        .filter(|d| !d.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".cache")))?;
+    Some(base.join("agent-bob"))
+}
+
+/// Canonical form for cache keys: parsed (lowercased scheme/host, default port
+/// dropped) and without the fragment, which never reaches the server.
+pub(super) fn normalize_url(url: &str) -> String {
+    match Url::parse(url.trim()) {
+        Ok(mut u) => {
+            u.set_fragment(None);
+            u.to_string()
+        }
+        Err(_) => url.trim().to_string(),
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,47 @@
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use regex::bytes::Regex as BytesRegex;
+
+static RE_CHARSET_PARAM: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)\bcharset\s*=\s*["']?([\w.:-]+)"#).unwrap());
+// `<meta charset="...">` and `<meta http-equiv="Content-Type" content="...; charset=...">`.
+static RE_META_CHARSET: Lazy<BytesRegex> = Lazy::new(|| {
+    BytesRegex::new(r#"(?i-u)<meta\b[^>]*?\bcharset\s*=\s*["']?\s*([\w.:-]+)"#).unwrap()
+});
+
+/// How far into the document a `<meta>` charset declaration is looked for.
+const META_PRESCAN_BYTES: usize = 4096;
+
+/// Decode an HTML body: BOM first, then the Content-Type charset, then a
+/// `<meta>` declaration near the top. Undeclared bodies are UTF-8 when they
+/// validate and windows-1252 (the web's de-facto Latin-1) otherwise.
+pub(super) fn decode_html(body: &[u8], content_type: Option<&str>) -> String {
+    let declared = || {
+        content_type
+            .and_then(|ct| RE_CHARSET_PARAM.captures(ct))
+            .and_then(|c| Encoding::for_label(c[1].as_bytes()))
+            .or_else(|| meta_charset(body))
+    };
+
+    let encoding = match Encoding::for_bom(body) {
+        Some((encoding, _)) => encoding,
+        None => declared().unwrap_or_else(|| match std::str::from_utf8(body) {
+            Ok(_) => UTF_8,
+            // Only invalid at the very end: the body cap split a character.
+            Err(e) if e.error_len().is_none() => UTF_8,
+            Err(_) => WINDOWS_1252,
+        }),
+    };
+
+    // Removes the BOM too; malformed sequences become U+FFFD.
+    let (text, _, _) = encoding.decode(body);
+    text.into_owned()
+}
+
+fn meta_charset(body: &[u8]) -> Option<&'static Encoding> {
+    let head = &body[..body.len().min(META_PRESCAN_BYTES)];
+    let label = RE_META_CHARSET.captures(head)?.get(1)?.as_bytes();
+    // A document that could be read as ASCII to find this tag isn't UTF-16.
+    Encoding::for_label(label).map(|e| e.output_encoding())
+}
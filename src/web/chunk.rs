@@ -0,0 +1,356 @@
+use super::{MdPage, content_body, parse_heading};
+use serde::{Deserialize, Serialize};
+
+/// A heading-delimited slice of a page, sized for embedding/RAG.
+#[derive(Debug, Clone)]
+pub struct MdChunk {
+    /// The page's `final_url`, for citing the chunk.
+    pub url: String,
+    /// Headings enclosing this chunk, outermost first (e.g. ["Installation", "From source"]).
+    pub heading_path: Vec<String>,
+    pub text: String,
+    /// Byte range of `text` within the page's `markdown`, overlap included.
+    pub start: usize,
+    pub end: usize,
+    pub chars: usize,
+    pub approx_tokens: usize,
+}
+
+/// Size limits for [`chunk_markdown`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChunkConfig {
+    /// Longest chunk, overlap included.
+    pub max_chars: usize,
+    /// Text repeated from the end of the previous chunk, so a passage cut at a
+    /// boundary is still whole in one of them. Capped at half of `max_chars`.
+    pub overlap_chars: usize,
+}
+
+impl Default for ChunkConfig {
+    fn default() -> Self {
+        Self {
+            max_chars: 2_000,
+            overlap_chars: 200,
+        }
+    }
+}
+
+impl MdPage {
+    /// Split the page content into chunks of at most `max_chars`, without overlap.
+    ///
+    /// Front matter and the Outline block are excluded. Concatenating the chunk
+    /// texts reproduces the content exactly.
+    pub fn chunks(&self, max_chars: usize) -> Vec<MdChunk> {
+        chunk_markdown(
+            self,
+            &ChunkConfig {
+                max_chars,
+                overlap_chars: 0,
+            },
+        )
+    }
+}
+
+/// Split a page's content for embedding: at headings first, then at
+/// paragraphs, then hard-split as a last resort. Small adjacent pieces are
+/// merged up to the limit, and each chunk but the first starts with the tail
+/// of the one before it.
+pub fn chunk_markdown(page: &MdPage, cfg: &ChunkConfig) -> Vec<MdChunk> {
+    let md = page.markdown.as_str();
+    let body = content_body(md);
+    let max_chars = cfg.max_chars.max(1);
+    let overlap = cfg.overlap_chars.min(max_chars / 2);
+    // Leave room for the overlap so chunks stay within `max_chars`.
+    let limit = max_chars - overlap;
+
+    // (heading path, start, end, chars) of each chunk before overlap.
+    let mut spans: Vec<(Vec<String>, usize, usize, usize)> = Vec::new();
+    for (path, text) in split_sections(body) {
+        for piece in split_to_limit(text, limit) {
+            let start = piece.as_ptr() as usize - md.as_ptr() as usize;
+            let end = start + piece.len();
+            let n = piece.chars().count();
+            match spans.last_mut() {
+                Some(last) if last.3 + n <= limit => {
+                    last.2 = end;
+                    last.3 += n;
+                }
+                _ => spans.push((path.clone(), start, end, n)),
+            }
+        }
+    }
+
+    let mut prev_start = spans.first().map_or(0, |s| s.1);
+    spans
+        .into_iter()
+        .map(|(heading_path, start, end, _)| {
+            let from = overlap_start(md, prev_start, start, overlap);
+            prev_start = start;
+            let text = &md[from..end];
+            let chars = text.chars().count();
+            MdChunk {
+                url: page.final_url.clone(),
+                heading_path,
+                text: text.to_string(),
+                start: from,
+                end,
+                chars,
+                approx_tokens: approx_tokens(chars),
+            }
+        })
+        .collect()
+}
+
+/// Back up from `start` by up to `overlap` chars, not past `floor`, then
+/// forward to the next word so the overlap doesn't open mid-word.
+fn overlap_start(md: &str, floor: usize, start: usize, overlap: usize) -> usize {
+    if overlap == 0 {
+        return start;
+    }
+    let before = &md[floor..start];
+    let Some((from, _)) = before
+        .char_indices()
+        .rev()
+        .nth(overlap - 1)
+        .filter(|(i, _)| *i > 0)
+    else {
+        // The previous chunk is no longer than the overlap: repeat all of it.
+        return floor;
+    };
+    // Skip the partial word and the whitespace after it.
+    match before[from..].find(char::is_whitespace) {
+        Some(i) => start - before[from + i..].trim_start().len(),
+        None => start,
+    }
+}
+
+/// Sections as (heading path, text including its heading line).
+fn split_sections(md: &str) -> Vec<(Vec<String>, &str)> {
+    let mut sections = Vec::new();
+    let mut stack: Vec<(u8, String)> = Vec::new();
+    let mut path: Vec<String> = Vec::new();
+    let mut start = 0usize;
+    let mut offset = 0usize;
+    let mut in_fence = false;
+
+    for line in md.split_inclusive('\n') {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        } else if !in_fence && let Some((level, text)) = parse_heading(line) {
+            if offset > start {
+                sections.push((path.clone(), &md[start..offset]));
+            }
+            stack.retain(|(l, _)| *l < level);
+            stack.push((level, text.to_string()));
+            path = stack.iter().map(|(_, t)| t.clone()).collect();
+            start = offset;
+        }
+        offset += line.len();
+    }
+
+    if offset > start {
+        sections.push((path, &md[start..offset]));
+    }
+    sections
+}
+
+/// Greedily pack paragraphs into pieces of at most `max_chars`.
+fn split_to_limit(text: &str, max_chars: usize) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut start = 0usize;
+    let mut end = 0usize;
+    let mut chars = 0usize;
+
+    for (a, b) in paragraph_ranges(text) {
+        let n = text[a..b].chars().count();
+        if chars + n > max_chars && end > start {
+            pieces.push(&text[start..end]);
+            start = end;
+            chars = 0;
+        }
+
+        if n > max_chars {
+            // Single oversized paragraph: hard-split on char boundaries.
+            let mut cut = a;
+            for (i, _) in text[a..b].char_indices().step_by(max_chars).skip(1) {
+                pieces.push(&text[cut..a + i]);
+                cut = a + i;
+            }
+            start = cut;
+            chars = text[cut..b].chars().count();
+        } else {
+            chars += n;
+        }
+        end = b;
+    }
+
+    if end > start {
+        pieces.push(&text[start..end]);
+    }
+    pieces
+}
+
+/// Byte ranges of paragraphs (each including its trailing blank lines).
+/// Blank lines inside code fences don't count as breaks.
+fn paragraph_ranges(text: &str) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut start = 0usize;
+    let mut offset = 0usize;
+    let mut in_fence = false;
+    let mut prev_blank = false;
+
+    for line in text.split_inclusive('\n') {
+        let blank = line.trim().is_empty();
+        if !blank && prev_blank && !in_fence && offset > start {
+            ranges.push((start, offset));
+            start = offset;
+        }
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        prev_blank = blank;
+        offset += line.len();
+    }
+
+    if offset > start {
+        ranges.push((start, offset));
+    }
+    ranges
+}
+
+fn approx_tokens(chars: usize) -> usize {
+    chars.div_ceil(4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::tests::{Rng, md_page};
+
+    const WORDS: &[&str] = &[
+        "the",
+        "borrow",
+        "checker",
+        "rejects",
+        "dangling",
+        "references",
+        "ownership",
+        "moves",
+        "values",
+        "日本語",
+        "🦀",
+        "a_very_long_identifier_without_any_spaces_in_it",
+    ];
+
+    /// Markdown with nested headings, paragraphs, fenced code with blank
+    /// lines inside, and the odd giant paragraph.
+    fn random_markdown(rng: &mut Rng) -> String {
+        let mut md = String::new();
+        for _ in 0..1 + rng.below(30) {
+            match rng.below(6) {
+                0 => {
+                    let level = 1 + rng.below(4);
+                    md.push_str(&format!("{} {}\n\n", "#".repeat(level), rng.pick(WORDS)));
+                }
+                1 => {
+                    md.push_str("```rust\n");
+                    for _ in 0..rng.below(8) {
+                        md.push_str(if rng.below(3) == 0 {
+                            "\n"
+                        } else {
+                            "let x = 1;\n"
+                        });
+                    }
+                    md.push_str("```\n\n");
+                }
+                2 => {
+                    let words: Vec<&str> =
+                        (0..200 + rng.below(400)).map(|_| rng.pick(WORDS)).collect();
+                    md.push_str(&words.join(" "));
+                    md.push_str("\n\n");
+                }
+                _ => {
+                    let words: Vec<&str> =
+                        (0..1 + rng.below(40)).map(|_| rng.pick(WORDS)).collect();
+                    md.push_str(&words.join(" "));
+                    md.push_str(if rng.below(4) == 0 { "\n" } else { "\n\n" });
+                }
+            }
+        }
+        md
+    }
+
+    #[test]
+    fn chunks_cover_the_content_exactly_once() {
+        let mut rng = Rng::new(0x5eed);
+        for _ in 0..300 {
+            let page = md_page(&random_markdown(&mut rng));
+            let max_chars = 10 + rng.below(600);
+            let chunks = page.chunks(max_chars);
+
+            let body = content_body(&page.markdown);
+            let joined: String = chunks.iter().map(|c| c.text.as_str()).collect();
+            assert_eq!(joined, body, "max_chars = {max_chars}");
+
+            let body_start = page.markdown.len() - body.len();
+            let mut at = body_start;
+            for chunk in &chunks {
+                assert_eq!(chunk.start, at, "gap or overlap before a chunk");
+                assert_eq!(&page.markdown[chunk.start..chunk.end], chunk.text);
+                assert!(chunk.chars <= max_chars, "{} > {max_chars}", chunk.chars);
+                assert_eq!(chunk.chars, chunk.text.chars().count());
+                at = chunk.end;
+            }
+            assert_eq!(at, page.markdown.len());
+        }
+    }
+
+    #[test]
+    fn overlapping_chunks_stay_within_the_limit_and_lose_nothing() {
+        let mut rng = Rng::new(0xc0ffee);
+        for _ in 0..300 {
+            let page = md_page(&random_markdown(&mut rng));
+            let cfg = ChunkConfig {
+                max_chars: 10 + rng.below(600),
+                overlap_chars: rng.below(200),
+            };
+            let chunks = chunk_markdown(&page, &cfg);
+
+            let body = content_body(&page.markdown);
+            let mut covered = page.markdown.len() - body.len();
+            for chunk in &chunks {
+                assert!(
+                    chunk.chars <= cfg.max_chars,
+                    "{} > {}",
+                    chunk.chars,
+                    cfg.max_chars
+                );
+                assert!(chunk.start <= covered, "text skipped before a chunk");
+                assert!(chunk.end > covered, "chunk adds nothing new");
+                assert_eq!(&page.markdown[chunk.start..chunk.end], chunk.text);
+                covered = chunk.end;
+            }
+            assert_eq!(covered, page.markdown.len());
+        }
+    }
+
+    #[test]
+    fn chunks_carry_their_heading_path() {
+        let page =
+            md_page("# Install\n\nIntro.\n\n## From source\n\nBuild it.\n\n# Usage\n\nRun it.\n");
+        let paths: Vec<Vec<String>> = page
+            .chunks(40)
+            .into_iter()
+            .map(|c| c.heading_path)
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                vec!["Install".to_string()],
+                vec!["Install".to_string(), "From source".to_string()],
+                vec!["Usage".to_string()],
+            ]
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use scraper::{ElementRef, Html, Node, Selector};
+
+static RE_PRE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<pre\b[^>]*>.*?</pre>").unwrap());
+static RE_PLACEHOLDER: Lazy<Regex> = Lazy::new(|| Regex::new(r"AGENTBOBCODE(\d+)").unwrap());
+static RE_LANG_CLASS: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(?:language-|lang-|highlight-source-|highlight-|sourcecode-)([\w+#.-]+)$")
+        .unwrap()
+});
+static SEL_PRE: Lazy<Selector> = Lazy::new(|| Selector::parse("pre").unwrap());
+
+/// Class names that are a language on their own (rustdoc's `<pre class="rust">`).
+const BARE_LANGS: &[&str] = &[
+    "bash",
+    "c",
+    "cpp",
+    "csharp",
+    "css",
+    "go",
+    "html",
+    "java",
+    "javascript",
+    "js",
+    "json",
+    "kotlin",
+    "python",
+    "ruby",
+    "rust",
+    "shell",
+    "sql",
+    "swift",
+    "toml",
+    "ts",
+    "typescript",
+    "yaml",
+];
+
+/// `<pre>` blocks, captured verbatim before html2md can flatten them. Each is
+/// replaced in the HTML by a placeholder paragraph and put back, fenced, with
+/// [`CodeBlocks::restore`] once cleaning can no longer prune its lines.
+#[derive(Debug, Default)]
+pub(super) struct CodeBlocks {
+    fenced: Vec<String>,
+}
+
+impl CodeBlocks {
+    pub(super) fn extract(html: &str) -> (String, Self) {
+        let mut blocks = Self::default();
+        let out = RE_PRE.replace_all(html, |caps: &regex::Captures| match fence(&caps[0]) {
+            Some(block) => {
+                blocks.fenced.push(block);
+                format!("<p>AGENTBOBCODE{}</p>", blocks.fenced.len() - 1)
+            }
+            None => caps[0].to_string(),
+        });
+        (out.into_owned(), blocks)
+    }
+
+    pub(super) fn restore(&self, md: &str) -> String {
+        if self.fenced.is_empty() {
+            return md.to_string();
+        }
+        RE_PLACEHOLDER
+            .replace_all(md, |caps: &regex::Captures| {
+                caps[1]
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|n| self.fenced.get(n))
+                    .map_or_else(|| caps[0].to_string(), |b| format!("\n{b}\n"))
+            })
+            .into_owned()
+    }
+}
+
+/// One `<pre>` as a fenced block, or `None` when it holds no text.
+fn fence(pre_html: &str) -> Option<String> {
+    let doc = Html::parse_fragment(pre_html);
+    let pre = doc.select(&SEL_PRE).next()?;
+
+    let mut code = String::new();
+    collect_code(pre, &mut code);
+    let code = code.trim_matches('\n').trim_end();
+    if code.trim().is_empty() {
+        return None;
+    }
+
+    // Longer than any backtick run inside, so the code can't close the fence.
+    let longest_run = code.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let ticks = "`".repeat(longest_run.max(2) + 1);
+    let lang = language(pre).unwrap_or_default();
+    Some(format!("{ticks}{lang}\n{code}\n{ticks}"))
+}
+
+/// Text under `el`, minus line-number gutters, with `<br>` as a newline.
+fn collect_code(el: ElementRef, out: &mut String) {
+    for child in el.children() {
+        match child.value() {
+            Node::Text(text) => out.push_str(text),
+            Node::Element(e) if e.name() == "br" => out.push('\n'),
+            Node::Element(e)
+                if e.classes().any(|c| {
+                    c.contains("lineno") || c.contains("line-number") || c == "gutter"
+                }) => {}
+            Node::Element(_) => {
+                if let Some(child) = ElementRef::wrap(child) {
+                    collect_code(child, out);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The language hint on the `<pre>` or the `<code>` inside it.
+fn language(pre: ElementRef) -> Option<String> {
+    let code = pre
+        .children()
+        .filter_map(ElementRef::wrap)
+        .find(|e| e.value().name() == "code");
+
+    [Some(pre), code].into_iter().flatten().find_map(|el| {
+        let e = el.value();
+        if let Some(lang) = e.attr("data-lang").or_else(|| e.attr("data-language")) {
+            return Some(lang.trim().to_ascii_lowercase()).filter(|l| !l.is_empty());
+        }
+        e.classes().find_map(|class| {
+            let class = class.to_ascii_lowercase();
+            if let Some(caps) = RE_LANG_CLASS.captures(&class) {
+                return Some(caps[1].to_string());
+            }
+            BARE_LANGS.contains(&class.as_str()).then_some(class)
+        })
+    })
+}
@@ -0,0 +1,457 @@
+use super::{
+    ContentExtractor, FetchConfig, FetchIdentity, HeaderField, ImagePolicy, LinkPolicy,
+    LlmCleanConfig, OutputFormat, QueryExpansion, RetryPolicy, SearchConfig, TokenModel, UserAgent,
+    language,
+};
+use std::fmt;
+use std::path::PathBuf;
+
+/// Smallest accepted `max_html_bytes`; below this almost every page is cut mid-head.
+pub const MIN_HTML_BYTES: usize = 4_096;
+
+/// A config that would misbehave at crawl time rather than fail loudly.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    /// No crawl task would ever get a permit.
+    ZeroConcurrency,
+    /// Every request would time out immediately.
+    ZeroTimeout,
+    HtmlBytesTooSmall {
+        max_html_bytes: usize,
+    },
+    /// Every page would be dropped as too short or truncated below the minimum.
+    MinAboveMax {
+        min_md_chars: usize,
+        max_md_chars: usize,
+    },
+    /// A ratio field outside `0.0..=1.0`.
+    RatioOutOfRange {
+        field: &'static str,
+        value: f32,
+    },
+    ZeroMaxPages,
+    /// A rate limit that is zero, negative or not a number.
+    InvalidRate {
+        value: f32,
+    },
+    /// `render_js` in a build without the `browser` feature.
+    BrowserUnavailable,
+    /// An `allowed_languages` entry that isn't an ISO 639-3 code detection knows.
+    UnknownLanguage(String),
+    /// A `UserAgent::Bot` that gives site owners no way to reach you.
+    BotWithoutContact,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::ZeroConcurrency => write!(f, "concurrency must be at least 1"),
+            ConfigError::ZeroTimeout => write!(f, "timeouts must be at least 1 second"),
+            ConfigError::HtmlBytesTooSmall { max_html_bytes } => write!(
+                f,
+                "max_html_bytes is {max_html_bytes}, must be at least {MIN_HTML_BYTES}"
+            ),
+            ConfigError::MinAboveMax {
+                min_md_chars,
+                max_md_chars,
+            } => write!(
+                f,
+                "min_md_chars ({min_md_chars}) is above max_md_chars ({max_md_chars})"
+            ),
+            ConfigError::RatioOutOfRange { field, value } => {
+                write!(f, "{field} is {value}, must be within 0.0..=1.0")
+            }
+            ConfigError::ZeroMaxPages => write!(f, "max_pages must be at least 1"),
+            ConfigError::InvalidRate { value } => {
+                write!(f, "per_host_requests_per_sec is {value}, must be above 0")
+            }
+            ConfigError::BrowserUnavailable => write!(
+                f,
+                "render_js needs agent-bob built with the `browser` feature"
+            ),
+            ConfigError::UnknownLanguage(code) => write!(
+                f,
+                "unknown language {code:?} in allowed_languages, expected an ISO 639-3 code like \"eng\""
+            ),
+            ConfigError::BotWithoutContact => {
+                write!(f, "a bot user agent needs a contact URL or email")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Written in place of secrets by `Debug` impls, so configs can be logged.
+pub(super) const REDACTED: &str = "<redacted>";
+
+// By hand to keep `github_token` out of logs.
+impl fmt::Debug for LlmCleanConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LlmCleanConfig")
+            .field("concurrency", &self.concurrency)
+            .field("search_concurrency", &self.search_concurrency)
+            .field("per_host_concurrency", &self.per_host_concurrency)
+            .field("per_host_requests_per_sec", &self.per_host_requests_per_sec)
+            .field("total_timeout_secs", &self.total_timeout_secs)
+            .field("connect_timeout_secs", &self.connect_timeout_secs)
+            .field("read_idle_timeout_secs", &self.read_idle_timeout_secs)
+            .field("retry", &self.retry)
+            .field("max_redirects", &self.max_redirects)
+            .field("identity", &self.identity)
+            .field("fetch", &self.fetch)
+            .field("require_html_content_type", &self.require_html_content_type)
+            .field("drop_non_success_status", &self.drop_non_success_status)
+            .field("max_html_bytes", &self.max_html_bytes)
+            .field("ingest_pdfs", &self.ingest_pdfs)
+            .field("convert_non_html", &self.convert_non_html)
+            .field("max_pdf_bytes", &self.max_pdf_bytes)
+            .field("max_md_chars", &self.max_md_chars)
+            .field("max_md_tokens", &self.max_md_tokens)
+            .field("token_model", &self.token_model)
+            .field("min_md_chars", &self.min_md_chars)
+            .field("max_link_density", &self.max_link_density)
+            .field("min_paragraphs", &self.min_paragraphs)
+            .field("max_link_lines_to_keep", &self.max_link_lines_to_keep)
+            .field("link_farm_run_threshold", &self.link_farm_run_threshold)
+            .field("max_line_len", &self.max_line_len)
+            .field("gfm_tables", &self.gfm_tables)
+            .field("max_table_cell_chars", &self.max_table_cell_chars)
+            .field("fence_code_blocks", &self.fence_code_blocks)
+            .field("site_extractors", &self.site_extractors)
+            .field(
+                "github_token",
+                &self.github_token.as_ref().map(|_| REDACTED),
+            )
+            .field("extract_fragments", &self.extract_fragments)
+            .field("images", &self.images)
+            .field("links", &self.links)
+            .field("allowed_languages", &self.allowed_languages)
+            .field("max_outline_headings", &self.max_outline_headings)
+            .field("output_format", &self.output_format)
+            .field("header_fields", &self.header_fields)
+            .field("extractors", &self.extractors)
+            .field("allowed_domains", &self.allowed_domains)
+            .field("blocked_domains", &self.blocked_domains)
+            .field("render_js", &self.render_js)
+            .field("browser_executable", &self.browser_executable)
+            .field("dedupe_content", &self.dedupe_content)
+            .field("near_duplicate_threshold", &self.near_duplicate_threshold)
+            .field("strip_boilerplate_phrases", &self.strip_boilerplate_phrases)
+            .field("dedupe_boilerplate", &self.dedupe_boilerplate)
+            .field("boilerplate_min_pages", &self.boilerplate_min_pages)
+            .field(
+                "boilerplate_min_block_chars",
+                &self.boilerplate_min_block_chars,
+            )
+            .field("snippets_only", &self.snippets_only)
+            .field("query_expansion", &self.query_expansion)
+            .field("sort_by_relevance", &self.sort_by_relevance)
+            .field("cache_dir", &self.cache_dir)
+            .field("cache_ttl_secs", &self.cache_ttl_secs)
+            .field("cache_max_bytes", &self.cache_max_bytes)
+            .field("follow_links_depth", &self.follow_links_depth)
+            .field(
+                "max_followed_links_per_page",
+                &self.max_followed_links_per_page,
+            )
+            .field("merge_followed_links", &self.merge_followed_links)
+            .field("max_pages", &self.max_pages)
+            .field("search_deadline_secs", &self.search_deadline_secs)
+            .field("max_total_bytes", &self.max_total_bytes)
+            .field("search", &self.search)
+            .field("respect_robots_txt", &self.respect_robots_txt)
+            .finish()
+    }
+}
+
+impl LlmCleanConfig {
+    /// Start from `Default` and override fields fluently; `build` validates.
+    pub fn builder() -> LlmCleanConfigBuilder {
+        LlmCleanConfigBuilder {
+            cfg: LlmCleanConfig::default(),
+        }
+    }
+
+    /// Check the cross-field invariants `build` enforces.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.concurrency == 0 || self.search_concurrency == 0 || self.per_host_concurrency == 0 {
+            return Err(ConfigError::ZeroConcurrency);
+        }
+        if self.total_timeout_secs == 0
+            || self.connect_timeout_secs == 0
+            || self.search_deadline_secs == Some(0)
+            || self.read_idle_timeout_secs == Some(0)
+        {
+            return Err(ConfigError::ZeroTimeout);
+        }
+        if self.max_html_bytes < MIN_HTML_BYTES {
+            return Err(ConfigError::HtmlBytesTooSmall {
+                max_html_bytes: self.max_html_bytes,
+            });
+        }
+        if self.min_md_chars > self.max_md_chars {
+            return Err(ConfigError::MinAboveMax {
+                min_md_chars: self.min_md_chars,
+                max_md_chars: self.max_md_chars,
+            });
+        }
+        for (field, value) in [
+            ("max_link_density", self.max_link_density),
+            ("near_duplicate_threshold", self.near_duplicate_threshold),
+        ] {
+            if !(0.0..=1.0).contains(&value) {
+                return Err(ConfigError::RatioOutOfRange { field, value });
+            }
+        }
+        if self.max_pages == 0 {
+            return Err(ConfigError::ZeroMaxPages);
+        }
+        if let Some(value) = self.per_host_requests_per_sec
+            && !(value > 0.0 && value.is_finite())
+        {
+            return Err(ConfigError::InvalidRate { value });
+        }
+        if self.render_js && !cfg!(feature = "browser") {
+            return Err(ConfigError::BrowserUnavailable);
+        }
+        if let Some(code) = self
+            .allowed_languages
+            .iter()
+            .find(|code| !language::is_known(code))
+        {
+            return Err(ConfigError::UnknownLanguage(code.clone()));
+        }
+        if let UserAgent::Bot { contact } = &self.identity.user_agent
+            && contact.trim().is_empty()
+        {
+            return Err(ConfigError::BotWithoutContact);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LlmCleanConfigBuilder {
+    cfg: LlmCleanConfig,
+}
+
+// One fluent setter per config field.
+macro_rules! setters {
+    ($($field:ident: $ty:ty),* $(,)?) => {
+        $(
+            pub fn $field(mut self, value: $ty) -> Self {
+                self.cfg.$field = value;
+                self
+            }
+        )*
+    };
+}
+
+impl LlmCleanConfigBuilder {
+    setters! {
+        concurrency: usize,
+        search_concurrency: usize,
+        per_host_concurrency: usize,
+        per_host_requests_per_sec: Option<f32>,
+        total_timeout_secs: u64,
+        connect_timeout_secs: u64,
+        read_idle_timeout_secs: Option<u64>,
+        retry: RetryPolicy,
+        max_redirects: usize,
+        identity: FetchIdentity,
+        fetch: FetchConfig,
+        require_html_content_type: bool,
+        drop_non_success_status: bool,
+        max_html_bytes: usize,
+        ingest_pdfs: bool,
+        convert_non_html: bool,
+        max_pdf_bytes: usize,
+        max_md_chars: usize,
+        max_md_tokens: Option<usize>,
+        token_model: TokenModel,
+        min_md_chars: usize,
+        max_link_density: f32,
+        min_paragraphs: usize,
+        max_link_lines_to_keep: usize,
+        link_farm_run_threshold: usize,
+        max_line_len: usize,
+        gfm_tables: bool,
+        max_table_cell_chars: usize,
+        fence_code_blocks: bool,
+        site_extractors: bool,
+        github_token: Option<String>,
+        extract_fragments: bool,
+        images: ImagePolicy,
+        links: LinkPolicy,
+        allowed_languages: Vec<String>,
+        max_outline_headings: usize,
+        output_format: OutputFormat,
+        header_fields: Vec<HeaderField>,
+        extractors: Vec<ContentExtractor>,
+        allowed_domains: Vec<String>,
+        blocked_domains: Vec<String>,
+        render_js: bool,
+        browser_executable: Option<PathBuf>,
+        dedupe_content: bool,
+        near_duplicate_threshold: f32,
+        strip_boilerplate_phrases: bool,
+        dedupe_boilerplate: bool,
+        boilerplate_min_pages: usize,
+        boilerplate_min_block_chars: usize,
+        snippets_only: bool,
+        query_expansion: QueryExpansion,
+        sort_by_relevance: bool,
+        cache_dir: Option<PathBuf>,
+        cache_ttl_secs: u64,
+        cache_max_bytes: u64,
+        follow_links_depth: u8,
+        max_followed_links_per_page: usize,
+        merge_followed_links: bool,
+        max_pages: usize,
+        search_deadline_secs: Option<u64>,
+        max_total_bytes: Option<usize>,
+        search: SearchConfig,
+        respect_robots_txt: bool,
+    }
+
+    pub fn build(self) -> Result<LlmCleanConfig, ConfigError> {
+        self.cfg.validate()?;
+        Ok(self.cfg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn err(builder: LlmCleanConfigBuilder) -> ConfigError {
+        builder.build().expect_err("config should be rejected")
+    }
+
+    #[test]
+    fn defaults_are_valid() {
+        assert_eq!(LlmCleanConfig::default().validate(), Ok(()));
+    }
+
+    #[test]
+    fn rejects_zero_concurrency() {
+        let b = LlmCleanConfig::builder;
+        assert_eq!(err(b().concurrency(0)), ConfigError::ZeroConcurrency);
+        assert_eq!(err(b().search_concurrency(0)), ConfigError::ZeroConcurrency);
+        assert_eq!(
+            err(b().per_host_concurrency(0)),
+            ConfigError::ZeroConcurrency
+        );
+    }
+
+    #[test]
+    fn rejects_zero_timeouts() {
+        let b = LlmCleanConfig::builder;
+        assert_eq!(err(b().total_timeout_secs(0)), ConfigError::ZeroTimeout);
+        assert_eq!(err(b().connect_timeout_secs(0)), ConfigError::ZeroTimeout);
+        assert_eq!(
+            err(b().search_deadline_secs(Some(0))),
+            ConfigError::ZeroTimeout
+        );
+        assert_eq!(
+            err(b().read_idle_timeout_secs(Some(0))),
+            ConfigError::ZeroTimeout
+        );
+    }
+
+    #[test]
+    fn rejects_tiny_html_cap() {
+        assert_eq!(
+            err(LlmCleanConfig::builder().max_html_bytes(MIN_HTML_BYTES - 1)),
+            ConfigError::HtmlBytesTooSmall {
+                max_html_bytes: MIN_HTML_BYTES - 1
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_min_above_max() {
+        assert_eq!(
+            err(LlmCleanConfig::builder()
+                .min_md_chars(500)
+                .max_md_chars(400)),
+            ConfigError::MinAboveMax {
+                min_md_chars: 500,
+                max_md_chars: 400
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_ratios_out_of_range() {
+        assert_eq!(
+            err(LlmCleanConfig::builder().max_link_density(1.5)),
+            ConfigError::RatioOutOfRange {
+                field: "max_link_density",
+                value: 1.5
+            }
+        );
+        assert_eq!(
+            err(LlmCleanConfig::builder().near_duplicate_threshold(-0.1)),
+            ConfigError::RatioOutOfRange {
+                field: "near_duplicate_threshold",
+                value: -0.1
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_zero_max_pages() {
+        assert_eq!(
+            err(LlmCleanConfig::builder().max_pages(0)),
+            ConfigError::ZeroMaxPages
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_rates() {
+        for value in [0.0, -1.0, f32::INFINITY] {
+            assert_eq!(
+                err(LlmCleanConfig::builder().per_host_requests_per_sec(Some(value))),
+                ConfigError::InvalidRate { value }
+            );
+        }
+        // NaN never compares equal, so match on the variant instead.
+        assert!(matches!(
+            err(LlmCleanConfig::builder().per_host_requests_per_sec(Some(f32::NAN))),
+            ConfigError::InvalidRate { value } if value.is_nan()
+        ));
+    }
+
+    #[cfg(not(feature = "browser"))]
+    #[test]
+    fn rejects_render_js_without_browser() {
+        assert_eq!(
+            err(LlmCleanConfig::builder().render_js(true)),
+            ConfigError::BrowserUnavailable
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_languages() {
+        assert_eq!(
+            err(LlmCleanConfig::builder().allowed_languages(vec!["eng".into(), "english".into()])),
+            ConfigError::UnknownLanguage("english".into())
+        );
+    }
+
+    #[test]
+    fn rejects_bot_without_contact() {
+        let identity = FetchIdentity {
+            user_agent: UserAgent::Bot {
+                contact: "  ".into(),
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            err(LlmCleanConfig::builder().identity(identity)),
+            ConfigError::BotWithoutContact
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use super::Extraction;
+use html2md::parse_html;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+static RE_FEED_ITEM: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)<(item|entry)\b[^>]*>(.*?)</(?:item|entry)>").unwrap());
+static RE_CDATA: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?s)^\s*<!\[CDATA\[(.*)\]\]>\s*$").unwrap());
+static RE_ATOM_LINK: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?is)<link\b[^>]*?\bhref\s*=\s*["']([^"']+)["'][^>]*>"#).unwrap());
+static RE_ATOM_ALTERNATE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)\brel\s*=\s*["']alternate["']"#).unwrap());
+static RE_REL: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)\brel\s*=").unwrap());
+
+/// Feed item summaries are cut at this many chars; the item links to the rest.
+const MAX_SUMMARY_CHARS: usize = 300;
+
+/// A non-HTML body that can still be turned into markdown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum BodyKind {
+    Json,
+    /// RSS or Atom; other XML is only a feed if it has a feed root.
+    Feed,
+    /// `text/plain` or `text/markdown`, used as is.
+    Text,
+}
+
+impl BodyKind {
+    /// By declared type; HTML and XHTML are `None`, as are types with no converter.
+    pub(super) fn of(content_type: Option<&str>) -> Option<Self> {
+        let ct = content_type?.to_ascii_lowercase();
+        let mime = ct.split(';').next().unwrap_or_default().trim();
+        match mime {
+            "text/html" | "application/xhtml+xml" => None,
+            "application/json" | "text/json" => Some(Self::Json),
+            m if m.ends_with("+json") => Some(Self::Json),
+            "application/rss+xml" | "application/atom+xml" | "application/xml" | "text/xml" => {
+                Some(Self::Feed)
+            }
+            "text/plain" | "text/markdown" | "text/x-markdown" => Some(Self::Text),
+            _ => None,
+        }
+    }
+
+    pub(super) fn extraction(self) -> Extraction {
+        match self {
+            Self::Json => Extraction::Json,
+            Self::Feed => Extraction::Feed,
+            Self::Text => Extraction::Text,
+        }
+    }
+}
+
+/// `body` as markdown, and a title when the body names itself (a feed's
+/// channel title). `None` when it doesn't parse as `kind`.
+pub(super) fn to_markdown(
+    kind: BodyKind,
+    body: &str,
+    max_chars: usize,
+) -> Option<(Option<String>, String)> {
+    match kind {
+        BodyKind::Json => json_to_markdown(body, max_chars).map(|md| (None, md)),
+        BodyKind::Feed => feed_to_markdown(body),
+        BodyKind::Text => Some((None, body.to_string())),
+    }
+}
+
+/// Pretty-printed in a fenced block, cut at a line boundary to fit
+/// `max_chars` so the fence always closes.
+fn json_to_markdown(body: &str, max_chars: usize) -> Option<String> {
+    let value: serde_json::Value =
+        serde_json::from_str(body.trim_start_matches('\u{feff}')).ok()?;
+    let pretty = serde_json::to_string_pretty(&value).ok()?;
+
+    let budget = max_chars.saturating_sub(16);
+    let mut out = String::from("```json\n");
+    let mut used = 0;
+    for line in pretty.lines() {
+        used += line.chars().count() + 1;
+        if used > budget {
+            out.push_str("...\n");
+            break;
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.push_str("```");
+    Some(out)
+}
+
+/// The channel's title and description, then one bullet per item: linked
+/// title, date, and the start of its summary.
+fn feed_to_markdown(xml: &str) -> Option<(Option<String>, String)> {
+    let head = xml
+        .chars()
+        .take(2_048)
+        .collect::<String>()
+        .to_ascii_lowercase();
+    if !head.contains("<rss") && !head.contains("<feed") && !head.contains("<rdf:rdf") {
+        return None;
+    }
+
+    // Channel fields sit before the first item.
+    let first_item = RE_FEED_ITEM.find(xml).map_or(xml.len(), |m| m.start());
+    let channel = &xml[..first_item];
+    let title = element(channel, "title");
+    let description = element(channel, "description").or_else(|| element(channel, "subtitle"));
+
+    let mut md = String::new();
+    if let Some(title) = &title {
+        md.push_str(&format!("# {title}\n\n"));
+    }
+    if let Some(description) = description {
+        md.push_str(&format!("{description}\n\n"));
+    }
+
+    for caps in RE_FEED_ITEM.captures_iter(xml) {
+        let item = &caps[2];
+        let title = element(item, "title").unwrap_or_else(|| "(untitled)".to_string());
+        let link = if caps[1].eq_ignore_ascii_case("entry") {
+            atom_link(item)
+        } else {
+            element(item, "link").or_else(|| element(item, "guid"))
+        };
+        match link {
+            Some(link) => md.push_str(&format!("- [{title}]({link})")),
+            None => md.push_str(&format!("- {title}")),
+        }
+        if let Some(date) = ["pubDate", "published", "updated", "dc:date"]
+            .iter()
+            .find_map(|tag| element(item, tag))
+        {
+            md.push_str(&format!(" ({date})"));
+        }
+        md.push('\n');
+        if let Some(summary) = ["description", "summary", "content"]
+            .iter()
+            .find_map(|tag| element(item, tag))
+        {
+            md.push_str(&format!("  {}\n", truncate(&summary, MAX_SUMMARY_CHARS)));
+        }
+    }
+    Some((title, md.trim_end().to_string()))
+}
+
+/// The text of the first `<tag>` in `xml`, markup and entities decoded and
+/// whitespace collapsed.
+fn element(xml: &str, tag: &str) -> Option<String> {
+    let re = Regex::new(&format!(
+        r"(?is)<{0}\b[^>]*>(.*?)</{0}>",
+        regex::escape(tag)
+    ))
+    .ok()?;
+    let raw = re.captures(xml)?.get(1)?.as_str();
+    let raw = match RE_CDATA.captures(raw) {
+        Some(caps) => caps.get(1).map_or("", |m| m.as_str()).to_string(),
+        // Escaped markup (`&lt;p&gt;`) is the usual way feeds carry HTML.
+        None if raw.contains("&lt;") => raw
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&amp;", "&"),
+        None => raw.to_string(),
+    };
+    let text = if raw.contains('<') || raw.contains('&') {
+        parse_html(&raw)
+    } else {
+        raw
+    };
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+/// An Atom entry's `rel="alternate"` link, or its first link without a `rel`.
+fn atom_link(entry: &str) -> Option<String> {
+    let links: Vec<_> = RE_ATOM_LINK.captures_iter(entry).collect();
+    links
+        .iter()
+        .find(|c| RE_ATOM_ALTERNATE.is_match(&c[0]))
+        .or_else(|| links.iter().find(|c| !RE_REL.is_match(&c[0])))
+        .map(|c| c[1].to_string())
+}
+
+fn truncate(s: &str, max_chars: usize) -> String {
+    match s.char_indices().nth(max_chars) {
+        Some((i, _)) => format!("{}…", s[..i].trim_end()),
+        None => s.to_string(),
+    }
+}
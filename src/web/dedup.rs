@@ -0,0 +1,112 @@
+use super::CleanPage;
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+const SHINGLE_WORDS: usize = 3;
+const MINHASH_LEN: usize = 64;
+
+/// Cheap exact hash plus a MinHash signature over word shingles.
+struct Fingerprint {
+    exact: u64,
+    minhash: [u64; MINHASH_LEN],
+}
+
+impl Fingerprint {
+    fn of(markdown: &str) -> Self {
+        let words: Vec<String> = markdown
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+
+        let exact = hash_of(&words);
+
+        // One hash function per slot, derived from the shingle hash by seeding a mixer.
+        let mut minhash = [u64::MAX; MINHASH_LEN];
+        for shingle in words.windows(SHINGLE_WORDS.min(words.len()).max(1)) {
+            let h = hash_of(shingle);
+            for (i, slot) in minhash.iter_mut().enumerate() {
+                *slot = (*slot).min(mix(h ^ (i as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)));
+            }
+        }
+
+        Self { exact, minhash }
+    }
+
+    /// Estimated Jaccard similarity of the two pages' shingle sets.
+    fn similarity(&self, other: &Fingerprint) -> f32 {
+        let same = self
+            .minhash
+            .iter()
+            .zip(&other.minhash)
+            .filter(|(a, b)| a == b)
+            .count();
+        same as f32 / MINHASH_LEN as f32
+    }
+}
+
+/// Drop pages whose content duplicates an earlier (higher-ranked) page.
+///
+/// `pages` must be in search-rank order. Returns the kept pages and, for each
+/// dropped page, the URL of the page it duplicated.
+pub(super) fn dedupe_pages(
+    pages: Vec<CleanPage>,
+    threshold: f32,
+) -> (Vec<CleanPage>, Vec<(CleanPage, String)>) {
+    let mut seen = Deduper::default();
+    let mut kept: Vec<CleanPage> = Vec::with_capacity(pages.len());
+    let mut dropped = Vec::new();
+
+    for page in pages {
+        match seen.duplicate_of(&page, threshold) {
+            Some(url) => dropped.push((page, url)),
+            None => kept.push(page),
+        }
+    }
+
+    (kept, dropped)
+}
+
+/// `dedupe_pages` one page at a time, for pages that arrive in completion order.
+#[derive(Default)]
+pub(super) struct Deduper {
+    urls: Vec<String>,
+    prints: Vec<Fingerprint>,
+    exact: HashMap<u64, usize>,
+}
+
+impl Deduper {
+    /// The URL of an earlier page that `page` duplicates; otherwise `page` is
+    /// remembered and `None` returned.
+    pub(super) fn duplicate_of(&mut self, page: &CleanPage, threshold: f32) -> Option<String> {
+        let fp = Fingerprint::of(&page.md);
+
+        // Exact match on normalized text first, then near-duplicates.
+        let twin = self.exact.get(&fp.exact).copied().or_else(|| {
+            self.prints
+                .iter()
+                .position(|other| fp.similarity(other) >= threshold)
+        });
+        if let Some(i) = twin {
+            return Some(self.urls[i].clone());
+        }
+
+        self.exact.insert(fp.exact, self.urls.len());
+        self.prints.push(fp);
+        self.urls.push(page.url.clone());
+        None
+    }
+}
+
+fn hash_of<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut h = DefaultHasher::new();
+    value.hash(&mut h);
+    h.finish()
+}
+
+/// SplitMix64 finalizer.
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
@@ -0,0 +1,64 @@
+use reqwest::Url;
+
+/// Whether `url`'s host passes `allowed` (when non-empty) and none of
+/// `blocked` matches it. URLs without a host only pass an empty allowlist.
+pub(super) fn is_allowed(url: &str, allowed: &[String], blocked: &[String]) -> bool {
+    if allowed.is_empty() && blocked.is_empty() {
+        return true;
+    }
+    let Some(host) = host(url) else {
+        return allowed.is_empty();
+    };
+    (allowed.is_empty() || allowed.iter().any(|p| matches(p, &host)))
+        && !blocked.iter().any(|p| matches(p, &host))
+}
+
+/// Whether `url`'s host matches `pattern`, as `allowed_domains` entries do.
+pub(super) fn host_matches(url: &str, pattern: &str) -> bool {
+    host(url).is_some_and(|host| matches(pattern, &host))
+}
+
+fn host(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    url.host_str()
+        .map(|h| h.trim_end_matches('.').to_ascii_lowercase())
+}
+
+/// `docs.rs` matches the domain and its subdomains; `*` matches any run of
+/// characters, so `*.rust-lang.org` matches every subdomain (and, as
+/// people mean it, `rust-lang.org` itself).
+fn matches(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.trim().trim_end_matches('.').to_ascii_lowercase();
+    if pattern.is_empty() {
+        return false;
+    }
+    if !pattern.contains('*') {
+        return host == pattern || host.ends_with(&format!(".{pattern}"));
+    }
+    if let Some(bare) = pattern.strip_prefix("*.")
+        && !bare.contains('*')
+        && host == bare
+    {
+        return true;
+    }
+    glob(&pattern, host)
+}
+
+fn glob(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    for (i, part) in parts.iter().enumerate() {
+        if i == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    rest.is_empty()
+}
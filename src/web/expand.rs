@@ -0,0 +1,118 @@
+use super::SearchHit;
+use anyhow::Result;
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Word pairs programmers search for interchangeably; each direction is tried.
+const SYNONYMS: &[(&str, &str)] = &[
+    ("error", "exception"),
+    ("crash", "panic"),
+    ("bug", "issue"),
+    ("fix", "solve"),
+    ("example", "sample"),
+    ("docs", "documentation"),
+    ("config", "configuration"),
+    ("install", "setup"),
+    ("delete", "remove"),
+    ("slow", "performance"),
+    ("function", "method"),
+    ("library", "crate"),
+];
+
+static RE_SYNONYMS: Lazy<Vec<(Regex, &'static str)>> = Lazy::new(|| {
+    SYNONYMS
+        .iter()
+        .flat_map(|&(a, b)| [(a, b), (b, a)])
+        .map(|(from, to)| {
+            let re = Regex::new(&format!(r"(?i)\b{}\b", regex::escape(from))).unwrap();
+            (re, to)
+        })
+        .collect()
+});
+
+/// Extra queries searched alongside each one, for recall on terse queries.
+/// Off until `synonyms` or `sites` asks for variants.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QueryExpansion {
+    /// Also search with a common synonym swapped in (`error` → `exception`, ...).
+    pub synonyms: bool,
+    /// Also search restricted to each of these sites, as `site:<domain> <query>`.
+    pub sites: Vec<String>,
+    /// Variants per query at most, the query itself not counted.
+    pub max_variants: usize,
+}
+
+impl Default for QueryExpansion {
+    fn default() -> Self {
+        Self {
+            synonyms: false,
+            sites: Vec::new(),
+            max_variants: 3,
+        }
+    }
+}
+
+/// Rewrites a query into variants to search as well. Implement with
+/// `#[async_trait]` and set it on `SearchHooks::expander`, e.g. for rewrites
+/// from a language model; it replaces `LlmCleanConfig::query_expansion`.
+#[async_trait]
+pub trait QueryExpander: Send + Sync {
+    /// Variants of `query`, not including `query` itself.
+    async fn expand(&self, query: &str) -> Result<Vec<String>>;
+}
+
+#[async_trait]
+impl QueryExpander for QueryExpansion {
+    async fn expand(&self, query: &str) -> Result<Vec<String>> {
+        let mut variants = Vec::new();
+        if self.synonyms {
+            for (re, to) in RE_SYNONYMS.iter() {
+                if re.is_match(query) {
+                    variants.push(re.replace_all(query, *to).into_owned());
+                }
+            }
+        }
+        if !query.contains("site:") {
+            variants.extend(self.sites.iter().map(|site| format!("site:{site} {query}")));
+        }
+        Ok(variants)
+    }
+}
+
+/// `variants` without blanks, repeats and `query` itself, capped at `max`.
+pub(super) fn distinct_variants(query: &str, variants: Vec<String>, max: usize) -> Vec<String> {
+    let mut seen: HashSet<String> = HashSet::from([query.trim().to_lowercase()]);
+    variants
+        .into_iter()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty() && seen.insert(v.to_lowercase()))
+        .take(max)
+        .collect()
+}
+
+/// Result lists merged rank by rank (every list's first hit, then every
+/// second, ...), so each variant contributes its best hits first. Repeated
+/// URLs keep their earliest place.
+pub(super) fn interleave(lists: Vec<Vec<SearchHit>>) -> Vec<SearchHit> {
+    let mut seen = HashSet::new();
+    let mut iters: Vec<_> = lists.into_iter().map(Vec::into_iter).collect();
+    let mut out = Vec::new();
+    loop {
+        let mut any = false;
+        for hits in &mut iters {
+            if let Some(hit) = hits.next() {
+                any = true;
+                if seen.insert(hit.url.clone()) {
+                    out.push(hit);
+                }
+            }
+        }
+        if !any {
+            return out;
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use super::{Extraction, LlmCleanConfig, clean_markdown_for_llm};
+use html2md::parse_html;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use scraper::{Html, Selector};
+use serde_json::Value;
+
+/// Shorter fallback text isn't worth a page.
+const MIN_FALLBACK_CHARS: usize = 80;
+
+static RE_JSON_LD: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?is)<script\b[^>]*\btype\s*=\s*["']?application/ld\+json["']?[^>]*>(.*?)</script>"#,
+    )
+    .unwrap()
+});
+static RE_NOSCRIPT_INNER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)<noscript\b[^>]*>(.*?)</noscript>").unwrap());
+
+/// Content for pages whose body is empty until JavaScript runs, best source
+/// first: JSON-LD `articleBody`, `<noscript>` blocks, then descriptions
+/// (JSON-LD, OpenGraph, `<meta name="description">`).
+pub(super) fn fallback_markdown(html: &str, cfg: &LlmCleanConfig) -> Option<(Extraction, String)> {
+    let json_ld = json_ld(html);
+
+    let article = json_ld
+        .iter()
+        .find_map(|v| find_str(v, "articleBody"))
+        .map(|body| {
+            let body = text_or_html(body, cfg);
+            match json_ld.iter().find_map(|v| find_str(v, "headline")) {
+                Some(headline) => format!("# {}\n\n{body}", headline.trim()),
+                None => body,
+            }
+        });
+    if let Some(md) = article.filter(|md| usable(md)) {
+        return Some((Extraction::FallbackJsonLd, md));
+    }
+
+    let noscript: Vec<String> = RE_NOSCRIPT_INNER
+        .captures_iter(html)
+        .map(|c| text_or_html(&c[1], cfg))
+        .filter(|md| !md.is_empty())
+        .collect();
+    let noscript = noscript.join("\n\n");
+    if usable(&noscript) {
+        return Some((Extraction::FallbackNoscript, noscript));
+    }
+
+    let description = json_ld
+        .iter()
+        .find_map(|v| find_str(v, "description"))
+        .map(str::to_string)
+        .or_else(|| meta_description(html))
+        .map(|d| d.trim().to_string());
+    description
+        .filter(|d| usable(d))
+        .map(|d| (Extraction::FallbackMeta, d))
+}
+
+/// Every JSON-LD block on the page that parses.
+pub(super) fn json_ld(html: &str) -> Vec<Value> {
+    RE_JSON_LD
+        .captures_iter(html)
+        .filter_map(|c| serde_json::from_str(c[1].trim()).ok())
+        .collect()
+}
+
+fn usable(md: &str) -> bool {
+    md.chars().count() >= MIN_FALLBACK_CHARS
+}
+
+/// Depth-first search for a non-empty string under `key`, through `@graph` and friends.
+pub(super) fn find_str<'a>(v: &'a Value, key: &str) -> Option<&'a str> {
+    match v {
+        Value::Object(map) => map
+            .get(key)
+            .and_then(Value::as_str)
+            .filter(|s| !s.trim().is_empty())
+            .or_else(|| map.values().find_map(|child| find_str(child, key))),
+        Value::Array(items) => items.iter().find_map(|child| find_str(child, key)),
+        _ => None,
+    }
+}
+
+/// Some sites put markup in `articleBody`; only run html2md when there is any.
+fn text_or_html(s: &str, cfg: &LlmCleanConfig) -> String {
+    if s.contains('<') {
+        clean_markdown_for_llm(&parse_html(s), cfg)
+    } else {
+        clean_markdown_for_llm(s, cfg)
+    }
+}
+
+fn meta_description(html: &str) -> Option<String> {
+    let doc = Html::parse_document(html);
+    [
+        r#"meta[property="og:description"]"#,
+        r#"meta[name="description"]"#,
+        r#"meta[name="twitter:description"]"#,
+    ]
+    .iter()
+    .filter_map(|s| Selector::parse(s).ok())
+    .find_map(|sel| {
+        doc.select(&sel)
+            .filter_map(|m| m.value().attr("content"))
+            .find(|c| !c.trim().is_empty())
+            .map(str::to_string)
+    })
+}
@@ -0,0 +1,163 @@
+use super::LlmCleanConfig;
+use super::config::REDACTED;
+use super::identity::AgentPicker;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use reqwest::header::{
+    CONTENT_TYPE, ETAG, HeaderMap, HeaderName, HeaderValue, LAST_MODIFIED, RETRY_AFTER, USER_AGENT,
+};
+use reqwest::{Client, Proxy};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+
+/// Client-wide settings for [`ReqwestFetcher`]. The proxy and headers may
+/// carry credentials, so they aren't serialized, and `Debug` shows only
+/// header names.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FetchConfig {
+    /// `http://`, `https://` or `socks5://` proxy for every request. `None`
+    /// still honors the `HTTP_PROXY`/`HTTPS_PROXY`/`NO_PROXY` environment.
+    #[serde(skip_serializing)]
+    pub proxy: Option<String>,
+    /// Sent with every request, e.g. credentials for an intranet wiki.
+    #[serde(skip_serializing)]
+    pub headers: Vec<(String, String)>,
+    /// Keep cookies across the requests of one search, for sites that set one
+    /// on the first visit and expect it back.
+    pub cookies: bool,
+}
+
+impl fmt::Debug for FetchConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let headers: Vec<(&str, &str)> = self
+            .headers
+            .iter()
+            .map(|(name, _)| (name.as_str(), REDACTED))
+            .collect();
+        f.debug_struct("FetchConfig")
+            .field("proxy", &self.proxy.as_ref().map(|_| REDACTED))
+            .field("headers", &headers)
+            .field("cookies", &self.cookies)
+            .finish()
+    }
+}
+
+/// One GET issued by the pipeline.
+#[derive(Debug, Clone)]
+pub struct FetchRequest {
+    pub url: String,
+    /// Extra request headers (`Accept`, conditional-request validators).
+    pub headers: Vec<(HeaderName, String)>,
+    /// Bytes of body worth reading; anything past this is discarded anyway.
+    pub max_body_bytes: usize,
+}
+
+/// The parts of a response the pipeline looks at.
+#[derive(Debug, Clone, Default)]
+pub struct FetchedResponse {
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// `Retry-After` on 429/503 responses, as sent.
+    pub retry_after: Option<String>,
+    /// At most `max_body_bytes` of the body.
+    pub body: Vec<u8>,
+    /// Where the request ended up after redirects.
+    pub final_url: String,
+}
+
+/// Performs the HTTP fetches for a search, so tests can serve canned responses
+/// and callers can swap in e.g. a headless browser. Implement with `#[async_trait]`.
+#[async_trait]
+pub trait Fetcher: Send + Sync {
+    async fn fetch(&self, req: FetchRequest) -> Result<FetchedResponse>;
+}
+
+/// The default fetcher: reqwest with `cfg.identity` and redirects followed.
+pub struct ReqwestFetcher {
+    client: Client,
+    agents: AgentPicker,
+}
+
+impl ReqwestFetcher {
+    pub fn new(cfg: &LlmCleanConfig) -> Result<Self> {
+        let mut default_headers = HeaderMap::new();
+        for (name, value) in &cfg.fetch.headers {
+            let name = HeaderName::try_from(name.as_str())
+                .with_context(|| format!("invalid header name: {name}"))?;
+            let value = HeaderValue::try_from(value.as_str())
+                .with_context(|| format!("invalid value for header {name}"))?;
+            default_headers.append(name, value);
+        }
+
+        let mut builder = Client::builder()
+            .default_headers(default_headers)
+            .cookie_store(cfg.fetch.cookies)
+            .timeout(Duration::from_secs(cfg.total_timeout_secs))
+            .connect_timeout(Duration::from_secs(cfg.connect_timeout_secs))
+            .pool_max_idle_per_host(8)
+            .redirect(reqwest::redirect::Policy::limited(cfg.max_redirects));
+        if let Some(proxy) = &cfg.fetch.proxy {
+            let proxy =
+                Proxy::all(proxy.as_str()).with_context(|| format!("invalid proxy: {proxy}"))?;
+            builder = builder.proxy(proxy);
+        }
+        // Resets after every successful read, so large-but-steady pages still complete.
+        if let Some(idle) = cfg.read_idle_timeout_secs {
+            builder = builder.read_timeout(Duration::from_secs(idle));
+        }
+        let client = builder.build().context("failed to build reqwest client")?;
+        Ok(Self {
+            client,
+            agents: AgentPicker::new(&cfg.identity),
+        })
+    }
+}
+
+#[async_trait]
+impl Fetcher for ReqwestFetcher {
+    async fn fetch(&self, req: FetchRequest) -> Result<FetchedResponse> {
+        let mut builder = self.client.get(&req.url);
+        if !req.headers.iter().any(|(name, _)| name == USER_AGENT) {
+            builder = builder.header(USER_AGENT, self.agents.pick(&req.url));
+        }
+        for (name, value) in req.headers {
+            builder = builder.header(name, value);
+        }
+        let mut resp = builder.send().await?;
+
+        let headers = resp.headers();
+        let mut out = FetchedResponse {
+            status: resp.status().as_u16(),
+            content_type: header_string(headers, CONTENT_TYPE),
+            etag: header_string(headers, ETAG),
+            last_modified: header_string(headers, LAST_MODIFIED),
+            retry_after: header_string(headers, RETRY_AFTER),
+            body: Vec::new(),
+            final_url: resp.url().to_string(),
+        };
+
+        // Stream so oversized pages stop downloading at the cap.
+        while out.body.len() < req.max_body_bytes
+            && let Some(chunk) = resp
+                .chunk()
+                .await
+                .with_context(|| format!("failed reading body: {}", req.url))?
+        {
+            out.body.extend_from_slice(&chunk);
+        }
+        out.body.truncate(req.max_body_bytes);
+
+        Ok(out)
+    }
+}
+
+fn header_string(headers: &HeaderMap, name: HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+}
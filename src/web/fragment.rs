@@ -0,0 +1,114 @@
+use scraper::node::Node;
+use scraper::{ElementRef, Html, Selector};
+
+/// An anchor with this much text is content in itself (an answer, a
+/// `<section>`), not just a marker next to a heading.
+const MIN_BLOCK_TEXT_CHARS: usize = 200;
+
+/// The part of `html` that `#fragment` points at: for a heading, the heading
+/// and everything after it up to the next heading of the same or a higher
+/// level; for any other element with enough text, the element itself. `None`
+/// when nothing has that id or name.
+pub(super) fn section_html(html: &str, fragment: &str) -> Option<String> {
+    if fragment.is_empty() {
+        return None;
+    }
+    let doc = Html::parse_document(html);
+    let target = anchor(&doc, fragment)?;
+
+    if let Some((block, level)) = heading_block(target) {
+        let mut out = block.html();
+        for node in block.next_siblings() {
+            match node.value() {
+                Node::Element(_) => {
+                    let Some(el) = ElementRef::wrap(node) else {
+                        continue;
+                    };
+                    if starts_section(el, level) {
+                        break;
+                    }
+                    out.push_str(&el.html());
+                }
+                Node::Text(text) => out.push_str(&escape(text)),
+                _ => {}
+            }
+        }
+        return Some(out);
+    }
+
+    (text_len(target) >= MIN_BLOCK_TEXT_CHARS).then(|| target.html())
+}
+
+/// The element with `id="fragment"`, or else an `<a name="fragment">`.
+fn anchor<'a>(doc: &'a Html, fragment: &str) -> Option<ElementRef<'a>> {
+    let all = Selector::parse("[id], a[name]").unwrap();
+    doc.select(&all)
+        .find(|el| el.value().id() == Some(fragment))
+        .or_else(|| {
+            doc.select(&all)
+                .find(|el| el.value().attr("name") == Some(fragment))
+        })
+}
+
+/// The heading `target` marks, as the element whose following siblings are
+/// its section: the heading itself, a heading around the anchor, or a small
+/// wrapper holding both (GitHub's `<div class="markdown-heading">`).
+fn heading_block(target: ElementRef<'_>) -> Option<(ElementRef<'_>, u8)> {
+    if let Some(level) = heading_level(target) {
+        return Some((target, level));
+    }
+    for node in target.ancestors() {
+        let Some(el) = ElementRef::wrap(node) else {
+            break;
+        };
+        if let Some(level) = heading_level(el) {
+            return Some((el, level));
+        }
+        if text_len(el) >= MIN_BLOCK_TEXT_CHARS {
+            break;
+        }
+        if let Some(level) = first_heading(el) {
+            return Some((el, level));
+        }
+    }
+    None
+}
+
+/// Whether `el` begins a section at `level` or above, itself or as the
+/// wrapper of one.
+fn starts_section(el: ElementRef<'_>, level: u8) -> bool {
+    if let Some(l) = heading_level(el) {
+        return l <= level;
+    }
+    el.children()
+        .find_map(ElementRef::wrap)
+        .and_then(heading_level)
+        .is_some_and(|l| l <= level)
+}
+
+fn first_heading(el: ElementRef<'_>) -> Option<u8> {
+    let sel = Selector::parse("h1, h2, h3, h4, h5, h6").unwrap();
+    el.select(&sel).next().and_then(heading_level)
+}
+
+fn heading_level(el: ElementRef<'_>) -> Option<u8> {
+    match el.value().name() {
+        "h1" => Some(1),
+        "h2" => Some(2),
+        "h3" => Some(3),
+        "h4" => Some(4),
+        "h5" => Some(5),
+        "h6" => Some(6),
+        _ => None,
+    }
+}
+
+fn text_len(el: ElementRef<'_>) -> usize {
+    el.text().map(|t| t.trim().len()).sum()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
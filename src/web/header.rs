@@ -0,0 +1,306 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// How `MdPage::markdown` starts: the page's metadata, before the outline and content.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    /// `---` fenced `key: value` lines.
+    #[default]
+    YamlFrontMatter,
+    /// `+++` fenced `key = "value"` lines, as Hugo and Zola read them.
+    TomlFrontMatter,
+    /// One JSON object on the first line.
+    JsonHeader,
+    /// No header; the metadata is only on the `MdPage` fields.
+    None,
+}
+
+/// A header entry. Entries that don't apply to a page (`final_url` without a
+/// redirect, `via` for a search hit, ...) are left out regardless.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HeaderField {
+    Query,
+    Url,
+    FinalUrl,
+    ClientRedirect,
+    Extraction,
+    Via,
+    Status,
+    Title,
+    SiteName,
+    Author,
+    PublishedAt,
+    /// Only when it differs from the final URL.
+    CanonicalUrl,
+    Description,
+    Snippet,
+}
+
+impl HeaderField {
+    /// Every field, in the default header order.
+    pub const ALL: [HeaderField; 14] = [
+        HeaderField::Query,
+        HeaderField::Url,
+        HeaderField::FinalUrl,
+        HeaderField::ClientRedirect,
+        HeaderField::Extraction,
+        HeaderField::Via,
+        HeaderField::Status,
+        HeaderField::Title,
+        HeaderField::SiteName,
+        HeaderField::Author,
+        HeaderField::PublishedAt,
+        HeaderField::CanonicalUrl,
+        HeaderField::Description,
+        HeaderField::Snippet,
+    ];
+
+    pub(super) fn key(self) -> &'static str {
+        match self {
+            HeaderField::Query => "query",
+            HeaderField::Url => "url",
+            HeaderField::FinalUrl => "final_url",
+            HeaderField::ClientRedirect => "client_redirect",
+            HeaderField::Extraction => "extraction",
+            HeaderField::Via => "via",
+            HeaderField::Status => "status",
+            HeaderField::Title => "title",
+            HeaderField::SiteName => "site_name",
+            HeaderField::Author => "author",
+            HeaderField::PublishedAt => "published_at",
+            HeaderField::CanonicalUrl => "canonical_url",
+            HeaderField::Description => "description",
+            HeaderField::Snippet => "snippet",
+        }
+    }
+}
+
+/// The header for `entries`, followed by a blank line; empty for `None` or no entries.
+pub(super) fn render_header(format: OutputFormat, entries: &[(HeaderField, Value)]) -> String {
+    if entries.is_empty() {
+        return String::new();
+    }
+    let line = |sep: &str, scalar: fn(&Value) -> String| -> String {
+        entries
+            .iter()
+            .map(|(field, value)| format!("{}{sep}{}\n", field.key(), scalar(value)))
+            .collect()
+    };
+    match format {
+        OutputFormat::YamlFrontMatter => format!("---\n{}---\n\n", line(": ", yaml_value)),
+        OutputFormat::TomlFrontMatter => format!("+++\n{}+++\n\n", line(" = ", toml_value)),
+        OutputFormat::JsonHeader => {
+            let fields: Vec<String> = entries
+                .iter()
+                .map(|(field, value)| format!("{:?}:{value}", field.key()))
+                .collect();
+            format!("{{{}}}\n\n", fields.join(","))
+        }
+        OutputFormat::None => String::new(),
+    }
+}
+
+/// `markdown` without the header `render_header` put in front of it, whichever format.
+pub fn strip_header(markdown: &str) -> &str {
+    for fence in ["---\n", "+++\n"] {
+        if let Some(rest) = markdown.strip_prefix(fence)
+            && let Some(end) = rest.find(&format!("\n{fence}"))
+        {
+            return &rest[end + 1 + fence.len()..];
+        }
+    }
+    if markdown.starts_with('{')
+        && let Some((first, rest)) = markdown.split_once('\n')
+        && serde_json::from_str::<serde_json::Map<String, Value>>(first).is_ok()
+    {
+        return rest;
+    }
+    markdown
+}
+
+fn yaml_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => yaml_scalar(s),
+        other => other.to_string(),
+    }
+}
+
+/// JSON string escapes are all valid in TOML basic strings.
+fn toml_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => {
+            Value::String(s.split_whitespace().collect::<Vec<_>>().join(" ")).to_string()
+        }
+        other => other.to_string(),
+    }
+}
+
+/// Render a front-matter value as a YAML scalar. Newlines are collapsed, and the
+/// value is double-quoted whenever a plain scalar would be misparsed.
+fn yaml_scalar(value: &str) -> String {
+    let collapsed = value.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    let needs_quotes = collapsed.is_empty()
+        || collapsed.contains([':', '#', '"', '\'', '\\'])
+        || collapsed.contains(char::is_control)
+        || collapsed.starts_with([
+            '-', '?', ',', '[', ']', '{', '}', '&', '*', '!', '|', '>', '%', '@', '`',
+        ])
+        || matches!(
+            collapsed.to_ascii_lowercase().as_str(),
+            "true" | "false" | "yes" | "no" | "on" | "off" | "null" | "~"
+        )
+        || collapsed.parse::<f64>().is_ok();
+
+    if !needs_quotes {
+        return collapsed;
+    }
+
+    let mut out = String::with_capacity(collapsed.len() + 2);
+    out.push('"');
+    for ch in collapsed.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04X}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NASTY: &[&str] = &[
+        "Rust: The Book — Chapter 3: Ownership",
+        "first line\nsecond line",
+        "{ looks like a map }",
+        "[looks, like, a, list]",
+        "- looks like a list item",
+        "say \"hi\" and 'bye'",
+        r"C:\Users\path",
+        "# not a comment",
+        "issue #42",
+        "yes",
+        "Null",
+        "1.5",
+        "",
+        "   padded   ",
+        "bell\u{7}inside",
+        "@handle",
+        "50% off: today only",
+        "plain title",
+    ];
+
+    fn entries(value: &str) -> Vec<(HeaderField, Value)> {
+        vec![
+            (HeaderField::Query, value.into()),
+            (HeaderField::Title, value.into()),
+            (HeaderField::Status, 200.into()),
+        ]
+    }
+
+    fn collapsed(value: &str) -> String {
+        value.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    /// Reads back the YAML the header writes: `key: scalar` lines, each
+    /// scalar plain or double-quoted. Double-quoted YAML escapes are the JSON
+    /// ones, so serde_json decodes them; plain scalars are checked against
+    /// what YAML would read as something other than a string.
+    fn parse_yaml(header: &str) -> Vec<(String, Value)> {
+        let body = header
+            .strip_prefix("---\n")
+            .and_then(|rest| rest.strip_suffix("---\n\n"))
+            .expect("fenced front matter");
+        body.lines()
+            .map(|line| {
+                let (key, scalar) = line.split_once(": ").expect("key: value");
+                assert!(!key.contains([':', ' ']), "bad key in {line:?}");
+                let value = if scalar.starts_with('"') {
+                    serde_json::from_str::<String>(scalar)
+                        .unwrap_or_else(|e| panic!("bad quoted scalar {scalar:?}: {e}"))
+                        .into()
+                } else if let Ok(n) = scalar.parse::<i64>() {
+                    n.into()
+                } else {
+                    assert!(
+                        !scalar.is_empty()
+                            && !scalar.contains(": ")
+                            && !scalar.contains(" #")
+                            && !scalar.ends_with(':')
+                            && !scalar.contains(char::is_control)
+                            && !scalar.starts_with(|c: char| "-?,[]{}&*!|>%@`#'\"".contains(c))
+                            && scalar.trim() == scalar,
+                        "plain scalar YAML would misread: {scalar:?}"
+                    );
+                    scalar.into()
+                };
+                (key.to_string(), value)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn yaml_front_matter_round_trips() {
+        for &value in NASTY {
+            let header = render_header(OutputFormat::YamlFrontMatter, &entries(value));
+            assert_eq!(
+                parse_yaml(&header),
+                vec![
+                    ("query".to_string(), collapsed(value).into()),
+                    ("title".to_string(), collapsed(value).into()),
+                    ("status".to_string(), 200.into()),
+                ],
+                "{header}"
+            );
+        }
+    }
+
+    #[test]
+    fn toml_front_matter_round_trips() {
+        for &value in NASTY {
+            let header = render_header(OutputFormat::TomlFrontMatter, &entries(value));
+            let body = header
+                .strip_prefix("+++\n")
+                .and_then(|rest| rest.strip_suffix("+++\n\n"))
+                .expect("fenced front matter");
+            let table: toml::Table = body.parse().unwrap_or_else(|e| panic!("{e}\n{header}"));
+            assert_eq!(table["query"].as_str(), Some(collapsed(value).as_str()));
+            assert_eq!(table["title"].as_str(), Some(collapsed(value).as_str()));
+            assert_eq!(table["status"].as_integer(), Some(200));
+        }
+    }
+
+    #[test]
+    fn json_header_round_trips() {
+        for &value in NASTY {
+            let header = render_header(OutputFormat::JsonHeader, &entries(value));
+            let parsed: serde_json::Map<String, Value> =
+                serde_json::from_str(header.trim_end()).unwrap();
+            assert_eq!(parsed["query"], value);
+            assert_eq!(parsed["status"], 200);
+        }
+    }
+
+    #[test]
+    fn strip_header_leaves_the_content() {
+        for format in [
+            OutputFormat::YamlFrontMatter,
+            OutputFormat::TomlFrontMatter,
+            OutputFormat::JsonHeader,
+        ] {
+            let markdown = render_header(format, &entries("Rust: ownership")) + "## Content\n";
+            assert_eq!(
+                strip_header(&markdown).trim_start(),
+                "## Content\n",
+                "{format:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use super::domains;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// A current desktop Chrome; some sites serve bare or blocked pages to anything else.
+pub const DEFAULT_USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+
+/// Recent desktop browsers for [`UserAgent::Rotate`] with no list of its own.
+const BROWSER_AGENTS: &[&str] = &[
+    DEFAULT_USER_AGENT,
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.2 Safari/605.1.15",
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:121.0) Gecko/20100101 Firefox/121.0",
+    "Mozilla/5.0 (X11; Linux x86_64; rv:121.0) Gecko/20100101 Firefox/121.0",
+];
+
+/// The `User-Agent` the default fetcher sends.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UserAgent {
+    /// [`DEFAULT_USER_AGENT`].
+    #[default]
+    Browser,
+    Custom(String),
+    /// One per request, in turn; empty means a built-in list of browsers.
+    Rotate(Vec<String>),
+    /// `agent-bob/<version> (+<contact>)`, for crawling in the open. The
+    /// product token is the one robots.txt groups are matched against.
+    Bot {
+        /// A URL or email address site owners can reach you at.
+        contact: String,
+    },
+}
+
+/// How requests identify themselves: a default `User-Agent` and overrides
+/// for sites that block it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FetchIdentity {
+    pub user_agent: UserAgent,
+    /// `(domain pattern, user agent)`; the first pattern matching the
+    /// request's host wins. Patterns match as in `allowed_domains`.
+    pub overrides: Vec<(String, String)>,
+}
+
+/// Picks the `User-Agent` per request, advancing rotation as it goes.
+#[derive(Debug)]
+pub(super) struct AgentPicker {
+    identity: FetchIdentity,
+    next: AtomicUsize,
+}
+
+impl AgentPicker {
+    pub(super) fn new(identity: &FetchIdentity) -> Self {
+        Self {
+            identity: identity.clone(),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    pub(super) fn pick(&self, url: &str) -> String {
+        if let Some((_, agent)) = self
+            .identity
+            .overrides
+            .iter()
+            .find(|(pattern, _)| domains::host_matches(url, pattern))
+        {
+            return agent.clone();
+        }
+        match &self.identity.user_agent {
+            UserAgent::Browser => DEFAULT_USER_AGENT.to_string(),
+            UserAgent::Custom(agent) => agent.clone(),
+            UserAgent::Rotate(agents) => {
+                let n = self.next.fetch_add(1, Ordering::Relaxed);
+                if agents.is_empty() {
+                    BROWSER_AGENTS[n % BROWSER_AGENTS.len()].to_string()
+                } else {
+                    agents[n % agents.len()].clone()
+                }
+            }
+            UserAgent::Bot { contact } => bot_agent(contact),
+        }
+    }
+}
+
+fn bot_agent(contact: &str) -> String {
+    format!(
+        "agent-bob/{} (+{})",
+        env!("CARGO_PKG_VERSION"),
+        contact.trim()
+    )
+}
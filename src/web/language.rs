@@ -0,0 +1,50 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use whatlang::Lang;
+
+static RE_MD_LINK: Lazy<Regex> = Lazy::new(|| Regex::new(r"!?\[([^\]]*)\]\([^)]*\)").unwrap());
+
+/// Less prose than this is too little to tell languages apart reliably.
+const MIN_SAMPLE_CHARS: usize = 200;
+/// Detection reads at most this much; the opening of a page is representative.
+const MAX_SAMPLE_CHARS: usize = 4_000;
+
+/// Whether `code` names a language detection knows, as an ISO 639-3 code.
+pub(super) fn is_known(code: &str) -> bool {
+    Lang::from_code(code.to_ascii_lowercase()).is_some()
+}
+
+/// The language `md` is written in, as an ISO 639-3 code, when detection is
+/// confident. Code blocks and link targets are left out of the sample.
+pub(super) fn detect(md: &str) -> Option<&'static str> {
+    let mut sample = String::new();
+    let mut in_fence = false;
+    for line in md.lines() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        sample.push_str(&RE_MD_LINK.replace_all(line, "$1"));
+        sample.push('\n');
+        if sample.len() >= MAX_SAMPLE_CHARS {
+            break;
+        }
+    }
+    if sample.chars().filter(|c| c.is_alphabetic()).count() < MIN_SAMPLE_CHARS {
+        return None;
+    }
+    let info = whatlang::detect(&sample)?;
+    info.is_reliable().then(|| info.lang().code())
+}
+
+/// False only when `md` is confidently in a language outside `allowed`; an
+/// empty list allows everything, and undetectable pages are kept.
+pub(super) fn is_allowed(md: &str, allowed: &[String]) -> bool {
+    if allowed.is_empty() {
+        return true;
+    }
+    detect(md).is_none_or(|lang| allowed.iter().any(|a| a.eq_ignore_ascii_case(lang)))
+}
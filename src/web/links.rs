@@ -0,0 +1,139 @@
+use super::relevance::query_terms;
+use super::sitemap::in_scope;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use reqwest::Url;
+use scraper::{Html, Selector};
+use std::collections::HashSet;
+
+static RE_LINK_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<link\b[^>]*>").unwrap());
+static RE_REL_CANONICAL: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)\brel\s*=\s*["']?[^"'>]*\bcanonical\b"#).unwrap());
+/// Anchor text of a link to the next part of the same document.
+static RE_NEXT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^\s*(?:next(?:\s+(?:page|part|chapter|section))?|continue(?:\s+reading)?|read\s+more|older\s+posts)\s*[›»→]*\s*$|^\s*[›»→]\s*$")
+        .unwrap()
+});
+static RE_HREF: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)\bhref\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s>]+))"#).unwrap());
+
+/// Same-host links from `html` worth following for `query`, best first.
+///
+/// Links are ranked by how many query terms their anchor text contains; links
+/// sharing no term with the query are not returned, except "next page" links
+/// (by `rel="next"` or their text), which rank first since paginated docs
+/// continue there. Fragments are stripped and links back to `base` itself
+/// are skipped.
+pub(super) fn follow_candidates(html: &str, base: &str, query: &str, max: usize) -> Vec<String> {
+    let Ok(base) = Url::parse(base) else {
+        return vec![];
+    };
+    let terms = query_terms(query);
+    if terms.is_empty() || max == 0 {
+        return vec![];
+    }
+
+    let doc = Html::parse_document(html);
+    let sel = Selector::parse("a[href]").unwrap();
+
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut scored: Vec<(usize, String)> = Vec::new();
+
+    for a in doc.select(&sel) {
+        let Some(mut link) = a.value().attr("href").and_then(|h| base.join(h).ok()) else {
+            continue;
+        };
+        link.set_fragment(None);
+
+        if !matches!(link.scheme(), "http" | "https")
+            || link.host_str() != base.host_str()
+            || same_page(&link, &base)
+        {
+            continue;
+        }
+
+        let anchor: String = a.text().collect();
+        let next = a.value().attr("rel").is_some_and(|rel| {
+            rel.split_whitespace()
+                .any(|r| r.eq_ignore_ascii_case("next"))
+        }) || RE_NEXT.is_match(&anchor);
+        let overlap = if next {
+            usize::MAX
+        } else {
+            query_terms(&anchor).intersection(&terms).count()
+        };
+        if overlap == 0 {
+            continue;
+        }
+
+        let link = link.to_string();
+        if seen.insert(link.clone()) {
+            scored.push((overlap, link));
+        }
+    }
+
+    // Stable: ties keep document order.
+    scored.sort_by(|a, b| b.0.cmp(&a.0));
+    scored.into_iter().take(max).map(|(_, url)| url).collect()
+}
+
+// Links to these are downloads or assets, never pages.
+static RE_NON_PAGE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\.(?:png|jpe?g|gif|webp|svg|ico|css|js|zip|gz|tgz|tar|exe|dmg|mp[34]|woff2?)$")
+        .unwrap()
+});
+
+/// Every link from `html` that stays within `site` (see `in_scope`), in
+/// document order, for crawling a whole site.
+pub(super) fn site_links(html: &str, base: &str, site: &Url) -> Vec<String> {
+    let Ok(base) = Url::parse(base) else {
+        return vec![];
+    };
+    let doc = Html::parse_document(html);
+    let sel = Selector::parse("a[href]").unwrap();
+
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut out = Vec::new();
+    for a in doc.select(&sel) {
+        let Some(mut link) = a.value().attr("href").and_then(|h| base.join(h).ok()) else {
+            continue;
+        };
+        link.set_fragment(None);
+
+        if !matches!(link.scheme(), "http" | "https")
+            || same_page(&link, &base)
+            || RE_NON_PAGE.is_match(link.path())
+            || !in_scope(site, link.as_str())
+        {
+            continue;
+        }
+        let link = link.to_string();
+        if seen.insert(link.clone()) {
+            out.push(link);
+        }
+    }
+    out
+}
+
+/// The page's `<link rel="canonical">`, resolved against `base`.
+pub(super) fn canonical_url(html: &str, base: &str) -> Option<String> {
+    let tag = RE_LINK_TAG
+        .find_iter(html)
+        .map(|m| m.as_str())
+        .find(|tag| RE_REL_CANONICAL.is_match(tag))?;
+    let caps = RE_HREF.captures(tag)?;
+    let href = caps.get(1).or(caps.get(2)).or(caps.get(3))?.as_str();
+
+    let base = Url::parse(base).ok()?;
+    let url = base.join(href.trim()).ok()?;
+
+    // A site-wide canonical pointing at the homepage is a misconfiguration, not a duplicate.
+    if url.path() == "/" && base.path() != "/" {
+        return None;
+    }
+    matches!(url.scheme(), "http" | "https").then(|| url.to_string())
+}
+
+fn same_page(a: &Url, b: &Url) -> bool {
+    a.path() == b.path() && a.query() == b.query()
+}
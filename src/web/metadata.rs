@@ -0,0 +1,123 @@
+use super::fallback::{find_str, json_ld};
+use scraper::{Html, Selector};
+use serde_json::Value;
+
+/// What a page says about itself in meta tags, OpenGraph and JSON-LD.
+#[derive(Debug, Clone, Default)]
+pub(super) struct PageMeta {
+    pub(super) description: Option<String>,
+    pub(super) published_at: Option<String>,
+    pub(super) author: Option<String>,
+    pub(super) site_name: Option<String>,
+    pub(super) canonical_url: Option<String>,
+}
+
+impl PageMeta {
+    /// JSON-LD first, as the most deliberate, then OpenGraph and plain meta
+    /// tags. `canonical_url` is the page's declared canonical, already resolved.
+    pub(super) fn extract(html: &str, canonical_url: Option<String>) -> Self {
+        let json_ld = json_ld(html);
+        let doc = Html::parse_document(html);
+        let from_ld = |key: &str| json_ld.iter().find_map(|v| find_str(v, key)).map(clean);
+
+        Self {
+            description: from_ld("description").or_else(|| {
+                meta(
+                    &doc,
+                    &[
+                        r#"meta[property="og:description"]"#,
+                        r#"meta[name="description"]"#,
+                        r#"meta[name="twitter:description"]"#,
+                    ],
+                )
+            }),
+            published_at: from_ld("datePublished").or_else(|| {
+                meta(
+                    &doc,
+                    &[
+                        r#"meta[property="article:published_time"]"#,
+                        r#"meta[itemprop="datePublished"]"#,
+                        r#"meta[name="date"]"#,
+                        r#"meta[name="DC.date.issued"]"#,
+                        r#"meta[name="publish-date"]"#,
+                    ],
+                )
+            }),
+            author: json_ld
+                .iter()
+                .find_map(|v| find(v, "author"))
+                .and_then(names)
+                .or_else(|| {
+                    meta(
+                        &doc,
+                        &[
+                            r#"meta[name="author"]"#,
+                            r#"meta[property="article:author"]"#,
+                            r#"meta[name="twitter:creator"]"#,
+                        ],
+                    )
+                }),
+            site_name: meta(
+                &doc,
+                &[
+                    r#"meta[property="og:site_name"]"#,
+                    r#"meta[name="application-name"]"#,
+                ],
+            )
+            .or_else(|| {
+                json_ld
+                    .iter()
+                    .find_map(|v| find(v, "publisher"))
+                    .and_then(names)
+            }),
+            canonical_url,
+        }
+    }
+}
+
+/// The first non-empty `content` of the `selectors`, in order.
+fn meta(doc: &Html, selectors: &[&str]) -> Option<String> {
+    selectors
+        .iter()
+        .filter_map(|s| Selector::parse(s).ok())
+        .find_map(|sel| {
+            doc.select(&sel)
+                .filter_map(|m| m.value().attr("content"))
+                .find(|c| !c.trim().is_empty())
+                .map(clean)
+        })
+}
+
+/// Depth-first search for the first value under `key`.
+fn find<'a>(v: &'a Value, key: &str) -> Option<&'a Value> {
+    match v {
+        Value::Object(map) => map
+            .get(key)
+            .or_else(|| map.values().find_map(|child| find(child, key))),
+        Value::Array(items) => items.iter().find_map(|child| find(child, key)),
+        _ => None,
+    }
+}
+
+/// A person or organization as JSON-LD gives it: a name, an object with a
+/// `name`, or a list of either.
+fn names(v: &Value) -> Option<String> {
+    let names: Vec<String> = match v {
+        Value::Array(items) => items.iter().filter_map(names).collect(),
+        Value::Object(map) => map
+            .get("name")
+            .and_then(Value::as_str)
+            .map(clean)
+            .into_iter()
+            .collect(),
+        Value::String(s) => vec![clean(s)],
+        _ => Vec::new(),
+    };
+    let names: Vec<String> = names.into_iter().filter(|n| !n.is_empty()).collect();
+    (!names.is_empty()).then(|| names.join(", "))
+}
+
+/// Whitespace runs collapsed, as meta values often carry the markup's line breaks.
+fn clean(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,46 @@
+use anyhow::{Result, anyhow};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+// "2.3 Results" / "4. Discussion": numbered section titles become headings.
+static RE_SECTION: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(\d{1,2}(?:\.\d{1,2}){0,4})\.?\s+\p{Lu}\S*").unwrap());
+// A word broken across lines by the layout.
+static RE_HYPHEN_BREAK: Lazy<Regex> = Lazy::new(|| Regex::new(r"(\p{Ll})-\n(\p{Ll})").unwrap());
+
+/// Section titles are short; longer numbered lines are list items or prose.
+const MAX_HEADING_CHARS: usize = 80;
+
+/// By declared type, or by magic bytes for servers that send `application/octet-stream`.
+pub(super) fn is_pdf(content_type: Option<&str>, body: &[u8]) -> bool {
+    content_type.is_some_and(|ct| ct.to_ascii_lowercase().contains("application/pdf"))
+        || body.starts_with(b"%PDF-")
+}
+
+/// Text of a PDF as markdown, numbered section titles turned into headings so
+/// the page gets an outline. Runs on the blocking pool; parsing is CPU-bound.
+pub(super) async fn pdf_to_markdown(body: Vec<u8>) -> Result<String> {
+    let text = tokio::task::spawn_blocking(move || pdf_extract::extract_text_from_mem(&body))
+        .await
+        .map_err(|e| anyhow!("pdf extraction panicked: {e}"))?
+        .map_err(|e| anyhow!("pdf extraction failed: {e}"))?;
+
+    let text = text.replace('\u{c}', "\n\n");
+    let text = RE_HYPHEN_BREAK.replace_all(&text, "$1$2");
+
+    let mut md = String::with_capacity(text.len());
+    for line in text.lines() {
+        let line = line.trim_end();
+        match RE_SECTION.captures(line.trim_start()) {
+            Some(c) if line.chars().count() <= MAX_HEADING_CHARS && !line.ends_with('.') => {
+                let depth = c[1].split('.').count().min(5);
+                md.push_str(&"#".repeat(depth + 1));
+                md.push(' ');
+                md.push_str(line.trim_start());
+            }
+            _ => md.push_str(line),
+        }
+        md.push('\n');
+    }
+    Ok(md)
+}
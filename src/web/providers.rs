@@ -0,0 +1,176 @@
+use super::config::REDACTED;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+use websearch::providers::brave::BraveProvider;
+use websearch::providers::duckduckgo::{DuckDuckGoConfig, DuckDuckGoProvider};
+use websearch::providers::google::GoogleProvider;
+use websearch::providers::searxng::SearxNGProvider;
+use websearch::{SearchOptions, web_search};
+
+const BING_ENDPOINT: &str = "https://api.bing.microsoft.com/v7.0/search";
+
+/// One search result.
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub url: String,
+    pub title: String,
+    pub snippet: Option<String>,
+}
+
+/// Turns a query into result URLs. Implement with `#[async_trait]` and set it
+/// on `SearchHooks::search` for a backend `SearchConfig` doesn't cover.
+#[async_trait]
+pub trait SearchProvider: Send + Sync {
+    async fn search(&self, query: &str, max_results: u32) -> Result<Vec<SearchHit>>;
+}
+
+/// The built-in search backends. Everything but DuckDuckGo needs credentials
+/// or an instance of its own. Serialized under the names the settings
+/// file uses; API keys are left out, and redacted from `Debug` output.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchConfig {
+    #[default]
+    DuckDuckGo,
+    Brave {
+        #[serde(skip_serializing, default)]
+        api_key: String,
+    },
+    /// Bing Web Search API v7.
+    Bing {
+        #[serde(skip_serializing, default)]
+        api_key: String,
+    },
+    /// A SearXNG instance with the JSON format enabled.
+    SearXng { base_url: String },
+    /// Google Programmable Search (Custom Search JSON API).
+    #[serde(rename = "google")]
+    GoogleCse {
+        #[serde(skip_serializing, default)]
+        api_key: String,
+        cx: String,
+    },
+}
+
+impl fmt::Debug for SearchConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DuckDuckGo => f.write_str("DuckDuckGo"),
+            Self::Brave { .. } => f.debug_struct("Brave").field("api_key", &REDACTED).finish(),
+            Self::Bing { .. } => f.debug_struct("Bing").field("api_key", &REDACTED).finish(),
+            Self::SearXng { base_url } => f
+                .debug_struct("SearXng")
+                .field("base_url", base_url)
+                .finish(),
+            Self::GoogleCse { cx, .. } => f
+                .debug_struct("GoogleCse")
+                .field("api_key", &REDACTED)
+                .field("cx", cx)
+                .finish(),
+        }
+    }
+}
+
+impl SearchConfig {
+    pub fn provider(&self) -> Arc<dyn SearchProvider> {
+        Arc::new(BuiltinProvider {
+            cfg: self.clone(),
+            client: Client::new(),
+        })
+    }
+}
+
+struct BuiltinProvider {
+    cfg: SearchConfig,
+    /// Only used for Bing; the `websearch` providers bring their own.
+    client: Client,
+}
+
+#[async_trait]
+impl SearchProvider for BuiltinProvider {
+    async fn search(&self, query: &str, max_results: u32) -> Result<Vec<SearchHit>> {
+        // `websearch` providers aren't `Clone` and `SearchOptions` owns one, so
+        // build a fresh one per query; it's just config.
+        let provider: Box<dyn websearch::SearchProvider> = match &self.cfg {
+            SearchConfig::DuckDuckGo => {
+                Box::new(DuckDuckGoProvider::with_config(DuckDuckGoConfig::default()))
+            }
+            SearchConfig::Brave { api_key } => Box::new(BraveProvider::new(api_key)?),
+            SearchConfig::SearXng { base_url } => Box::new(SearxNGProvider::new(base_url)?),
+            SearchConfig::GoogleCse { api_key, cx } => Box::new(GoogleProvider::new(api_key, cx)?),
+            SearchConfig::Bing { api_key } => {
+                return bing(&self.client, api_key, query, max_results).await;
+            }
+        };
+
+        let results = web_search(SearchOptions {
+            query: query.to_string(),
+            max_results: Some(max_results),
+            provider,
+            ..Default::default()
+        })
+        .await?;
+        Ok(results
+            .into_iter()
+            .map(|r| SearchHit {
+                url: r.url,
+                title: r.title,
+                snippet: r.snippet,
+            })
+            .collect())
+    }
+}
+
+#[derive(Deserialize)]
+struct BingResponse {
+    #[serde(rename = "webPages")]
+    web_pages: Option<BingWebPages>,
+}
+
+#[derive(Deserialize)]
+struct BingWebPages {
+    value: Vec<BingPage>,
+}
+
+#[derive(Deserialize)]
+struct BingPage {
+    url: String,
+    name: String,
+    snippet: Option<String>,
+}
+
+async fn bing(
+    client: &Client,
+    api_key: &str,
+    query: &str,
+    max_results: u32,
+) -> Result<Vec<SearchHit>> {
+    let body = client
+        .get(BING_ENDPOINT)
+        .query(&[("q", query), ("count", &max_results.to_string())])
+        .header("Ocp-Apim-Subscription-Key", api_key)
+        .send()
+        .await?
+        .error_for_status()
+        .context("bing search failed")?
+        .text()
+        .await?;
+    let resp: BingResponse = serde_json::from_str(&body).context("unexpected bing response")?;
+
+    Ok(resp
+        .web_pages
+        .map(|p| p.value)
+        .unwrap_or_default()
+        .into_iter()
+        .take(max_results as usize)
+        .map(|p| SearchHit {
+            url: p.url,
+            title: p.name,
+            snippet: p.snippet,
+        })
+        .collect())
+}
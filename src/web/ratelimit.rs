@@ -0,0 +1,84 @@
+use reqwest::Url;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Per-host cap on in-flight requests plus an optional token bucket on
+/// request starts. Sits under the global `concurrency` semaphore.
+pub(super) struct HostLimiter {
+    max_in_flight: usize,
+    rate: Option<f32>,
+    hosts: Mutex<HashMap<String, Arc<Host>>>,
+}
+
+struct Host {
+    in_flight: Arc<Semaphore>,
+    bucket: Mutex<Bucket>,
+}
+
+/// `tokens` goes negative while callers are queued for a refill.
+struct Bucket {
+    tokens: f32,
+    refilled: Instant,
+}
+
+impl HostLimiter {
+    pub(super) fn new(max_in_flight: usize, rate: Option<f32>) -> Self {
+        Self {
+            max_in_flight,
+            rate,
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Wait for a slot on `url`'s host; the request may start once this
+    /// returns and counts as in flight until the permit drops.
+    pub(super) async fn acquire(&self, url: &str) -> Option<OwnedSemaphorePermit> {
+        let host_name = Url::parse(url)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_ascii_lowercase))?;
+        let host = self
+            .hosts
+            .lock()
+            .unwrap()
+            .entry(host_name)
+            .or_insert_with(|| {
+                Arc::new(Host {
+                    in_flight: Arc::new(Semaphore::new(self.max_in_flight)),
+                    bucket: Mutex::new(Bucket {
+                        tokens: self.burst(),
+                        refilled: Instant::now(),
+                    }),
+                })
+            })
+            .clone();
+
+        let permit = host
+            .in_flight
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("semaphore closed");
+
+        if let Some(rate) = self.rate {
+            let wait = {
+                let mut bucket = host.bucket.lock().unwrap();
+                let now = Instant::now();
+                let refill = now.duration_since(bucket.refilled).as_secs_f32() * rate;
+                bucket.tokens = (bucket.tokens + refill).min(self.burst()) - 1.0;
+                bucket.refilled = now;
+                (bucket.tokens < 0.0).then(|| Duration::from_secs_f32(-bucket.tokens / rate))
+            };
+            if let Some(wait) = wait {
+                tokio::time::sleep(wait).await;
+            }
+        }
+        Some(permit)
+    }
+
+    /// Requests a host may take back to back after a quiet spell.
+    fn burst(&self) -> f32 {
+        self.rate.map_or(1.0, |r| r.max(1.0))
+    }
+}
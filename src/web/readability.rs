@@ -0,0 +1,198 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use scraper::{ElementRef, Html, Selector};
+use std::collections::HashMap;
+
+static RE_UNLIKELY: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)banner|breadcrumb|combx|comment|community|cookie|disqus|extra|footer|gdpr|header|legends|menu|modal|related|remark|replies|rss|share|shoutbox|sidebar|skyscraper|social|sponsor|ad-break|agegate|pagination|pager|popup|promo|subscribe|yom-remote").unwrap()
+});
+static RE_MAYBE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)and|article|body|column|content|main|shadow").unwrap());
+static RE_POSITIVE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)article|body|content|entry|hentry|h-entry|main|page|post|text|blog|story|docs?\b|markdown").unwrap()
+});
+static RE_NEGATIVE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)-ad-|hidden|^hid$| hid$| hid |^hid |banner|combx|comment|com-|contact|foot|footer|footnote|gdpr|masthead|media|meta|outbrain|promo|related|scroll|share|shoutbox|sidebar|skyscraper|sponsor|shopping|tags|widget").unwrap()
+});
+
+static SEL_SCORABLE: Lazy<Selector> =
+    Lazy::new(|| Selector::parse("p, pre, td, blockquote").unwrap());
+static SEL_LINK: Lazy<Selector> = Lazy::new(|| Selector::parse("a").unwrap());
+
+/// Paragraphs shorter than this don't vote for their container.
+const MIN_SCORED_CHARS: usize = 25;
+/// Below this much text the result is not worth returning.
+const MIN_RESULT_CHARS: usize = 200;
+
+const DROPPED_TAGS: &[&str] = &[
+    "script", "style", "noscript", "svg", "form", "iframe", "nav", "aside", "footer", "button",
+    "input", "select", "textarea",
+];
+const VOID_TAGS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Readability-style main-content extraction: paragraphs score their parent
+/// and grandparent by length and commas, scores are discounted by link
+/// density, and the best container is returned together with siblings that
+/// score close to it, pruned of navigation, forms and link lists.
+pub(super) fn extract(html: &str) -> Option<String> {
+    let doc = Html::parse_document(html);
+
+    let mut candidates: Vec<ElementRef> = Vec::new();
+    let mut scores = HashMap::new();
+
+    for el in doc.select(&SEL_SCORABLE) {
+        if el.ancestors().filter_map(ElementRef::wrap).any(is_unlikely) {
+            continue;
+        }
+        let len = text_len(el);
+        if len < MIN_SCORED_CHARS {
+            continue;
+        }
+        let commas = el.text().map(|t| t.matches(',').count()).sum::<usize>();
+        let score = 1.0 + commas as f32 + (len / 100).min(3) as f32;
+
+        let containers = el.ancestors().filter_map(ElementRef::wrap).take(2);
+        for (level, container) in containers.enumerate() {
+            let entry = scores.entry(container.id()).or_insert_with(|| {
+                candidates.push(container);
+                initial_score(container)
+            });
+            *entry += if level == 0 { score } else { score / 2.0 };
+        }
+    }
+
+    let (top, top_score) = candidates
+        .iter()
+        .map(|el| (*el, scores[&el.id()] * (1.0 - link_density(*el))))
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+
+    // Content is often split across sibling containers (intro div, body div).
+    let threshold = (top_score * 0.2).max(10.0);
+    let mut out = String::from(r#"<div id="extracted">"#);
+    let siblings: Vec<ElementRef> = match top.parent() {
+        Some(parent) => parent.children().filter_map(ElementRef::wrap).collect(),
+        None => vec![top],
+    };
+    for sib in siblings {
+        let keep = sib == top
+            || scores
+                .get(&sib.id())
+                .is_some_and(|s| s * (1.0 - link_density(sib)) >= threshold)
+            || (sib.value().name() == "p" && text_len(sib) > 80 && link_density(sib) < 0.25);
+        if keep {
+            write_pruned(sib, &mut out);
+        }
+    }
+    out.push_str("</div>");
+
+    let extracted = Html::parse_fragment(&out);
+    let len: usize = extracted
+        .root_element()
+        .text()
+        .map(|t| t.trim().chars().count())
+        .sum();
+    (len >= MIN_RESULT_CHARS).then_some(out)
+}
+
+fn initial_score(el: ElementRef) -> f32 {
+    let tag = match el.value().name() {
+        "div" | "article" | "main" | "section" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "address" | "ol" | "ul" | "dl" | "dd" | "dt" | "li" | "form" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    };
+    tag + class_weight(el)
+}
+
+/// +25 for each of class and id that looks like content, -25 for chrome.
+fn class_weight(el: ElementRef) -> f32 {
+    let mut weight = 0.0;
+    let class = el.value().attr("class");
+    for name in [class, el.value().id()].into_iter().flatten() {
+        if RE_NEGATIVE.is_match(name) {
+            weight -= 25.0;
+        }
+        if RE_POSITIVE.is_match(name) {
+            weight += 25.0;
+        }
+    }
+    weight
+}
+
+fn is_unlikely(el: ElementRef) -> bool {
+    if matches!(
+        el.value().name(),
+        "body" | "html" | "a" | "main" | "article"
+    ) {
+        return false;
+    }
+    let names = format!(
+        "{} {}",
+        el.value().attr("class").unwrap_or(""),
+        el.value().id().unwrap_or("")
+    );
+    RE_UNLIKELY.is_match(&names) && !RE_MAYBE.is_match(&names)
+}
+
+fn text_len(el: ElementRef) -> usize {
+    el.text().map(|t| t.trim().chars().count()).sum()
+}
+
+/// Fraction of the element's text that sits inside links.
+fn link_density(el: ElementRef) -> f32 {
+    let total = text_len(el);
+    if total == 0 {
+        return 0.0;
+    }
+    let in_links: usize = el.select(&SEL_LINK).map(text_len).sum();
+    in_links as f32 / total as f32
+}
+
+/// Drop chrome and link lists that sit inside otherwise good content.
+fn should_prune(el: ElementRef) -> bool {
+    let name = el.value().name();
+    if DROPPED_TAGS.contains(&name) || is_unlikely(el) {
+        return true;
+    }
+    matches!(name, "div" | "section" | "ul" | "ol" | "table")
+        && class_weight(el) < 25.0
+        && link_density(el) > 0.5
+}
+
+/// Serialize `el` as HTML, skipping pruned descendants.
+fn write_pruned(el: ElementRef, out: &mut String) {
+    if should_prune(el) {
+        return;
+    }
+    let name = el.value().name();
+    out.push('<');
+    out.push_str(name);
+    for (key, value) in el.value().attrs() {
+        out.push_str(&format!(r#" {key}="{}""#, escape(value, true)));
+    }
+    out.push('>');
+    if VOID_TAGS.contains(&name) {
+        return;
+    }
+
+    for child in el.children() {
+        if let Some(child) = ElementRef::wrap(child) {
+            write_pruned(child, out);
+        } else if let Some(text) = child.value().as_text() {
+            out.push_str(&escape(text, false));
+        }
+    }
+    out.push_str(&format!("</{name}>"));
+}
+
+fn escape(s: &str, attr: bool) -> String {
+    let s = s
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    if attr { s.replace('"', "&quot;") } else { s }
+}
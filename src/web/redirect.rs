@@ -0,0 +1,40 @@
+use super::RE_SCRIPT;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use reqwest::Url;
+
+static RE_META_REFRESH: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)<meta\b[^>]*\bhttp-equiv\s*=\s*["']?refresh["']?[^>]*>"#).unwrap()
+});
+static RE_REFRESH_URL: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?is)\bcontent\s*=\s*["']\s*[\d.]*\s*[;,]\s*url\s*=\s*['"]?([^"'\s>]+)"#).unwrap()
+});
+static RE_JS_LOCATION: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?i)\blocation(?:\.href)?\s*=\s*["']([^"']+)["']|\blocation\.(?:replace|assign)\(\s*["']([^"']+)["']"#,
+    )
+    .unwrap()
+});
+
+/// Target of a `<meta http-equiv="refresh">` tag or an inline-script
+/// `location` assignment, resolved against `base`.
+pub(super) fn client_redirect_target(html: &str, base: &str) -> Option<String> {
+    let meta = RE_META_REFRESH
+        .find_iter(html)
+        .find_map(|tag| RE_REFRESH_URL.captures(tag.as_str()))
+        .map(|c| c[1].to_string());
+
+    let script = || {
+        RE_SCRIPT.find_iter(html).find_map(|script| {
+            RE_JS_LOCATION
+                .captures(script.as_str())
+                .and_then(|c| c.get(1).or_else(|| c.get(2)))
+                .map(|m| m.as_str().to_string())
+        })
+    };
+
+    let target = meta.or_else(script)?;
+    let target = target.trim().replace("&amp;", "&");
+    let url = Url::parse(base).ok()?.join(&target).ok()?;
+    matches!(url.scheme(), "http" | "https").then(|| url.to_string())
+}
@@ -0,0 +1,70 @@
+use super::{MdPage, content_body};
+use std::collections::{HashMap, HashSet};
+
+// BM25 term saturation and length normalization.
+const K1: f32 = 1.2;
+const B: f32 = 0.75;
+const REFERENCE_LEN: f32 = 1_000.0;
+
+// Title and outline terms count for more than body terms.
+const TITLE_WEIGHT: f32 = 3.0;
+const OUTLINE_WEIGHT: f32 = 2.0;
+
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "how", "in", "is", "of", "on", "or", "the", "to", "what", "with",
+];
+
+/// Score how well `page` matches `query`, in `0.0..1.0`.
+///
+/// BM25-style saturation of weighted term frequencies, averaged over the
+/// distinct query terms. There is no corpus, so no IDF: the score is
+/// comparable across pages and queries without re-scoring the whole set.
+pub fn score_relevance(query: &str, page: &MdPage) -> f32 {
+    let terms = query_terms(query);
+    if terms.is_empty() {
+        return 0.0;
+    }
+
+    let mut tf: HashMap<String, f32> = HashMap::new();
+    let mut len = 0usize;
+    let mut add = |text: &str, weight: f32| {
+        for tok in tokenize(text) {
+            len += 1;
+            if terms.contains(&tok) {
+                *tf.entry(tok).or_default() += weight;
+            }
+        }
+    };
+
+    add(content_body(&page.markdown), 1.0);
+    if let Some(title) = &page.title {
+        add(title, TITLE_WEIGHT);
+    }
+    for h in &page.outline {
+        add(&h.text, OUTLINE_WEIGHT);
+    }
+
+    let norm = 1.0 - B + B * (len as f32 / REFERENCE_LEN);
+    let total: f32 = terms
+        .iter()
+        .map(|t| {
+            let f = tf.get(t).copied().unwrap_or(0.0);
+            f / (f + K1 * norm)
+        })
+        .sum();
+
+    total / terms.len() as f32
+}
+
+/// Distinct lowercase terms of `text`, minus stop words.
+pub(super) fn query_terms(text: &str) -> HashSet<String> {
+    tokenize(text)
+        .filter(|t| !STOP_WORDS.contains(&t.as_str()))
+        .collect()
+}
+
+fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+}
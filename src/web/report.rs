@@ -0,0 +1,114 @@
+use super::{CrawlStats, DropReason, MdPage};
+use std::fmt;
+
+/// Everything one search produced, failures included. See [`super::search_with_report`].
+#[derive(Debug, Clone, Default)]
+pub struct SearchReport {
+    pub pages: Vec<MdPage>,
+    /// Searches and URLs that produced no page, in completion order.
+    pub failures: Vec<CrawlFailure>,
+    pub stats: CrawlStats,
+}
+
+/// A query or URL that didn't make it into the results.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrawlFailure {
+    pub query: String,
+    /// `None` when the search itself failed.
+    pub url: Option<String>,
+    pub reason: FailureReason,
+    /// The underlying error, or the drop reason, for logs.
+    pub message: String,
+}
+
+/// Why a [`CrawlFailure`] happened. Duplicates aren't failures: their
+/// content is in the results under another URL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum FailureReason {
+    /// The search provider returned an error.
+    Search,
+    /// Connect, total or read-idle timeout, after any retries.
+    Timeout,
+    /// DNS, connect, TLS or body-read failure, after any retries.
+    Network,
+    /// A non-2xx status.
+    HttpStatus(u16),
+    /// Not HTML, and not a PDF that could be ingested.
+    NonHtml,
+    /// Too little content, before or after boilerplate stripping, and no
+    /// fallback text.
+    TooSmall,
+    /// Mostly links with little prose.
+    LowQuality,
+    /// A body that couldn't be turned into text, such as a broken PDF.
+    ParseError,
+    RobotsDisallowed,
+    /// Outside `LlmCleanConfig::allowed_languages`.
+    WrongLanguage,
+    /// Excluded by `allowed_domains` or `blocked_domains`.
+    BlockedDomain,
+    /// Still unfinished when the call's deadline or byte budget ran out.
+    OverBudget,
+    Cancelled,
+}
+
+impl FailureReason {
+    /// The failure a drop amounts to; `None` for duplicates. `status` is the
+    /// response status, when there was one.
+    pub(super) fn from_drop(reason: DropReason, status: Option<u16>) -> Option<Self> {
+        Some(match reason {
+            DropReason::NonSuccessStatus => Self::HttpStatus(status.unwrap_or(0)),
+            DropReason::NotHtml => Self::NonHtml,
+            DropReason::TooShort | DropReason::JsRendered => Self::TooSmall,
+            DropReason::LowQuality => Self::LowQuality,
+            DropReason::Cancelled => Self::Cancelled,
+            DropReason::RobotsDisallowed => Self::RobotsDisallowed,
+            DropReason::WrongLanguage => Self::WrongLanguage,
+            DropReason::BlockedDomain => Self::BlockedDomain,
+            DropReason::OverBudget => Self::OverBudget,
+            DropReason::Duplicate | DropReason::CanonicalDuplicate => return None,
+        })
+    }
+
+    /// Timeouts and transport errors by their `reqwest` cause; anything else
+    /// went wrong turning the body into text.
+    pub(super) fn from_error(e: &anyhow::Error) -> Self {
+        match e
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<reqwest::Error>())
+        {
+            Some(e) if e.is_timeout() => Self::Timeout,
+            Some(_) => Self::Network,
+            None => Self::ParseError,
+        }
+    }
+
+    /// Whether the fetch broke, as opposed to the page being filtered out.
+    pub fn is_error(self) -> bool {
+        matches!(
+            self,
+            Self::Search | Self::Timeout | Self::Network | Self::ParseError
+        )
+    }
+}
+
+impl fmt::Display for FailureReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Search => f.write_str("search failed"),
+            Self::Timeout => f.write_str("timed out"),
+            Self::Network => f.write_str("network error"),
+            Self::HttpStatus(status) => write!(f, "http status {status}"),
+            Self::NonHtml => f.write_str("not html"),
+            Self::TooSmall => f.write_str("too little content"),
+            Self::LowQuality => f.write_str("link-heavy, too little prose"),
+            Self::ParseError => f.write_str("unreadable content"),
+            Self::RobotsDisallowed => f.write_str("disallowed by robots.txt"),
+            Self::WrongLanguage => f.write_str("not in an allowed language"),
+            Self::BlockedDomain => f.write_str("blocked domain"),
+            Self::OverBudget => f.write_str("over the search budget"),
+            Self::Cancelled => f.write_str("cancelled"),
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How often, and after how long, a fetch that failed transiently is tried
+/// again: on 408/425/429/5xx gateway statuses, timeouts and refused connections.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Tries per URL, the first included; 1 disables retries.
+    pub max_attempts: u32,
+    /// Wait before the second try; doubled for each one after, with jitter.
+    pub base_delay_ms: u64,
+    /// Longest wait between tries. A `Retry-After` asking for more isn't honored
+    /// by waiting: the response is kept as it is.
+    pub max_delay_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay_ms: 500,
+            max_delay_ms: 10_000,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The wait before try `attempt + 1`, or `None` when the server asked for
+    /// longer than `max_delay_ms`. Only the seconds form of `Retry-After` is
+    /// read; a date falls back to the backoff.
+    pub(super) fn delay(&self, attempt: u32, retry_after: Option<&str>) -> Option<Duration> {
+        let max = Duration::from_millis(self.max_delay_ms);
+        if let Some(secs) = retry_after.and_then(|v| v.trim().parse::<u64>().ok()) {
+            let wanted = Duration::from_secs(secs);
+            return (wanted <= max).then_some(wanted);
+        }
+
+        let backoff = self
+            .base_delay_ms
+            .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+            .min(self.max_delay_ms);
+        // Equal jitter: at least half the backoff, so retries still spread out.
+        let half = backoff / 2;
+        Some(Duration::from_millis(half + jitter(half + 1)))
+    }
+}
+
+pub(super) fn is_retryable_status(status: u16) -> bool {
+    matches!(status, 408 | 425 | 429 | 500 | 502 | 503 | 504)
+}
+
+/// Timeouts and failed connects; anything else (TLS, bad URL, ...) would fail again.
+pub(super) fn is_transient(e: &anyhow::Error) -> bool {
+    e.chain()
+        .filter_map(|cause| cause.downcast_ref::<reqwest::Error>())
+        .any(|e| e.is_timeout() || e.is_connect())
+}
+
+/// Not random, just different enough between tasks and tries.
+fn jitter(bound: u64) -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.subsec_nanos() as u64);
+    nanos.wrapping_mul(0x9e37_79b9_7f4a_7c15) % bound
+}
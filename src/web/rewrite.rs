@@ -0,0 +1,116 @@
+use super::{ImagePolicy, LinkPolicy};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use reqwest::Url;
+
+static RE_ANCHOR: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<a\b[^>]*>").unwrap());
+static RE_IMG: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<img\b[^>]*>").unwrap());
+static RE_BASE_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<base\b[^>]*>").unwrap());
+static RE_SRC: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)(\ssrc\s*=\s*)(?:"([^"]*)"|'([^']*)'|([^\s>]+))"#).unwrap());
+static RE_LINK_HREF: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)(\shref\s*=\s*)(?:"([^"]*)"|'([^']*)'|([^\s>]+))"#).unwrap());
+static RE_ALT: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)\salt\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s>]+))"#).unwrap());
+static RE_HREF: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)\bhref\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s>]+))"#).unwrap());
+/// A markdown link or image, with the `!` of an image captured.
+static RE_MD_LINK: Lazy<Regex> = Lazy::new(|| Regex::new(r"(!?)\[([^\]]*)\]\([^)]*\)").unwrap());
+
+/// What relative URLs on the page resolve against: `<base href>` when the
+/// page has one, else the URL it was fetched from.
+pub(super) fn document_base(html: &str, url: &str) -> Option<Url> {
+    let url = Url::parse(url).ok()?;
+    let base_href = RE_BASE_TAG
+        .find(html)
+        .and_then(|tag| attr(&RE_HREF, tag.as_str()))
+        .and_then(|href| url.join(href.trim()).ok());
+    Some(base_href.unwrap_or(url))
+}
+
+/// Apply `policy` to every `<img>` in `html`, before html2md turns them into
+/// `![alt](src)`.
+pub(super) fn rewrite_images(html: &str, base: Option<&Url>, policy: ImagePolicy) -> String {
+    if policy == ImagePolicy::Keep {
+        return html.to_string();
+    }
+    RE_IMG
+        .replace_all(html, |caps: &Captures| {
+            let tag = &caps[0];
+            match policy {
+                ImagePolicy::Keep => tag.to_string(),
+                ImagePolicy::Drop => String::new(),
+                ImagePolicy::AltText => match attr(&RE_ALT, tag).map(str::trim) {
+                    Some(alt) if !alt.is_empty() => format!(" [image: {alt}] "),
+                    _ => String::new(),
+                },
+                ImagePolicy::Absolute => match base {
+                    Some(base) => absolutize(tag, &RE_SRC, base),
+                    None => tag.to_string(),
+                },
+            }
+        })
+        .into_owned()
+}
+
+/// `<a href>`s in `html` resolved against `base`, unless `policy` keeps them
+/// as they are. Stripping happens later, in [`strip_md_links`], so link
+/// density still counts the links.
+pub(super) fn rewrite_links(html: &str, base: Option<&Url>, policy: LinkPolicy) -> String {
+    match (policy, base) {
+        (LinkPolicy::Absolute, Some(base)) => RE_ANCHOR
+            .replace_all(html, |caps: &Captures| {
+                absolutize(&caps[0], &RE_LINK_HREF, base)
+            })
+            .into_owned(),
+        _ => html.to_string(),
+    }
+}
+
+/// `md` with each link replaced by its text; images and fenced code stay.
+pub(super) fn strip_md_links(md: &str) -> String {
+    let mut out = String::with_capacity(md.len());
+    let mut in_fence = false;
+    for line in md.lines() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        if in_fence || line.trim_start().starts_with("```") {
+            out.push_str(line);
+        } else {
+            out.push_str(
+                &RE_MD_LINK.replace_all(line, |caps: &Captures| match &caps[1] {
+                    "!" => caps[0].to_string(),
+                    _ => caps[2].to_string(),
+                }),
+            );
+        }
+        out.push('\n');
+    }
+    out.truncate(out.trim_end().len());
+    out
+}
+
+/// `tag` with the URL `re` captures resolved against `base`; `data:` URIs
+/// are left alone.
+fn absolutize(tag: &str, re: &Regex, base: &Url) -> String {
+    re.replace(tag, |caps: &Captures| {
+        let url = value(caps).trim();
+        match base.join(url) {
+            Ok(abs) if !url.starts_with("data:") => format!("{}\"{abs}\"", &caps[1]),
+            _ => caps[0].to_string(),
+        }
+    })
+    .into_owned()
+}
+
+/// The first value `re` captures from `tag`, quoted or not.
+fn attr<'a>(re: &Regex, tag: &'a str) -> Option<&'a str> {
+    let caps = re.captures(tag)?;
+    caps.iter().skip(1).flatten().last().map(|m| m.as_str())
+}
+
+/// The attribute value in `caps`, from whichever quoting alternative matched.
+fn value<'a>(caps: &Captures<'a>) -> &'a str {
+    (2..=4).find_map(|i| caps.get(i)).map_or("", |m| m.as_str())
+}
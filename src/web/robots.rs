@@ -0,0 +1,206 @@
+use super::{FetchRequest, Fetcher};
+use reqwest::Url;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+use tracing::debug;
+
+/// Product token matched against `User-agent:` lines; anything else falls back to `*`.
+const AGENT: &str = "agent-bob";
+const MAX_ROBOTS_BYTES: usize = 512 * 1024;
+/// Longer delays are clamped so one site can't stall a whole search.
+const MAX_CRAWL_DELAY: Duration = Duration::from_secs(10);
+
+/// robots.txt rules per origin, fetched once per search, plus the
+/// crawl-delay schedule for each origin.
+#[derive(Default)]
+pub(super) struct Robots {
+    rules: Mutex<HashMap<String, Arc<OnceCell<RobotsTxt>>>>,
+    next_slot: Mutex<HashMap<String, Instant>>,
+}
+
+impl Robots {
+    /// False when robots.txt disallows `url`. Otherwise waits out the origin's
+    /// crawl-delay, if it has one, and returns true.
+    pub(super) async fn admit(&self, fetcher: &dyn Fetcher, url: &str) -> bool {
+        let Ok(parsed) = Url::parse(url) else {
+            return true;
+        };
+        let origin = parsed.origin().ascii_serialization();
+
+        let cell = self
+            .rules
+            .lock()
+            .unwrap()
+            .entry(origin.clone())
+            .or_default()
+            .clone();
+        let robots = cell.get_or_init(|| fetch_robots(fetcher, &origin)).await;
+
+        let path = match parsed.query() {
+            Some(q) => format!("{}?{q}", parsed.path()),
+            None => parsed.path().to_string(),
+        };
+        if !robots.allows(&path) {
+            return false;
+        }
+
+        if let Some(delay) = robots.crawl_delay {
+            let slot = {
+                let mut next = self.next_slot.lock().unwrap();
+                let now = Instant::now();
+                let slot = next.get(&origin).map_or(now, |t| (*t).max(now));
+                next.insert(origin, slot + delay.min(MAX_CRAWL_DELAY));
+                slot
+            };
+            tokio::time::sleep_until(slot.into()).await;
+        }
+        true
+    }
+}
+
+/// A missing or unreachable robots.txt means no restrictions.
+async fn fetch_robots(fetcher: &dyn Fetcher, origin: &str) -> RobotsTxt {
+    let url = format!("{origin}/robots.txt");
+    let req = FetchRequest {
+        url: url.clone(),
+        headers: vec![],
+        max_body_bytes: MAX_ROBOTS_BYTES,
+    };
+    match fetcher.fetch(req).await {
+        Ok(resp) if (200..=299).contains(&resp.status) => {
+            RobotsTxt::parse(&String::from_utf8_lossy(&resp.body), AGENT)
+        }
+        Ok(resp) => {
+            debug!(url, status = resp.status, "no robots.txt");
+            RobotsTxt::default()
+        }
+        Err(e) => {
+            debug!(url, error = %format!("{e:#}"), "robots.txt fetch failed");
+            RobotsTxt::default()
+        }
+    }
+}
+
+/// The rules from the group(s) that apply to `agent`.
+#[derive(Debug, Default)]
+struct RobotsTxt {
+    /// `(allow, pattern)`; empty patterns are dropped at parse time.
+    rules: Vec<(bool, String)>,
+    crawl_delay: Option<Duration>,
+}
+
+#[derive(Default)]
+struct Group {
+    agents: Vec<String>,
+    rules: Vec<(bool, String)>,
+    crawl_delay: Option<Duration>,
+}
+
+impl RobotsTxt {
+    /// Groups naming `agent` win over `*` groups; several matching groups merge.
+    fn parse(text: &str, agent: &str) -> Self {
+        let mut groups: Vec<Group> = Vec::new();
+        // Consecutive `User-agent` lines share one group.
+        let mut in_agents = false;
+
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+
+            match key.trim().to_ascii_lowercase().as_str() {
+                "user-agent" => {
+                    if !in_agents {
+                        groups.push(Group::default());
+                        in_agents = true;
+                    }
+                    if let Some(g) = groups.last_mut() {
+                        g.agents.push(value.to_ascii_lowercase());
+                    }
+                }
+                key @ ("allow" | "disallow") => {
+                    in_agents = false;
+                    if let Some(g) = groups.last_mut()
+                        && !value.is_empty()
+                    {
+                        g.rules.push((key == "allow", value.to_string()));
+                    }
+                }
+                "crawl-delay" => {
+                    in_agents = false;
+                    if let Some(g) = groups.last_mut()
+                        && let Ok(secs) = value.parse::<f64>()
+                        && secs.is_finite()
+                        && secs >= 0.0
+                    {
+                        g.crawl_delay = Some(Duration::from_secs_f64(secs));
+                    }
+                }
+                // Sitemap and unknown keys don't end the agent list.
+                _ => {}
+            }
+        }
+
+        let agent = agent.to_ascii_lowercase();
+        let named: Vec<&Group> = groups
+            .iter()
+            .filter(|g| g.agents.contains(&agent))
+            .collect();
+        let chosen = if named.is_empty() {
+            groups
+                .iter()
+                .filter(|g| g.agents.iter().any(|a| a == "*"))
+                .collect()
+        } else {
+            named
+        };
+
+        let mut robots = RobotsTxt::default();
+        for g in chosen {
+            robots.rules.extend(g.rules.iter().cloned());
+            robots.crawl_delay = robots.crawl_delay.max(g.crawl_delay);
+        }
+        robots
+    }
+
+    /// The longest matching pattern decides; `Allow` wins a tie.
+    fn allows(&self, path: &str) -> bool {
+        if path == "/robots.txt" {
+            return true;
+        }
+        self.rules
+            .iter()
+            .filter(|(_, pattern)| pattern_matches(pattern, path))
+            .max_by_key(|(allow, pattern)| (pattern.len(), *allow))
+            .is_none_or(|(allow, _)| *allow)
+    }
+}
+
+/// Prefix match with `*` wildcards and an optional `$` end anchor.
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(p) => (p, true),
+        None => (pattern, false),
+    };
+    let mut parts = pattern.split('*');
+    let Some(mut rest) = path.strip_prefix(parts.next().unwrap_or("")) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    for (i, part) in parts.iter().enumerate() {
+        // With an anchor the last piece has to sit at the very end.
+        if anchored && i + 1 == parts.len() {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    !anchored || rest.is_empty()
+}
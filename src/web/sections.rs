@@ -0,0 +1,75 @@
+use super::{MdPage, RE_MD_LINK, content_body, parse_heading};
+
+/// A heading in the page content, with byte offsets into it.
+struct Heading {
+    level: u8,
+    text: String,
+    /// Start of the heading line.
+    start: usize,
+    /// Start of the line after it.
+    body_start: usize,
+}
+
+impl MdPage {
+    /// Every heading in the content, in page order, with the text under it:
+    /// everything up to the next heading of the same or a higher level, so
+    /// subsections are included. Headings are named as in `outline`, but
+    /// aren't capped at `max_outline_headings`.
+    pub fn sections(&self) -> Vec<(String, String)> {
+        let body = content_body(&self.markdown);
+        let headings = headings(body);
+        headings
+            .iter()
+            .enumerate()
+            .map(|(i, h)| {
+                let end = headings[i + 1..]
+                    .iter()
+                    .find(|next| next.level <= h.level)
+                    .map_or(body.len(), |next| next.start);
+                (h.text.clone(), body[h.body_start..end].trim().to_string())
+            })
+            .collect()
+    }
+
+    /// The text under `heading`, e.g. "Examples" or "Configuration". Matches
+    /// ignore case; an exact match wins over the first heading containing it.
+    pub fn section(&self, heading: &str) -> Option<String> {
+        let want = heading.trim().trim_start_matches('#').trim().to_lowercase();
+        if want.is_empty() {
+            return None;
+        }
+        let mut sections = self.sections();
+        let i = sections
+            .iter()
+            .position(|(h, _)| h.to_lowercase() == want)
+            .or_else(|| {
+                sections
+                    .iter()
+                    .position(|(h, _)| h.to_lowercase().contains(&want))
+            })?;
+        Some(sections.swap_remove(i).1)
+    }
+}
+
+/// Headings outside code fences.
+fn headings(md: &str) -> Vec<Heading> {
+    let mut out = Vec::new();
+    let mut offset = 0usize;
+    let mut in_fence = false;
+
+    for line in md.split_inclusive('\n') {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        } else if !in_fence && let Some((level, text)) = parse_heading(line) {
+            let text = RE_MD_LINK.replace_all(text, "$1");
+            out.push(Heading {
+                level,
+                text: text.trim().to_string(),
+                start: offset,
+                body_start: offset + line.len(),
+            });
+        }
+        offset += line.len();
+    }
+    out
+}
@@ -0,0 +1,271 @@
+use super::{
+    CrawlJob, CrawlStats, FetchRequest, Fetcher, LlmCleanConfig, MdPage, SearchHooks, crawl_all,
+    crawl_ctx, finish_pages,
+};
+use anyhow::{Context, Result, bail};
+use flate2::read::GzDecoder;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use reqwest::Url;
+use std::collections::HashSet;
+use std::io::Read;
+use tracing::{debug, warn};
+
+static RE_LOC: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)<loc>\s*(?:<!\[CDATA\[)?\s*(.*?)\s*(?:\]\]>)?\s*</loc>").unwrap()
+});
+// The sitemap protocol's own limit on an uncompressed file.
+const MAX_SITEMAP_BYTES: usize = 50 * 1024 * 1024;
+
+static RE_SITEMAP_INDEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)<sitemapindex[\s>]").unwrap());
+
+/// Crawl up to `limit` pages listed in a site's sitemap, through the same
+/// fetch/clean pipeline as search.
+///
+/// `base_url` is a site root, a path prefix that listed URLs must fall under
+/// (`https://docs.rs/tokio`), or the sitemap URL itself. `filter` is a regex,
+/// or a plain substring when it doesn't compile as one. Sitemap-index files
+/// are followed one level; gzipped sitemaps are decompressed.
+pub async fn crawl_sitemap(
+    base_url: &str,
+    filter: Option<&str>,
+    cfg: &LlmCleanConfig,
+    limit: usize,
+) -> Result<Vec<MdPage>> {
+    let hooks = SearchHooks::default();
+    let ctx = crawl_ctx(cfg, &hooks, None)?;
+
+    let base = Url::parse(base_url).with_context(|| format!("invalid base url: {base_url}"))?;
+    let matcher =
+        filter.map(|f| Regex::new(f).unwrap_or_else(|_| Regex::new(&regex::escape(f)).unwrap()));
+    let wanted =
+        |url: &str| in_scope(&base, url) && matcher.as_ref().is_none_or(|re| re.is_match(url));
+
+    let urls = sitemap_urls(ctx.fetcher.as_ref(), &base, limit, wanted).await?;
+
+    // Pages carry the filter (or the site) as their query, for scoring and the header.
+    let query = filter.unwrap_or(base_url).to_string();
+    let mut seen: HashSet<String> = HashSet::new();
+    let jobs = urls
+        .into_iter()
+        .filter(|url| seen.insert(url.clone()))
+        .enumerate()
+        .map(|(i, url)| CrawlJob {
+            rank: vec![i],
+            query: query.clone(),
+            url,
+            title: None,
+            snippet: None,
+            via: None,
+            depth: 0,
+        })
+        .collect();
+
+    let mut stats = CrawlStats::default();
+    let (pages, _) = crawl_all(&ctx, jobs, &mut seen, &mut stats, None).await;
+    Ok(finish_pages(
+        pages,
+        &[query],
+        cfg,
+        &hooks,
+        &mut stats,
+        &mut Vec::new(),
+    ))
+}
+
+/// Crawl a whole site: the pages its sitemap lists under `url`, or, when it
+/// has none, the pages reached by following links from `url` up to `depth`
+/// hops without leaving its host and path. `cfg.max_pages` caps either way.
+pub async fn crawl_site(url: &str, depth: u8, cfg: &LlmCleanConfig) -> Result<Vec<MdPage>> {
+    match crawl_sitemap(url, None, cfg, cfg.max_pages).await {
+        Ok(pages) if !pages.is_empty() => return Ok(pages),
+        Ok(_) => debug!(url, "sitemap listed no usable pages, following links"),
+        Err(e) => debug!(url, error = %format!("{e:#}"), "no sitemap, following links"),
+    }
+
+    let site = Url::parse(url).with_context(|| format!("invalid site url: {url}"))?;
+    let mut cfg = cfg.clone();
+    cfg.follow_links_depth = depth;
+    let hooks = SearchHooks::default();
+    let ctx = crawl_ctx(&cfg, &hooks, Some(site))?;
+
+    let query = url.to_string();
+    let mut seen = HashSet::from([query.clone()]);
+    let job = CrawlJob {
+        rank: vec![0],
+        query: query.clone(),
+        url: query.clone(),
+        title: None,
+        snippet: None,
+        via: None,
+        depth: 0,
+    };
+    let mut stats = CrawlStats::default();
+    let (pages, _) = crawl_all(&ctx, vec![job], &mut seen, &mut stats, None).await;
+    Ok(finish_pages(
+        pages,
+        &[query],
+        &cfg,
+        &hooks,
+        &mut stats,
+        &mut Vec::new(),
+    ))
+}
+
+/// The first `limit` sitemap URLs accepted by `wanted`, in sitemap order.
+async fn sitemap_urls(
+    fetcher: &dyn Fetcher,
+    base: &Url,
+    limit: usize,
+    wanted: impl Fn(&str) -> bool,
+) -> Result<Vec<String>> {
+    let path = base.path();
+    let candidates: Vec<Url> = if path.ends_with(".xml") || path.ends_with(".xml.gz") {
+        vec![base.clone()]
+    } else {
+        ["/sitemap.xml", "/sitemap.xml.gz"]
+            .iter()
+            .filter_map(|p| base.join(p).ok())
+            .collect()
+    };
+
+    let mut root = None;
+    for url in &candidates {
+        if let Some(xml) = fetch_sitemap(fetcher, url.as_str()).await? {
+            root = Some(xml);
+            break;
+        }
+    }
+    let Some(root) = root else {
+        let tried: Vec<&str> = candidates.iter().map(Url::as_str).collect();
+        bail!("no sitemap found (tried {})", tried.join(", "));
+    };
+
+    let mut urls: Vec<String> = Vec::new();
+    if RE_SITEMAP_INDEX.is_match(&root) {
+        for child in locs(&root) {
+            if urls.len() >= limit {
+                break;
+            }
+            match fetch_sitemap(fetcher, &child).await {
+                // Indexes are only followed one level deep.
+                Ok(Some(xml)) if !RE_SITEMAP_INDEX.is_match(&xml) => {
+                    urls.extend(locs(&xml).into_iter().filter(|u| wanted(u)));
+                }
+                Ok(_) => {}
+                Err(e) => warn!(sitemap = %child, error = %format!("{e:#}"), "skipping sitemap"),
+            }
+        }
+    } else {
+        urls.extend(locs(&root).into_iter().filter(|u| wanted(u)));
+    }
+
+    urls.truncate(limit);
+    Ok(urls)
+}
+
+/// Fetch one sitemap as text. `None` for 404/410, so callers can try the next candidate.
+async fn fetch_sitemap(fetcher: &dyn Fetcher, url: &str) -> Result<Option<String>> {
+    let resp = fetcher
+        .fetch(FetchRequest {
+            url: url.to_string(),
+            headers: vec![],
+            max_body_bytes: MAX_SITEMAP_BYTES,
+        })
+        .await
+        .with_context(|| format!("request failed: {url}"))?;
+
+    if resp.status == 404 || resp.status == 410 {
+        return Ok(None);
+    }
+    if !(200..=299).contains(&resp.status) {
+        bail!("sitemap {url} returned {}", resp.status);
+    }
+    let bytes = resp.body;
+
+    // `.xml.gz` files are served as gzip payloads, not with Content-Encoding.
+    let bytes = if bytes.starts_with(&[0x1f, 0x8b]) {
+        gunzip(&bytes, MAX_SITEMAP_BYTES).with_context(|| format!("bad gzip sitemap {url}"))?
+    } else {
+        bytes
+    };
+
+    Ok(Some(String::from_utf8_lossy(&bytes).into_owned()))
+}
+
+/// Inflate `bytes`, failing rather than growing past `limit` on a gzip bomb.
+fn gunzip(bytes: &[u8], limit: usize) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    GzDecoder::new(bytes)
+        .take(limit as u64 + 1)
+        .read_to_end(&mut out)?;
+    if out.len() > limit {
+        bail!("inflates past {limit} bytes");
+    }
+    Ok(out)
+}
+
+fn locs(xml: &str) -> Vec<String> {
+    RE_LOC
+        .captures_iter(xml)
+        .map(|c| unescape_xml(&c[1]))
+        .filter(|u| !u.is_empty())
+        .collect()
+}
+
+fn unescape_xml(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Same host (ignoring `www.`), and under `base`'s path when it has one.
+pub(super) fn in_scope(base: &Url, url: &str) -> bool {
+    let Ok(url) = Url::parse(url) else {
+        return false;
+    };
+    let host = |u: &Url| {
+        u.host_str()
+            .map(|h| h.trim_start_matches("www.").to_string())
+    };
+    if host(&url) != host(base) {
+        return false;
+    }
+
+    let prefix = base.path().trim_end_matches('/');
+    if prefix.is_empty() || prefix.ends_with(".xml") || prefix.ends_with(".xml.gz") {
+        return true;
+    }
+    url.path() == prefix || url.path().starts_with(&format!("{prefix}/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut enc = GzEncoder::new(Vec::new(), Compression::best());
+        enc.write_all(data).unwrap();
+        enc.finish().unwrap()
+    }
+
+    #[test]
+    fn gunzips_within_the_limit() {
+        let xml = b"<urlset><url><loc>https://example.com/</loc></url></urlset>";
+        assert_eq!(gunzip(&gzip(xml), xml.len()).unwrap(), xml);
+    }
+
+    #[test]
+    fn refuses_gzip_bombs() {
+        // A megabyte of zeros compresses to about a kilobyte.
+        let bomb = gzip(&vec![0; 1024 * 1024]);
+        assert!(bomb.len() < 4096);
+        let err = gunzip(&bomb, 64 * 1024).unwrap_err();
+        assert!(err.to_string().contains("inflates past"), "{err}");
+    }
+}
@@ -0,0 +1,98 @@
+use super::{LlmCleanConfig, domains, rewrite};
+use reqwest::Url;
+use reqwest::header::HeaderName;
+use scraper::Html;
+use tracing::debug;
+
+mod github;
+mod rustdoc;
+mod stackexchange;
+mod wikipedia;
+
+/// A site the generic extraction handles badly, and how to do better.
+struct Site {
+    name: &'static str,
+    /// Patterns as in `allowed_domains`.
+    hosts: &'static [&'static str],
+    /// Text the HTML must contain, a cheap test before parsing; empty for
+    /// none.
+    marker: &'static str,
+    /// Markdown for the page, or `None` to leave it to the generic path
+    /// (a page of the site that isn't the kind the extractor knows).
+    extract: fn(&Html, Option<&Url>, &LlmCleanConfig) -> Option<String>,
+}
+
+/// Tried in order; the first that matches the host and returns markdown wins.
+const SITES: &[Site] = &[
+    Site {
+        name: "stackexchange",
+        hosts: stackexchange::HOSTS,
+        marker: "",
+        extract: stackexchange::extract,
+    },
+    Site {
+        name: "rustdoc",
+        hosts: rustdoc::HOSTS,
+        marker: rustdoc::MARKER,
+        extract: rustdoc::extract,
+    },
+];
+
+/// Markdown from the site extractor for `url`, when there is one and it
+/// recognizes the page.
+pub(super) fn extract(html: &str, url: &str, cfg: &LlmCleanConfig) -> Option<String> {
+    let mut sites = SITES
+        .iter()
+        .filter(|site| site.hosts.iter().any(|p| domains::host_matches(url, p)))
+        .filter(|site| html.contains(site.marker))
+        .peekable();
+    sites.peek()?;
+
+    let doc = Html::parse_document(html);
+    let base = rewrite::document_base(html, url);
+    sites.find_map(|site| {
+        let md = (site.extract)(&doc, base.as_ref(), cfg)?;
+        debug!(site = site.name, "used site extractor");
+        Some(md)
+    })
+}
+
+/// One request to a site's API.
+pub(super) struct ApiCall {
+    pub(super) url: String,
+    pub(super) headers: Vec<(HeaderName, String)>,
+}
+
+/// A page its site serves cleaner through an API than as HTML: the calls
+/// to make, and how to turn their answers into markdown.
+pub(super) struct ApiPage(Api);
+
+enum Api {
+    GitHub(github::Page),
+    Wikipedia(wikipedia::Page),
+}
+
+impl ApiPage {
+    pub(super) fn for_url(url: &str) -> Option<Self> {
+        github::Page::parse(url)
+            .map(Api::GitHub)
+            .or_else(|| wikipedia::Page::parse(url).map(Api::Wikipedia))
+            .map(Self)
+    }
+
+    pub(super) fn calls(&self, cfg: &LlmCleanConfig) -> Vec<ApiCall> {
+        match &self.0 {
+            Api::GitHub(page) => page.calls(cfg),
+            Api::Wikipedia(page) => page.calls(),
+        }
+    }
+
+    /// Title and markdown from the answers to `calls`, in order; `None` when
+    /// they aren't what the site's API returns.
+    pub(super) fn render(&self, bodies: &[Vec<u8>]) -> Option<(Option<String>, String)> {
+        match &self.0 {
+            Api::GitHub(page) => page.render(bodies),
+            Api::Wikipedia(page) => page.render(bodies),
+        }
+    }
+}
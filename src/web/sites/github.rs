@@ -0,0 +1,177 @@
+use super::ApiCall;
+use crate::web::LlmCleanConfig;
+use reqwest::Url;
+use reqwest::header::{ACCEPT, AUTHORIZATION};
+use serde::Deserialize;
+
+const API: &str = "https://api.github.com";
+
+/// First path segments that are GitHub's own pages rather than owners.
+const RESERVED: &[&str] = &[
+    "about",
+    "apps",
+    "collections",
+    "enterprise",
+    "explore",
+    "features",
+    "login",
+    "marketplace",
+    "notifications",
+    "orgs",
+    "organizations",
+    "pricing",
+    "search",
+    "settings",
+    "sponsors",
+    "topics",
+    "trending",
+];
+
+/// Comments fetched per issue or pull request, oldest first.
+const MAX_COMMENTS: usize = 30;
+
+/// A repository's front page or one of its issues or pull requests.
+pub(super) enum Page {
+    Readme {
+        owner: String,
+        repo: String,
+    },
+    Issue {
+        owner: String,
+        repo: String,
+        number: u64,
+    },
+}
+
+#[derive(Deserialize)]
+struct Issue {
+    title: String,
+    body: Option<String>,
+    state: String,
+    user: User,
+    created_at: String,
+    /// Present on pull requests, which the issues API serves too.
+    pull_request: Option<serde_json::Value>,
+    comments: u64,
+}
+
+#[derive(Deserialize)]
+struct Comment {
+    user: User,
+    body: Option<String>,
+    created_at: String,
+}
+
+#[derive(Deserialize)]
+struct User {
+    login: String,
+}
+
+impl Page {
+    /// `github.com/<owner>/<repo>` and `.../issues/<n>` or `.../pull/<n>`;
+    /// other GitHub pages (code, wikis, profiles) keep the HTML path.
+    pub(super) fn parse(url: &str) -> Option<Self> {
+        let url = Url::parse(url).ok()?;
+        if !matches!(url.host_str()?, "github.com" | "www.github.com") {
+            return None;
+        }
+        let segments: Vec<&str> = url.path_segments()?.filter(|s| !s.is_empty()).collect();
+        let (owner, repo) = match segments.as_slice() {
+            [owner, repo, ..] if !RESERVED.contains(owner) => {
+                (owner.to_string(), repo.trim_end_matches(".git").to_string())
+            }
+            _ => return None,
+        };
+        match segments[2..] {
+            [] => Some(Self::Readme { owner, repo }),
+            ["issues" | "pull", number, ..] => Some(Self::Issue {
+                owner,
+                repo,
+                number: number.parse().ok()?,
+            }),
+            _ => None,
+        }
+    }
+
+    pub(super) fn calls(&self, cfg: &LlmCleanConfig) -> Vec<ApiCall> {
+        let call = |url: String, accept: &str| {
+            let mut headers = vec![(ACCEPT, accept.to_string())];
+            if let Some(token) = &cfg.github_token {
+                headers.push((AUTHORIZATION, format!("Bearer {token}")));
+            }
+            ApiCall { url, headers }
+        };
+        match self {
+            Self::Readme { owner, repo } => vec![call(
+                format!("{API}/repos/{owner}/{repo}/readme"),
+                "application/vnd.github.raw",
+            )],
+            Self::Issue {
+                owner,
+                repo,
+                number,
+            } => vec![
+                call(
+                    format!("{API}/repos/{owner}/{repo}/issues/{number}"),
+                    "application/vnd.github+json",
+                ),
+                call(
+                    format!(
+                        "{API}/repos/{owner}/{repo}/issues/{number}/comments?per_page={MAX_COMMENTS}"
+                    ),
+                    "application/vnd.github+json",
+                ),
+            ],
+        }
+    }
+
+    pub(super) fn render(&self, bodies: &[Vec<u8>]) -> Option<(Option<String>, String)> {
+        match self {
+            Self::Readme { owner, repo } => {
+                let readme = String::from_utf8_lossy(bodies.first()?);
+                Some((Some(format!("{owner}/{repo}")), readme.trim().to_string()))
+            }
+            Self::Issue {
+                owner,
+                repo,
+                number,
+            } => {
+                let issue: Issue = serde_json::from_slice(bodies.first()?).ok()?;
+                let comments: Vec<Comment> = bodies
+                    .get(1)
+                    .and_then(|body| serde_json::from_slice(body).ok())
+                    .unwrap_or_default();
+
+                let kind = match issue.pull_request {
+                    Some(_) => "Pull request",
+                    None => "Issue",
+                };
+                let mut md = format!(
+                    "# {} (#{number})\n\n{kind} in {owner}/{repo}, {}, opened by @{} on {}\n\n{}\n",
+                    issue.title,
+                    issue.state,
+                    issue.user.login,
+                    date(&issue.created_at),
+                    issue.body.as_deref().unwrap_or("").trim(),
+                );
+                if !comments.is_empty() {
+                    md.push_str(&format!("\n## Comments ({})\n", issue.comments));
+                    for c in &comments {
+                        md.push_str(&format!(
+                            "\n### @{} on {}\n\n{}\n",
+                            c.user.login,
+                            date(&c.created_at),
+                            c.body.as_deref().unwrap_or("").trim()
+                        ));
+                    }
+                }
+                Some((Some(issue.title), md))
+            }
+        }
+    }
+}
+
+/// The date part of an ISO 8601 timestamp.
+fn date(timestamp: &str) -> &str {
+    timestamp.split('T').next().unwrap_or(timestamp)
+}
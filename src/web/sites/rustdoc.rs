@@ -0,0 +1,118 @@
+use crate::web::{LlmCleanConfig, content_to_markdown};
+use once_cell::sync::Lazy;
+use reqwest::Url;
+use scraper::node::Node;
+use scraper::{ElementRef, Html, Selector};
+
+/// Rustdoc output lives anywhere (docs.rs, doc.rust-lang.org, a project's
+/// own site), so the markup decides, not the host.
+pub(super) const HOSTS: &[&str] = &["*"];
+
+/// In the HTML of every rustdoc page; cheaper to look for than to parse.
+pub(super) const MARKER: &str = "rustdoc";
+
+static SEL_RUSTDOC: Lazy<Selector> = Lazy::new(|| Selector::parse(".rustdoc").unwrap());
+static SEL_MAIN: Lazy<Selector> = Lazy::new(|| Selector::parse("#main-content").unwrap());
+/// Signatures of impls and their items, which rustdoc renders as headings.
+static SEL_CODE_HEADER: Lazy<Selector> = Lazy::new(|| Selector::parse(".code-header").unwrap());
+/// Navigation and chrome inside the content, plus the impls every type has
+/// (auto traits, blankets) and the bodies of trait impls, whose docs are
+/// the trait's.
+static SEL_NOISE: Lazy<Selector> = Lazy::new(|| {
+    Selector::parse(
+        "a.src, a.srclink, .rightside, .out-of-band, a.anchor, a.tooltip, button, \
+         rustdoc-toolbar, nav, .sidebar, .notable-traits, .hideme, \
+         #synthetic-implementations, #synthetic-implementations-list, \
+         #blanket-implementations, #blanket-implementations-list, \
+         #trait-implementations-list .impl-items",
+    )
+    .unwrap()
+});
+
+/// Elements written without a closing tag.
+const VOID: &[&str] = &["br", "hr", "img", "wbr"];
+
+/// The item's declaration, docs and examples, then its methods and impls
+/// with each signature as a code block; source links, anchors, version
+/// badges and the sidebar go.
+pub(super) fn extract(doc: &Html, base: Option<&Url>, cfg: &LlmCleanConfig) -> Option<String> {
+    doc.select(&SEL_RUSTDOC).next()?;
+    let main = doc.select(&SEL_MAIN).next()?;
+
+    let mut html = String::new();
+    write_children(main, &mut html);
+    let md = content_to_markdown(&html, base, cfg);
+    (!md.trim().is_empty()).then_some(md)
+}
+
+fn write_children(el: ElementRef<'_>, out: &mut String) {
+    for node in el.children() {
+        match node.value() {
+            Node::Element(_) => {
+                if let Some(child) = ElementRef::wrap(node) {
+                    write_element(child, out);
+                }
+            }
+            Node::Text(text) => out.push_str(&escape(text)),
+            _ => {}
+        }
+    }
+}
+
+fn write_element(el: ElementRef<'_>, out: &mut String) {
+    if SEL_NOISE.matches(&el) {
+        return;
+    }
+    if SEL_CODE_HEADER.matches(&el) {
+        let mut sig = String::new();
+        collect_text(el, &mut sig);
+        out.push_str("<pre class=\"rust\"><code>");
+        out.push_str(&escape(sig.trim()));
+        out.push_str("</code></pre>");
+        return;
+    }
+
+    // Collapsible blocks: the summary holds the signature, so keep both open.
+    let name = match el.value().name() {
+        "details" | "summary" => "div",
+        name => name,
+    };
+    out.push('<');
+    out.push_str(name);
+    for (attr, value) in el.value().attrs() {
+        out.push_str(&format!(
+            " {attr}=\"{}\"",
+            escape(value).replace('"', "&quot;")
+        ));
+    }
+    out.push('>');
+    if VOID.contains(&name) {
+        return;
+    }
+    write_children(el, out);
+    out.push_str(&format!("</{name}>"));
+}
+
+/// The text of `el` without the parts `SEL_NOISE` drops (a `source` link
+/// inside a signature heading).
+fn collect_text(el: ElementRef<'_>, out: &mut String) {
+    for node in el.children() {
+        match node.value() {
+            Node::Element(_) => {
+                if let Some(child) = ElementRef::wrap(node)
+                    && !SEL_NOISE.matches(&child)
+                {
+                    collect_text(child, out);
+                }
+            }
+            Node::Text(text) => out.push_str(text),
+            _ => {}
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
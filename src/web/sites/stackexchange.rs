@@ -0,0 +1,97 @@
+use crate::web::{LlmCleanConfig, content_to_markdown};
+use once_cell::sync::Lazy;
+use reqwest::Url;
+use scraper::{ElementRef, Html, Selector};
+
+/// Stack Overflow and the rest of the Stack Exchange network.
+pub(super) const HOSTS: &[&str] = &[
+    "stackoverflow.com",
+    "*.stackexchange.com",
+    "superuser.com",
+    "serverfault.com",
+    "askubuntu.com",
+    "mathoverflow.net",
+    "stackapps.com",
+];
+
+/// Answers kept besides the accepted one, highest score first.
+const TOP_ANSWERS: usize = 3;
+
+static SEL_TITLE: Lazy<Selector> = Lazy::new(|| Selector::parse("#question-header h1").unwrap());
+static SEL_QUESTION: Lazy<Selector> = Lazy::new(|| Selector::parse("#question").unwrap());
+static SEL_ANSWER: Lazy<Selector> = Lazy::new(|| Selector::parse("#answers .answer").unwrap());
+static SEL_BODY: Lazy<Selector> = Lazy::new(|| Selector::parse(".js-post-body").unwrap());
+static SEL_VOTES: Lazy<Selector> = Lazy::new(|| Selector::parse(".js-vote-count").unwrap());
+static SEL_TAG: Lazy<Selector> = Lazy::new(|| Selector::parse(".post-taglist .post-tag").unwrap());
+
+struct Post<'a> {
+    score: i64,
+    accepted: bool,
+    body: ElementRef<'a>,
+}
+
+/// The question, then the accepted answer and the top-scored others, each
+/// under a heading with its score. Comments, sidebars and "Related" go.
+pub(super) fn extract(doc: &Html, base: Option<&Url>, cfg: &LlmCleanConfig) -> Option<String> {
+    let title = text(doc.select(&SEL_TITLE).next()?);
+    let question = post(doc.select(&SEL_QUESTION).next()?)?;
+
+    let mut answers: Vec<Post> = doc.select(&SEL_ANSWER).filter_map(post).collect();
+    // Stable: ties keep the page's order.
+    answers.sort_by_key(|a| (!a.accepted, -a.score));
+    let accepted = answers.first().is_some_and(|a| a.accepted) as usize;
+    answers.truncate(accepted + TOP_ANSWERS);
+
+    let to_md = |post: &Post| content_to_markdown(&post.body.inner_html(), base, cfg);
+    let mut md = format!("# {title}\n\n");
+    let tags: Vec<String> = doc.select(&SEL_TAG).map(text).collect();
+    if !tags.is_empty() {
+        md.push_str(&format!("Tags: {}\n\n", tags.join(", ")));
+    }
+    md.push_str(&format!(
+        "## Question (score {})\n\n{}\n",
+        question.score,
+        to_md(&question).trim()
+    ));
+    for answer in &answers {
+        let label = if answer.accepted {
+            "Accepted answer"
+        } else {
+            "Answer"
+        };
+        md.push_str(&format!(
+            "\n## {label} (score {})\n\n{}\n",
+            answer.score,
+            to_md(answer).trim()
+        ));
+    }
+    Some(md)
+}
+
+fn post(el: ElementRef<'_>) -> Option<Post<'_>> {
+    let body = el.select(&SEL_BODY).next()?;
+    let score = el
+        .value()
+        .attr("data-score")
+        .or_else(|| el.select(&SEL_VOTES).next()?.value().attr("data-value"))
+        .and_then(|s| s.trim().parse().ok())
+        .unwrap_or(0);
+    let accepted = el
+        .value()
+        .classes()
+        .any(|c| c == "accepted-answer" || c == "js-accepted-answer");
+    Some(Post {
+        score,
+        accepted,
+        body,
+    })
+}
+
+fn text(el: ElementRef<'_>) -> String {
+    el.text()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,189 @@
+use super::ApiCall;
+use reqwest::Url;
+use reqwest::header::ACCEPT;
+use serde::Deserialize;
+
+/// Namespaces whose pages aren't articles (`/wiki/Talk:Rust`).
+const NAMESPACES: &[&str] = &[
+    "Category",
+    "File",
+    "Help",
+    "Portal",
+    "Special",
+    "Talk",
+    "Template",
+    "User",
+    "Wikipedia",
+];
+
+/// Sections of citations and links rather than content, dropped with
+/// their subsections.
+const APPARATUS: &[&str] = &[
+    "Bibliography",
+    "Citations",
+    "External links",
+    "Further reading",
+    "Notes",
+    "References",
+    "See also",
+    "Sources",
+];
+
+/// An article on one of the Wikipedias.
+pub(super) struct Page {
+    /// `en.wikipedia.org`, mobile hosts mapped to their desktop ones.
+    host: String,
+    title: String,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    query: Query,
+}
+
+#[derive(Deserialize)]
+struct Query {
+    pages: Vec<Article>,
+}
+
+#[derive(Deserialize)]
+struct Article {
+    title: String,
+    #[serde(default)]
+    extract: String,
+    #[serde(default)]
+    missing: bool,
+}
+
+impl Page {
+    /// `<lang>.wikipedia.org/wiki/<title>` and its mobile twin.
+    pub(super) fn parse(url: &str) -> Option<Self> {
+        let url = Url::parse(url).ok()?;
+        let host = url.host_str()?.to_ascii_lowercase();
+        let lang = host.strip_suffix(".wikipedia.org")?;
+        let lang = lang.strip_suffix(".m").unwrap_or(lang);
+        if lang.is_empty() || lang.contains('.') {
+            return None;
+        }
+
+        let title = percent_decode(url.path().strip_prefix("/wiki/")?).replace('_', " ");
+        let namespaced = title
+            .split_once(':')
+            .is_some_and(|(ns, _)| NAMESPACES.iter().any(|n| n.eq_ignore_ascii_case(ns)));
+        if title.trim().is_empty() || namespaced {
+            return None;
+        }
+        Some(Self {
+            host: format!("{lang}.wikipedia.org"),
+            title,
+        })
+    }
+
+    /// The plain-text extract, with `== Heading ==` section markers.
+    pub(super) fn calls(&self) -> Vec<ApiCall> {
+        let url = Url::parse_with_params(
+            &format!("https://{}/w/api.php", self.host),
+            [
+                ("action", "query"),
+                ("prop", "extracts"),
+                ("explaintext", "1"),
+                ("exsectionformat", "wiki"),
+                ("redirects", "1"),
+                ("format", "json"),
+                ("formatversion", "2"),
+                ("titles", self.title.as_str()),
+            ],
+        )
+        .expect("api url is valid");
+        vec![ApiCall {
+            url: url.into(),
+            headers: vec![(ACCEPT, "application/json".to_string())],
+        }]
+    }
+
+    pub(super) fn render(&self, bodies: &[Vec<u8>]) -> Option<(Option<String>, String)> {
+        let response: Response = serde_json::from_slice(bodies.first()?).ok()?;
+        let article = response.query.pages.into_iter().next()?;
+        if article.missing || article.extract.trim().is_empty() {
+            return None;
+        }
+        let md = format!("# {}\n\n{}\n", article.title, sections(&article.extract));
+        Some((Some(article.title), md))
+    }
+}
+
+/// `extract` as markdown: `== Heading ==` lines become `## Heading`, one
+/// blank line between blocks, and the `APPARATUS` sections go.
+fn sections(extract: &str) -> String {
+    let mut blocks: Vec<String> = Vec::new();
+    // Level of the dropped section being skipped.
+    let mut skipping: Option<usize> = None;
+
+    for line in extract.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        if let Some((level, heading)) = heading(line) {
+            if skipping.is_some_and(|skip| level > skip) {
+                continue;
+            }
+            skipping = APPARATUS
+                .iter()
+                .any(|a| a.eq_ignore_ascii_case(heading))
+                .then_some(level);
+            if skipping.is_none() {
+                blocks.push(format!("{} {heading}", "#".repeat(level.min(6))));
+            }
+        } else if skipping.is_none() {
+            blocks.push(line.to_string());
+        }
+    }
+
+    // Headings left with nothing under them.
+    let mut out: Vec<String> = Vec::new();
+    for block in blocks {
+        let level = block.chars().take_while(|&c| c == '#').count();
+        while level > 0
+            && let Some(last) = out.last()
+        {
+            let last_level = last.chars().take_while(|&c| c == '#').count();
+            if last_level == 0 || last_level < level {
+                break;
+            }
+            out.pop();
+        }
+        out.push(block);
+    }
+    while out.last().is_some_and(|b| b.starts_with('#')) {
+        out.pop();
+    }
+    out.join("\n\n")
+}
+
+/// Level and text of a `== Heading ==` line; `==` is level 2, as on the
+/// page.
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|&c| c == '=').count();
+    if level < 2 || !line.ends_with(&"=".repeat(level)) || line.len() <= 2 * level {
+        return None;
+    }
+    let text = line[level..line.len() - level].trim();
+    (!text.is_empty()).then_some((level, text))
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(byte) = s
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            out.push(byte);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
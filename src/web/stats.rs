@@ -0,0 +1,116 @@
+use super::DropReason;
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::info;
+
+/// Counters and timings for one search call, for tuning the config knobs.
+#[derive(Debug, Clone, Default)]
+pub struct CrawlStats {
+    /// Search hits per query, in query order (before URL dedup).
+    pub hits_per_query: Vec<(String, usize)>,
+    /// Pages returned per query, in query order.
+    pub pages_per_query: Vec<(String, usize)>,
+    /// Hits and followed links skipped because their URL was already queued.
+    pub urls_deduped: usize,
+    /// Requests that got a response.
+    pub fetched: usize,
+    /// Requests that failed outright (connect, timeout, body read).
+    pub fetch_errors: usize,
+    /// Pages served from the on-disk cache without a request.
+    pub cache_hits: usize,
+    /// Expired cache entries the server confirmed unchanged (304), so only
+    /// headers were transferred. Also counted in `fetched` and `cache_hits`.
+    pub revalidated: usize,
+    /// Drops by reason; `CanonicalDuplicate` includes fetches skipped up front.
+    pub dropped: HashMap<DropReason, usize>,
+    pub bytes_downloaded: u64,
+    /// Wall time spent in the search provider.
+    pub search_time: Duration,
+    /// Wall time of the fetch phase (includes per-page cleaning in the tasks).
+    pub fetch_time: Duration,
+    /// Summed extraction/cleaning time across pages, plus the cross-page passes.
+    pub clean_time: Duration,
+    pub total_time: Duration,
+    /// Time to fetch a page, body included.
+    pub fetch_latency_p50: Option<Duration>,
+    pub fetch_latency_p95: Option<Duration>,
+}
+
+impl CrawlStats {
+    pub fn dropped_for(&self, reason: DropReason) -> usize {
+        self.dropped.get(&reason).copied().unwrap_or(0)
+    }
+
+    pub(super) fn record_drop(&mut self, reason: DropReason) {
+        *self.dropped.entry(reason).or_default() += 1;
+    }
+
+    pub(super) fn record_fetch(&mut self, m: &FetchMetrics) {
+        if m.latency.is_some() {
+            self.fetched += 1;
+        }
+        if m.cache_hit {
+            self.cache_hits += 1;
+        }
+        if m.revalidated {
+            self.revalidated += 1;
+        }
+        self.bytes_downloaded += m.bytes as u64;
+        self.clean_time += m.clean_time;
+    }
+
+    /// One event per call, with the drops broken down by filter.
+    pub(super) fn log_summary(&self, pages: usize) {
+        let mut dropped: Vec<_> = self.dropped.iter().collect();
+        dropped.sort_by(|a, b| b.1.cmp(a.1));
+        let dropped = dropped
+            .iter()
+            .map(|(reason, n)| format!("{reason}={n}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let ms = |d: Option<Duration>| d.map(|d| d.as_millis() as u64);
+        info!(
+            pages,
+            fetched = self.fetched,
+            fetch_errors = self.fetch_errors,
+            cache_hits = self.cache_hits,
+            revalidated = self.revalidated,
+            urls_deduped = self.urls_deduped,
+            bytes = self.bytes_downloaded,
+            dropped = %dropped,
+            latency_p50_ms = ms(self.fetch_latency_p50),
+            latency_p95_ms = ms(self.fetch_latency_p95),
+            search_ms = self.search_time.as_millis() as u64,
+            fetch_ms = self.fetch_time.as_millis() as u64,
+            total_ms = self.total_time.as_millis() as u64,
+            "search finished"
+        );
+    }
+
+    pub(super) fn set_latencies(&mut self, mut latencies: Vec<Duration>) {
+        latencies.sort();
+        self.fetch_latency_p50 = percentile(&latencies, 0.50);
+        self.fetch_latency_p95 = percentile(&latencies, 0.95);
+    }
+}
+
+/// Per-URL measurements, filled in by the crawl task and aggregated at join time.
+#[derive(Debug, Default)]
+pub(super) struct FetchMetrics {
+    pub(super) latency: Option<Duration>,
+    pub(super) bytes: usize,
+    pub(super) clean_time: Duration,
+    pub(super) cache_hit: bool,
+    /// The cache hit took a 304 round trip.
+    pub(super) revalidated: bool,
+    /// The status the drop checks saw, for reporting `NonSuccessStatus` drops.
+    pub(super) status: Option<u16>,
+}
+
+fn percentile(sorted: &[Duration], p: f64) -> Option<Duration> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let idx = (p * (sorted.len() - 1) as f64).round() as usize;
+    sorted.get(idx).copied()
+}
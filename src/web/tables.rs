@@ -0,0 +1,224 @@
+use html2md::parse_html;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use scraper::{ElementRef, Html, Selector};
+
+static RE_TABLE_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)<(/?)table\b[^>]*>").unwrap());
+static RE_PLACEHOLDER: Lazy<Regex> = Lazy::new(|| Regex::new(r"AGENTBOBTABLE(\d+)").unwrap());
+static RE_TEXT_ALIGN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)text-align\s*:\s*(left|center|right)").unwrap());
+static SEL_TABLE: Lazy<Selector> = Lazy::new(|| Selector::parse("table").unwrap());
+static SEL_ROW: Lazy<Selector> = Lazy::new(|| Selector::parse("tr").unwrap());
+static SEL_CAPTION: Lazy<Selector> = Lazy::new(|| Selector::parse("caption").unwrap());
+
+/// Data tables rendered as GitHub-flavored markdown, which html2md mangles.
+/// Each is replaced in the HTML by a placeholder paragraph; put them back
+/// into the converted markdown with [`Tables::restore`].
+#[derive(Debug, Default)]
+pub(super) struct Tables {
+    rendered: Vec<String>,
+}
+
+impl Tables {
+    /// Swap every outermost data table in `html` for a placeholder. Layout
+    /// tables (nested tables, a single row or column) are left to html2md.
+    pub(super) fn extract(html: &str, max_cell_chars: usize) -> (String, Self) {
+        let mut tables = Self::default();
+        let mut out = String::with_capacity(html.len());
+        let mut copied = 0;
+        let mut depth = 0usize;
+        let mut start = 0;
+
+        for tag in RE_TABLE_TAG.captures_iter(html) {
+            let m = tag.get(0).unwrap();
+            if tag[1].is_empty() {
+                if depth == 0 {
+                    start = m.start();
+                }
+                depth += 1;
+                continue;
+            }
+            // A stray close tag.
+            if depth == 0 {
+                continue;
+            }
+            depth -= 1;
+            if depth > 0 {
+                continue;
+            }
+            if let Some(md) = render(&html[start..m.end()], max_cell_chars) {
+                out.push_str(&html[copied..start]);
+                out.push_str(&format!("<p>AGENTBOBTABLE{}</p>", tables.rendered.len()));
+                tables.rendered.push(md);
+                copied = m.end();
+            }
+        }
+        out.push_str(&html[copied..]);
+        (out, tables)
+    }
+
+    /// Replace the placeholders in `md` with the tables they stand for.
+    pub(super) fn restore(&self, md: &str) -> String {
+        if self.rendered.is_empty() {
+            return md.to_string();
+        }
+        RE_PLACEHOLDER
+            .replace_all(md, |caps: &regex::Captures| {
+                caps[1]
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|n| self.rendered.get(n))
+                    .map_or_else(|| caps[0].to_string(), |t| format!("\n{t}\n"))
+            })
+            .into_owned()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Align {
+    None,
+    Left,
+    Center,
+    Right,
+}
+
+struct Cell {
+    text: String,
+    header: bool,
+    align: Align,
+}
+
+/// The markdown for one `<table>` element, or `None` for layout tables.
+fn render(table_html: &str, max_cell_chars: usize) -> Option<String> {
+    let doc = Html::parse_fragment(table_html);
+    let table = doc.select(&SEL_TABLE).next()?;
+    if table.select(&SEL_TABLE).nth(1).is_some() {
+        return None;
+    }
+
+    let rows: Vec<Vec<Cell>> = table
+        .select(&SEL_ROW)
+        .map(|tr| row_cells(tr, max_cell_chars))
+        .filter(|cells| !cells.is_empty())
+        .collect();
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    if rows.len() < 2 || columns < 2 {
+        return None;
+    }
+
+    let mut out = String::new();
+    if let Some(caption) = table.select(&SEL_CAPTION).next() {
+        let caption = collapse(&caption.text().collect::<String>());
+        if !caption.is_empty() {
+            out.push_str(&format!("**{caption}**\n\n"));
+        }
+    }
+
+    // GFM needs a header row; without `<th>`s the first row has to serve.
+    let header = &rows[0];
+    let body = &rows[1..];
+    let align_row = if header.iter().any(|c| c.header) {
+        body.first().unwrap_or(header)
+    } else {
+        header
+    };
+
+    push_row(&mut out, header, columns);
+    out.push('|');
+    for col in 0..columns {
+        let align = header
+            .get(col)
+            .map(|c| c.align)
+            .filter(|&a| a != Align::None)
+            .or_else(|| align_row.get(col).map(|c| c.align))
+            .unwrap_or(Align::None);
+        out.push_str(match align {
+            Align::None => " --- |",
+            Align::Left => " :-- |",
+            Align::Center => " :-: |",
+            Align::Right => " --: |",
+        });
+    }
+    out.push('\n');
+    for row in body {
+        push_row(&mut out, row, columns);
+    }
+    Some(out)
+}
+
+/// The row's cells, with a `colspan` cell followed by empty ones to keep
+/// the columns lined up.
+fn row_cells(tr: ElementRef, max_cell_chars: usize) -> Vec<Cell> {
+    let mut cells = Vec::new();
+    for cell in tr.children().filter_map(ElementRef::wrap) {
+        let name = cell.value().name();
+        if name != "td" && name != "th" {
+            continue;
+        }
+        let align = cell
+            .value()
+            .attr("align")
+            .map(str::to_string)
+            .or_else(|| {
+                let style = cell.value().attr("style")?;
+                Some(RE_TEXT_ALIGN.captures(style)?[1].to_string())
+            })
+            .map_or(Align::None, |a| match a.to_ascii_lowercase().as_str() {
+                "left" => Align::Left,
+                "center" => Align::Center,
+                "right" => Align::Right,
+                _ => Align::None,
+            });
+        let span = cell
+            .value()
+            .attr("colspan")
+            .and_then(|s| s.trim().parse::<usize>().ok())
+            .unwrap_or(1)
+            .clamp(1, 20);
+
+        cells.push(Cell {
+            text: cell_text(&cell.inner_html(), max_cell_chars),
+            header: name == "th",
+            align,
+        });
+        for _ in 1..span {
+            cells.push(Cell {
+                text: String::new(),
+                header: name == "th",
+                align: Align::None,
+            });
+        }
+    }
+    cells
+}
+
+/// A cell's markdown on one line, pipes escaped, cut to `max_chars`.
+fn cell_text(inner_html: &str, max_chars: usize) -> String {
+    let text = collapse(&parse_html(inner_html))
+        .replace("\\|", "|")
+        .replace('|', "\\|");
+    if text.chars().count() <= max_chars {
+        return text;
+    }
+    let mut cut: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+    // Don't leave a dangling escape.
+    if cut.ends_with('\\') {
+        cut.pop();
+    }
+    cut.push('…');
+    cut
+}
+
+fn push_row(out: &mut String, row: &[Cell], columns: usize) {
+    out.push('|');
+    for col in 0..columns {
+        out.push(' ');
+        out.push_str(row.get(col).map_or("", |c| c.text.as_str()));
+        out.push_str(" |");
+    }
+    out.push('\n');
+}
+
+fn collapse(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,787 @@
+//! Pipeline tests against canned responses; nothing here touches the network.
+
+use super::*;
+use std::sync::Mutex;
+
+/// Serves canned responses by URL; anything else is a 404.
+#[derive(Default)]
+pub(super) struct FakeFetcher {
+    responses: Mutex<HashMap<String, FetchedResponse>>,
+    stalled: Mutex<HashSet<String>>,
+    requested: Mutex<Vec<String>>,
+}
+
+impl FakeFetcher {
+    pub(super) fn with(self, url: &str, status: u16, content_type: &str, body: &[u8]) -> Self {
+        self.responses.lock().unwrap().insert(
+            url.to_string(),
+            FetchedResponse {
+                status,
+                content_type: Some(content_type.to_string()),
+                body: body.to_vec(),
+                final_url: url.to_string(),
+                ..Default::default()
+            },
+        );
+        self
+    }
+
+    pub(super) fn html(self, url: &str, body: &str) -> Self {
+        self.with(url, 200, "text/html; charset=utf-8", body.as_bytes())
+    }
+
+    /// Every page URL fetched so far, in request order; robots.txt lookups are left out.
+    pub(super) fn requested(&self) -> Vec<String> {
+        let requested = self.requested.lock().unwrap();
+        requested
+            .iter()
+            .filter(|url| !url.ends_with("/robots.txt"))
+            .cloned()
+            .collect()
+    }
+
+    /// Requests for `url` never answer.
+    pub(super) fn stall(self, url: &str) -> Self {
+        self.stalled.lock().unwrap().insert(url.to_string());
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl Fetcher for FakeFetcher {
+    async fn fetch(&self, req: FetchRequest) -> Result<FetchedResponse> {
+        self.requested.lock().unwrap().push(req.url.clone());
+        if self.stalled.lock().unwrap().contains(&req.url) {
+            std::future::pending::<()>().await;
+        }
+        // Fragments never reach a server.
+        let path = req.url.split('#').next().unwrap_or_default();
+        let resp = self.responses.lock().unwrap().get(path).cloned();
+        let mut resp = resp.unwrap_or_else(|| FetchedResponse {
+            status: 404,
+            content_type: Some("text/plain".to_string()),
+            final_url: req.url.clone(),
+            ..Default::default()
+        });
+        resp.body.truncate(req.max_body_bytes);
+        Ok(resp)
+    }
+}
+
+/// Serves `app` on a free local port; returns its base URL.
+pub(super) async fn serve(app: axum::Router) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{addr}")
+}
+
+/// A bare HTTP server that sends `chunks` HTML chunks of `chunk_bytes`, `gap` apart.
+pub(super) async fn trickle(chunks: usize, chunk_bytes: usize, gap: Duration) -> String {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut request = [0u8; 4096];
+                let _ = socket.read(&mut request).await;
+                let head = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\ncontent-length: {}\r\n\r\n",
+                    chunks * chunk_bytes
+                );
+                socket.write_all(head.as_bytes()).await?;
+                for i in 0..chunks {
+                    if i > 0 {
+                        tokio::time::sleep(gap).await;
+                    }
+                    socket.write_all(&vec![b'x'; chunk_bytes]).await?;
+                }
+                std::io::Result::Ok(())
+            });
+        }
+    });
+    format!("http://{addr}/")
+}
+
+/// A fresh, empty directory under the system temp dir.
+pub(super) fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("agent-bob-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+/// Defaults minus the per-host rate limit, which only slows tests down.
+pub(super) fn test_config() -> LlmCleanConfig {
+    LlmCleanConfig {
+        per_host_requests_per_sec: None,
+        ..Default::default()
+    }
+}
+
+/// An article with `paragraphs` paragraphs of plain English prose.
+pub(super) fn article(title: &str, paragraphs: usize) -> String {
+    let body: String = (0..paragraphs)
+        .map(|i| {
+            format!(
+                "<p>Paragraph {i} explains how the borrow checker tracks ownership \
+                 of values, why references must not outlive the data they point to, \
+                 and how lifetimes make those rules visible in function signatures.</p>"
+            )
+        })
+        .collect();
+    format!(
+        "<html><head><title>{title}</title></head><body><main><h1>{title}</h1>{body}</main></body></html>"
+    )
+}
+
+/// The pages `urls` produce, and the reason each dropped URL was dropped.
+pub(super) async fn fetch_with(
+    fetcher: impl Into<Arc<FakeFetcher>>,
+    urls: &[&str],
+    cfg: &LlmCleanConfig,
+) -> (Vec<MdPage>, Vec<(String, DropReason)>) {
+    let drops = Arc::new(Mutex::new(Vec::new()));
+    let seen = drops.clone();
+    let hooks = SearchHooks {
+        fetcher: Some(fetcher.into()),
+        progress: Some(Arc::new(move |event| {
+            if let CrawlEvent::PageDropped { url, reason } = event {
+                seen.lock().unwrap().push((url, reason));
+            }
+        })),
+        ..Default::default()
+    };
+    let urls: Vec<String> = urls.iter().map(|u| u.to_string()).collect();
+    let pages = fetch_urls(&urls, cfg, &hooks).await.unwrap();
+    let drops = drops.lock().unwrap().clone();
+    (pages, drops)
+}
+
+#[tokio::test]
+async fn drops_non_success_status() {
+    let url = "https://example.com/gone";
+    let fetcher = FakeFetcher::default().with(url, 410, "text/html", article("Gone", 5).as_bytes());
+    let (pages, drops) = fetch_with(fetcher, &[url], &test_config()).await;
+    assert!(pages.is_empty());
+    assert_eq!(drops, vec![(url.to_string(), DropReason::NonSuccessStatus)]);
+}
+
+#[tokio::test]
+async fn keeps_non_success_status_when_allowed() {
+    let url = "https://example.com/gone";
+    let fetcher = FakeFetcher::default().with(url, 410, "text/html", article("Gone", 5).as_bytes());
+    let cfg = LlmCleanConfig {
+        drop_non_success_status: false,
+        ..test_config()
+    };
+    let (pages, _) = fetch_with(fetcher, &[url], &cfg).await;
+    assert_eq!(pages.len(), 1);
+    assert_eq!(pages[0].status, 410);
+}
+
+#[tokio::test]
+async fn drops_non_html_content_type() {
+    let url = "https://example.com/archive.bin";
+    let fetcher = FakeFetcher::default().with(
+        url,
+        200,
+        "application/octet-stream",
+        article("Binary", 5).as_bytes(),
+    );
+    let (pages, drops) = fetch_with(fetcher, &[url], &test_config()).await;
+    assert!(pages.is_empty());
+    assert_eq!(drops, vec![(url.to_string(), DropReason::NotHtml)]);
+}
+
+#[tokio::test]
+async fn keeps_any_content_type_when_not_required() {
+    let url = "https://example.com/archive.bin";
+    let fetcher = FakeFetcher::default().with(
+        url,
+        200,
+        "application/octet-stream",
+        article("Binary", 5).as_bytes(),
+    );
+    let cfg = LlmCleanConfig {
+        require_html_content_type: false,
+        ..test_config()
+    };
+    let (pages, _) = fetch_with(fetcher, &[url], &cfg).await;
+    assert_eq!(pages.len(), 1);
+}
+
+#[tokio::test]
+async fn decodes_declared_charset() {
+    let url = "https://example.com/cafe";
+    let html = article("Café au lait", 5);
+    let (latin1, _, _) = encoding_rs::WINDOWS_1252.encode(&html);
+    let fetcher = FakeFetcher::default().with(url, 200, "text/html; charset=windows-1252", &latin1);
+    let (pages, _) = fetch_with(fetcher, &[url], &test_config()).await;
+    assert_eq!(pages.len(), 1);
+    assert!(pages[0].markdown.contains("Café au lait"));
+    assert!(!pages[0].markdown.contains('\u{FFFD}'));
+}
+
+#[tokio::test]
+async fn decodes_meta_charset() {
+    let url = "https://example.com/cafe";
+    let html =
+        article("Café au lait", 5).replace("<head>", r#"<head><meta charset="windows-1252">"#);
+    let (latin1, _, _) = encoding_rs::WINDOWS_1252.encode(&html);
+    let fetcher = FakeFetcher::default().with(url, 200, "text/html", &latin1);
+    let (pages, _) = fetch_with(fetcher, &[url], &test_config()).await;
+    assert_eq!(pages.len(), 1);
+    assert!(pages[0].markdown.contains("Café au lait"));
+}
+
+#[tokio::test]
+async fn truncates_long_pages() {
+    let url = "https://example.com/long";
+    let fetcher = FakeFetcher::default().html(url, &article("Long", 200));
+    let cfg = LlmCleanConfig {
+        max_md_chars: 4_000,
+        ..test_config()
+    };
+    let (pages, _) = fetch_with(fetcher, &[url], &cfg).await;
+    assert_eq!(pages.len(), 1);
+    let md = &pages[0].markdown;
+    assert!(md.trim_end().ends_with("[...truncated...]"));
+    assert!(md.chars().count() <= cfg.max_md_chars + "\n\n[...truncated...]\n".len());
+    // Cut at a paragraph, not mid-sentence.
+    let body = md
+        .trim_end()
+        .trim_end_matches("[...truncated...]")
+        .trim_end();
+    assert!(body.ends_with("function signatures."), "{body}");
+}
+
+#[tokio::test]
+async fn caps_body_at_max_html_bytes() {
+    let url = "https://example.com/huge";
+    let fetcher = FakeFetcher::default().html(url, &article("Huge", 400));
+    let cfg = LlmCleanConfig {
+        max_html_bytes: config::MIN_HTML_BYTES,
+        ingest_pdfs: false,
+        ..test_config()
+    };
+    let (pages, _) = fetch_with(fetcher, &[url], &cfg).await;
+    assert_eq!(pages.len(), 1);
+    assert!(pages[0].markdown.len() < config::MIN_HTML_BYTES);
+}
+
+#[test]
+fn truncation_backs_up_before_a_fence_opened_in_the_window() {
+    let prose = "word ".repeat(184);
+    let code = "let x = 1;\n\n".repeat(20);
+    let md = format!("{prose}\n\n```rust\n{code}```\n");
+    let out = truncate_at_boundary(&md, 1_000);
+    assert_eq!(out, prose.trim_end());
+}
+
+#[test]
+fn truncation_closes_a_fence_opened_before_the_window() {
+    let code = "let x = 1;\n\n".repeat(200);
+    let md = format!("Intro.\n\n```rust\n{code}```\n");
+    let out = truncate_at_boundary(&md, 1_000);
+    assert!(out.starts_with("Intro.\n\n```rust\n"));
+    assert!(out.ends_with("\n```"), "{out}");
+    assert_eq!(open_fence_start(&out), None);
+    assert!(out.chars().count() <= 1_000 + "\n```".len());
+}
+
+#[test]
+fn truncation_hard_cuts_a_single_giant_paragraph() {
+    let md = "ownership ".repeat(1_000);
+    let out = truncate_at_boundary(&md, 1_000);
+    assert_eq!(out.chars().count(), 1_000);
+    assert!(md.starts_with(&out));
+}
+
+#[test]
+fn truncation_keeps_multibyte_characters_whole() {
+    let md = "日本語のテキスト".repeat(500);
+    let out = truncate_at_boundary(&md, 1_001);
+    assert_eq!(out.chars().count(), 1_001);
+    assert!(md.starts_with(&out));
+}
+
+#[test]
+fn truncation_leaves_short_text_alone() {
+    let md = "# Title\n\nShort.";
+    assert_eq!(truncate_at_boundary(md, 1_000), md);
+}
+
+/// A page with `md` as its cleaned content, finished as a crawl would.
+pub(super) fn md_page(md: &str) -> MdPage {
+    let cfg = LlmCleanConfig {
+        max_md_chars: usize::MAX,
+        ..test_config()
+    };
+    let page = CleanPage {
+        query: "test".to_string(),
+        url: "https://example.com/page".to_string(),
+        final_url: "https://example.com/page".to_string(),
+        section: None,
+        client_redirect: false,
+        extraction: Extraction::Content,
+        status: 200,
+        title: None,
+        snippet: None,
+        meta: PageMeta::default(),
+        via: None,
+        md: md.to_string(),
+    };
+    let tokenizer = SearchHooks::default().tokenizer(&cfg);
+    finish_page(page, &cfg, tokenizer.as_ref())
+}
+
+/// Deterministic xorshift, for property tests without a dependency.
+pub(super) struct Rng(u64);
+
+impl Rng {
+    pub(super) fn new(seed: u64) -> Self {
+        Self(seed.max(1))
+    }
+
+    pub(super) fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n.max(1) as u64) as usize
+    }
+
+    pub(super) fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.below(items.len())]
+    }
+}
+
+/// A navigation hub: a short intro and a long list of topic links.
+fn link_hub(links: usize) -> String {
+    let items: String = (0..links)
+        .map(|i| format!(r#"<li><a href="/topics/{i}">Topic number {i}</a></li>"#))
+        .collect();
+    format!(
+        "<html><head><title>All topics</title></head><body><main><h1>All topics</h1>\
+         <p>Browse every topic.</p><ul>{items}</ul></main></body></html>"
+    )
+}
+
+#[tokio::test]
+async fn drops_link_hubs_and_keeps_articles() {
+    let (hub, post) = ("https://example.com/topics", "https://example.com/post");
+    let fetcher = FakeFetcher::default()
+        .html(hub, &link_hub(300))
+        .html(post, &article("Ownership", 8));
+    let (pages, drops) = fetch_with(fetcher, &[hub, post], &test_config()).await;
+    assert_eq!(pages.len(), 1);
+    assert_eq!(pages[0].url, post);
+    assert_eq!(drops, vec![(hub.to_string(), DropReason::LowQuality)]);
+}
+
+#[tokio::test]
+async fn keeps_link_hubs_without_a_quality_gate() {
+    let hub = "https://example.com/topics";
+    let fetcher = FakeFetcher::default().html(hub, &link_hub(300));
+    let cfg = LlmCleanConfig {
+        max_link_density: 1.0,
+        min_paragraphs: 0,
+        ..test_config()
+    };
+    let (pages, _) = fetch_with(fetcher, &[hub], &cfg).await;
+    assert_eq!(pages.len(), 1);
+}
+
+#[test]
+fn link_density_counts_link_text() {
+    assert_eq!(link_density(""), 0.0);
+    assert_eq!(link_density("[a](b)"), 1.0);
+    let hub = "- [One](/1)\n- [Two](/2)\n- [Three](/3)\n";
+    assert!(link_density(hub) > 0.8);
+    let prose = "Ownership rules are checked at compile time, see [the book](/book).";
+    assert!(link_density(prose) < 0.4);
+}
+
+#[test]
+fn prose_paragraphs_skip_link_lists() {
+    let md = "- [One](/1)\n- [Two](/2)\n\n\
+              Ownership rules are checked at compile time by the borrow checker, always.\n\n\
+              ```rust\nlet x = 1;\n```";
+    assert_eq!(prose_paragraphs(md), 2);
+}
+
+/// A hero image drawn as inline SVG, and responsive images with `srcset`.
+const IMAGE_FIXTURE: &str = r##"<main>
+<h1>Release notes</h1>
+<svg class="hero" viewBox="0 0 100 100" xmlns="http://www.w3.org/2000/svg">
+  <defs><linearGradient id="g"><stop offset="0" stop-color="#f00"/></linearGradient></defs>
+  <path d="M0 0L100 100Z"/><text x="10" y="20">SVG LABEL</text>
+</svg>
+<p>The release makes the borrow checker faster.</p>
+<img src="/hero.png" alt="Hero shot"
+     srcset="/hero-480.png 480w, /hero-800.png 800w"
+     sizes="(max-width: 600px) 480px, 800px">
+<picture>
+  <source srcset="/photo.webp 1x, /photo@2x.webp 2x" type="image/webp">
+  <img src="/photo.png" alt="Team photo" data-srcset="/photo-lazy.png 2x">
+</picture>
+<p>Fonts: <a href="data:font/woff2;base64,d09GMgABAAAAA">font</a>,
+   blob data:application/octet-stream;base64,QUJDREVGR0hJSktMTU5PUA== inline.</p>
+</main>"##;
+
+#[test]
+fn drops_inline_svg() {
+    let base = reqwest::Url::parse("https://example.com/news/").unwrap();
+    let md = content_to_markdown(IMAGE_FIXTURE, Some(&base), &test_config());
+    assert!(md.contains("The release makes the borrow checker faster."));
+    for svg in [
+        "SVG LABEL",
+        "M0 0",
+        "linearGradient",
+        "stop-color",
+        "viewBox",
+    ] {
+        assert!(!md.contains(svg), "{svg} left in:\n{md}");
+    }
+}
+
+#[test]
+fn drops_srcset_and_sizes() {
+    let base = reqwest::Url::parse("https://example.com/news/").unwrap();
+    let md = content_to_markdown(IMAGE_FIXTURE, Some(&base), &test_config());
+    assert!(md.contains("Hero shot"), "{md}");
+    assert!(md.contains("https://example.com/hero.png"), "{md}");
+    for soup in [
+        "480w",
+        "hero-800",
+        "max-width",
+        "photo@2x",
+        "1x",
+        "photo-lazy",
+    ] {
+        assert!(!md.contains(soup), "{soup} left in:\n{md}");
+    }
+}
+
+#[test]
+fn drops_data_uris_of_any_type() {
+    let base = reqwest::Url::parse("https://example.com/news/").unwrap();
+    let md = content_to_markdown(IMAGE_FIXTURE, Some(&base), &test_config());
+    for blob in ["base64", "d09GMg", "QUJDREVG", "data:"] {
+        assert!(!md.contains(blob), "{blob} left in:\n{md}");
+    }
+    assert!(
+        md.contains("(data omitted)") || md.contains("[data omitted]"),
+        "{md}"
+    );
+}
+
+#[tokio::test]
+async fn cancelling_returns_promptly_with_finished_pages() {
+    let fast = "https://example.com/fast";
+    let slow = ["https://example.com/slow-1", "https://example.com/slow-2"];
+    let fetcher = FakeFetcher::default()
+        .html(fast, &article("Fast", 5))
+        .stall(slow[0])
+        .stall(slow[1]);
+    let cancel = CancellationToken::new();
+    let token = cancel.clone();
+    let drops = Arc::new(Mutex::new(Vec::new()));
+    let seen = drops.clone();
+    let hooks = SearchHooks {
+        fetcher: Some(Arc::new(fetcher)),
+        cancel: Some(cancel),
+        progress: Some(Arc::new(move |event| match event {
+            CrawlEvent::FetchCompleted { .. } => token.cancel(),
+            CrawlEvent::PageDropped { url, reason } => seen.lock().unwrap().push((url, reason)),
+            _ => {}
+        })),
+        ..Default::default()
+    };
+    let urls = [fast, slow[0], slow[1]].map(String::from);
+
+    let started = Instant::now();
+    let pages = tokio::time::timeout(
+        Duration::from_secs(5),
+        fetch_urls(&urls, &test_config(), &hooks),
+    )
+    .await
+    .expect("cancelled fetch should not hang")
+    .unwrap();
+
+    assert!(
+        started.elapsed() < Duration::from_secs(1),
+        "took {:?}",
+        started.elapsed()
+    );
+    assert_eq!(
+        pages.iter().map(|p| p.url.as_str()).collect::<Vec<_>>(),
+        [fast]
+    );
+    let mut drops = drops.lock().unwrap().clone();
+    drops.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(drops, slow.map(|u| (u.to_string(), DropReason::Cancelled)));
+}
+
+#[tokio::test]
+async fn revalidates_stale_cache_entries_with_etag() {
+    use axum::http::{HeaderMap, StatusCode, header};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const ETAG: &str = "\"v1\"";
+    let (full, not_modified) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+    let (full_hits, not_modified_hits) = (full.clone(), not_modified.clone());
+    let body = article("Cached", 5);
+    let app = axum::Router::new().route(
+        "/page",
+        axum::routing::get(move |headers: HeaderMap| async move {
+            if headers
+                .get(header::IF_NONE_MATCH)
+                .is_some_and(|v| v == ETAG)
+            {
+                not_modified_hits.fetch_add(1, Ordering::SeqCst);
+                return (
+                    StatusCode::NOT_MODIFIED,
+                    [(header::ETAG, ETAG)],
+                    String::new(),
+                );
+            }
+            full_hits.fetch_add(1, Ordering::SeqCst);
+            (StatusCode::OK, [(header::ETAG, ETAG)], body)
+        }),
+    );
+    let url = format!("{}/page", serve(app).await);
+
+    let dir = temp_dir("revalidate");
+    let cfg = LlmCleanConfig {
+        cache_dir: Some(dir.clone()),
+        cache_ttl_secs: 0,
+        respect_robots_txt: false,
+        ..test_config()
+    };
+    let urls = [url];
+    let first = fetch_urls(&urls, &cfg, &SearchHooks::default())
+        .await
+        .unwrap();
+    // The TTL has one-second resolution; wait for the entry to go stale.
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    let second = fetch_urls(&urls, &cfg, &SearchHooks::default())
+        .await
+        .unwrap();
+    let _ = std::fs::remove_dir_all(&dir);
+
+    assert_eq!(full.load(Ordering::SeqCst), 1);
+    assert_eq!(not_modified.load(Ordering::SeqCst), 1);
+    assert_eq!(first.len(), 1);
+    assert_eq!(second.len(), 1);
+    assert_eq!(first[0].markdown, second[0].markdown);
+    assert!(second[0].markdown.contains("borrow checker"));
+}
+
+fn idle_request(url: &str) -> FetchRequest {
+    FetchRequest {
+        url: url.to_string(),
+        headers: Vec::new(),
+        max_body_bytes: usize::MAX,
+    }
+}
+
+#[tokio::test]
+async fn idle_timeout_aborts_stalled_bodies() {
+    let url = trickle(3, 1024, Duration::from_secs(5)).await;
+    let cfg = LlmCleanConfig {
+        read_idle_timeout_secs: Some(1),
+        ..test_config()
+    };
+    let fetcher = ReqwestFetcher::new(&cfg).unwrap();
+
+    let started = Instant::now();
+    let err = fetcher.fetch(idle_request(&url)).await.unwrap_err();
+    assert!(
+        started.elapsed() < Duration::from_secs(3),
+        "took {:?}",
+        started.elapsed()
+    );
+    assert!(
+        format!("{err:#}").contains("failed reading body"),
+        "{err:#}"
+    );
+}
+
+#[tokio::test]
+async fn idle_timeout_lets_steady_bodies_finish() {
+    // Twice the idle timeout in total, but never idle for long.
+    let url = trickle(20, 8 * 1024, Duration::from_millis(100)).await;
+    let cfg = LlmCleanConfig {
+        read_idle_timeout_secs: Some(1),
+        ..test_config()
+    };
+    let fetcher = ReqwestFetcher::new(&cfg).unwrap();
+
+    let resp = fetcher.fetch(idle_request(&url)).await.unwrap();
+    assert_eq!(resp.status, 200);
+    assert_eq!(resp.body.len(), 20 * 8 * 1024);
+}
+
+/// A page with no content of its own that meta-refreshes to `target`.
+fn interstitial(target: &str) -> String {
+    format!(
+        r#"<html><head><meta http-equiv="refresh" content="0; url={target}"></head>
+<body><p>Redirecting...</p></body></html>"#
+    )
+}
+
+#[tokio::test]
+async fn follows_meta_refresh_interstitials() {
+    let (hop, target) = ("https://example.com/go", "https://example.com/article");
+    let fetcher = FakeFetcher::default()
+        .html(hop, &interstitial(target))
+        .html(target, &article("Target", 5));
+    let (pages, drops) = fetch_with(fetcher, &[hop], &test_config()).await;
+    assert!(drops.is_empty(), "{drops:?}");
+    assert_eq!(pages.len(), 1);
+    assert_eq!(pages[0].url, hop);
+    assert_eq!(pages[0].final_url, target);
+    assert!(pages[0].client_redirect);
+    assert!(pages[0].markdown.contains("borrow checker"));
+}
+
+#[tokio::test]
+async fn meta_refresh_respects_blocked_domains() {
+    let (hop, target) = ("https://example.com/go", "https://blocked.example/article");
+    let fetcher = Arc::new(
+        FakeFetcher::default()
+            .html(hop, &interstitial(target))
+            .html(target, &article("Target", 5)),
+    );
+    let cfg = LlmCleanConfig {
+        blocked_domains: vec!["blocked.example".into()],
+        ..test_config()
+    };
+    let (pages, drops) = fetch_with(fetcher.clone(), &[hop], &cfg).await;
+    assert!(pages.is_empty());
+    assert_eq!(drops, [(hop.to_string(), DropReason::BlockedDomain)]);
+    assert_eq!(fetcher.requested(), [hop]);
+}
+
+#[tokio::test]
+async fn meta_refresh_respects_allowed_domains() {
+    let (hop, target) = (
+        "https://example.com/go",
+        "https://elsewhere.example/article",
+    );
+    let fetcher = FakeFetcher::default()
+        .html(hop, &interstitial(target))
+        .html(target, &article("Target", 5));
+    let cfg = LlmCleanConfig {
+        allowed_domains: vec!["example.com".into()],
+        ..test_config()
+    };
+    let (pages, drops) = fetch_with(fetcher, &[hop], &cfg).await;
+    assert!(pages.is_empty());
+    assert_eq!(drops, [(hop.to_string(), DropReason::BlockedDomain)]);
+}
+
+#[tokio::test]
+async fn meta_refresh_skips_known_targets() {
+    let target = "https://example.com/article";
+    let hops = ["https://example.com/go-1", "https://example.com/go-2"];
+    let fetcher = Arc::new(
+        FakeFetcher::default()
+            .html(hops[0], &interstitial(target))
+            .html(hops[1], &interstitial(target))
+            .html(target, &article("Target", 5)),
+    );
+    // One at a time, so the first hop has claimed the article before the second looks.
+    let cfg = LlmCleanConfig {
+        concurrency: 1,
+        ..test_config()
+    };
+    let (pages, drops) = fetch_with(fetcher.clone(), &hops, &cfg).await;
+    assert_eq!(pages.len(), 1);
+    assert_eq!(
+        drops,
+        [(hops[1].to_string(), DropReason::CanonicalDuplicate)]
+    );
+    assert_eq!(fetcher.requested(), [hops[0], target, hops[1]]);
+}
+
+#[tokio::test]
+async fn keeps_each_linked_section_of_a_page() {
+    let page = "https://docs.example.com/guide";
+    let section = |id: &str, topic: &str| {
+        let body: String = (0..3)
+            .map(|i| {
+                format!(
+                    "<p>{topic} step {i} walks through the options the tool reads, where \
+                     it looks for them, and what happens when two of them disagree.</p>"
+                )
+            })
+            .collect();
+        format!("<h2 id=\"{id}\">{topic}</h2>{body}")
+    };
+    let html = format!(
+        "<html><body><main><h1>Guide</h1>{}{}</main></body></html>",
+        section("install", "Installing"),
+        section("configuration", "Configuring"),
+    );
+    let fetcher = FakeFetcher::default().html(page, &html);
+    let cfg = LlmCleanConfig {
+        extract_fragments: true,
+        concurrency: 1,
+        ..test_config()
+    };
+    let urls = [format!("{page}#install"), format!("{page}#configuration")];
+    let urls: Vec<&str> = urls.iter().map(String::as_str).collect();
+    let (pages, drops) = fetch_with(fetcher, &urls, &cfg).await;
+
+    assert!(drops.is_empty(), "{drops:?}");
+    assert_eq!(pages.len(), 2);
+    assert!(pages[0].markdown.contains("Installing step"));
+    assert!(!pages[0].markdown.contains("Configuring step"));
+    assert!(pages[1].markdown.contains("Configuring step"));
+    assert!(!pages[1].markdown.contains("Installing step"));
+}
+
+#[tokio::test]
+async fn every_started_fetch_finishes_even_when_robots_disallow() {
+    let (open, private) = (
+        "https://example.com/docs",
+        "https://example.com/private/notes",
+    );
+    let fetcher = FakeFetcher::default()
+        .with(
+            "https://example.com/robots.txt",
+            200,
+            "text/plain",
+            b"User-agent: *\nDisallow: /private\n",
+        )
+        .html(open, &article("Docs", 5))
+        .html(private, &article("Private", 5));
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let seen = events.clone();
+    let hooks = SearchHooks {
+        fetcher: Some(Arc::new(fetcher)),
+        progress: Some(Arc::new(move |event| seen.lock().unwrap().push(event))),
+        ..Default::default()
+    };
+    let urls = [open, private].map(String::from);
+    let pages = fetch_urls(&urls, &test_config(), &hooks).await.unwrap();
+    assert_eq!(pages.len(), 1);
+
+    let events = events.lock().unwrap();
+    let count = |pick: fn(&CrawlEvent) -> bool| events.iter().filter(|e| pick(e)).count();
+    let started = count(|e| matches!(e, CrawlEvent::FetchStarted { .. }));
+    let finished = count(|e| {
+        matches!(
+            e,
+            CrawlEvent::FetchCompleted { .. } | CrawlEvent::FetchFailed { .. }
+        )
+    });
+    assert_eq!((started, finished), (1, 1));
+    assert!(events.iter().any(|e| matches!(
+        e,
+        CrawlEvent::PageDropped { url, reason: DropReason::RobotsDisallowed } if url == private
+    )));
+}
@@ -0,0 +1,118 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+// The pre-tokenization split BPE tokenizers apply before merging: words with
+// their leading space, short digit groups, punctuation runs and whitespace.
+static RE_PIECE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r" ?\p{L}+| ?\p{N}{1,3}| ?[^\s\p{L}\p{N}]+|\s+").unwrap());
+
+/// Counts tokens the way a model's tokenizer would. Implement this and set it
+/// on `SearchHooks::tokenizer` to budget with an exact vocabulary.
+pub trait Tokenizer: Send + Sync {
+    fn count_tokens(&self, text: &str) -> usize;
+}
+
+/// Built-in token estimates, per tokenizer family.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenModel {
+    /// GPT-4 and GPT-3.5.
+    #[default]
+    Cl100k,
+    /// GPT-4o and later OpenAI models.
+    O200k,
+    /// Llama 3, Qwen and other large-vocabulary open models.
+    Llama3,
+    /// SentencePiece models with small vocabularies (Llama 2, Mistral, Gemma).
+    SentencePiece,
+}
+
+impl TokenModel {
+    /// The family a model name belongs to; unknown names get the default.
+    pub fn for_model(name: &str) -> Self {
+        let name = name.to_ascii_lowercase();
+        let name = name.rsplit('/').next().unwrap_or(&name);
+        if ["gpt-4o", "gpt-4.1", "gpt-5", "o1", "o3", "o4"]
+            .iter()
+            .any(|p| name.starts_with(p))
+        {
+            TokenModel::O200k
+        } else if ["llama3", "llama-3", "qwen", "deepseek"]
+            .iter()
+            .any(|p| name.starts_with(p))
+        {
+            TokenModel::Llama3
+        } else if ["llama2", "llama-2", "mistral", "mixtral", "gemma"]
+            .iter()
+            .any(|p| name.starts_with(p))
+        {
+            TokenModel::SentencePiece
+        } else {
+            TokenModel::Cl100k
+        }
+    }
+
+    pub fn tokenizer(self) -> Arc<dyn Tokenizer> {
+        Arc::new(EstimateTokenizer { model: self })
+    }
+
+    /// Letters a single token covers in common words, and in non-ASCII text.
+    fn chars_per_token(self) -> (usize, f32) {
+        match self {
+            TokenModel::Cl100k => (6, 1.0),
+            TokenModel::O200k => (7, 1.6),
+            TokenModel::Llama3 => (7, 1.4),
+            TokenModel::SentencePiece => (4, 1.0),
+        }
+    }
+}
+
+/// Splits like a BPE tokenizer, then charges each piece by length instead of
+/// looking it up in a vocabulary. Within about 10% on English prose and code.
+struct EstimateTokenizer {
+    model: TokenModel,
+}
+
+impl Tokenizer for EstimateTokenizer {
+    fn count_tokens(&self, text: &str) -> usize {
+        let (word, non_ascii) = self.model.chars_per_token();
+        RE_PIECE
+            .find_iter(text)
+            .map(|m| {
+                let piece = m.as_str().trim_start_matches(' ');
+                if piece.is_empty() || piece.trim().is_empty() {
+                    // Whitespace runs mostly merge into one token.
+                    return 1;
+                }
+                let ascii = piece.chars().filter(char::is_ascii).count();
+                let other = piece.chars().count() - ascii;
+                ascii.div_ceil(word) + (other as f32 / non_ascii).ceil() as usize
+            })
+            .sum()
+    }
+}
+
+/// The longest prefix of `s`, in chars, that fits in `max_tokens`.
+pub(super) fn chars_within(tokenizer: &dyn Tokenizer, s: &str, max_tokens: usize) -> usize {
+    // `ends[n - 1]` is the byte offset just past the first `n` chars.
+    let ends: Vec<usize> = s
+        .char_indices()
+        .map(|(i, _)| i)
+        .skip(1)
+        .chain([s.len()])
+        .collect();
+    let prefix = |n: usize| if n == 0 { "" } else { &s[..ends[n - 1]] };
+
+    let (mut lo, mut hi) = (0, ends.len());
+    while lo < hi {
+        let mid = (lo + hi).div_ceil(2);
+        if tokenizer.count_tokens(prefix(mid)) <= max_tokens {
+            lo = mid;
+        } else {
+            hi = mid - 1;
+        }
+    }
+    lo
+}